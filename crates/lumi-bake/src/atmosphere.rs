@@ -0,0 +1,390 @@
+use lumi_core::{
+    util::DeviceExt, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BufferBindingType, BufferInitDescriptor, BufferUsages, ComputePipeline,
+    ComputePipelineDescriptor, Device, Extent3d, FilterMode, Maintain, PipelineLayoutDescriptor,
+    Queue, SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderStages, StorageTextureAccess,
+    Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureViewDimension,
+};
+use lumi_util::{bytemuck, math::Vec3};
+use tracing_log::log;
+
+use crate::BakedEnvironment;
+
+/// The parameters of the atmosphere of a planet, rendered by
+/// [`BakedEnvironment::from_atmosphere`].
+///
+/// Distances are in kilometers and coefficients are per kilometer, the defaults match the
+/// atmosphere of the earth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Atmosphere {
+    pub rayleigh_scattering: Vec3,
+    /// The height over which the density of rayleigh scattering falls off by a factor of `e`.
+    pub rayleigh_scale_height: f32,
+    pub mie_scattering: Vec3,
+    pub mie_absorption: Vec3,
+    /// The height over which the density of mie scattering falls off by a factor of `e`.
+    pub mie_scale_height: f32,
+    /// How much mie scattering favors forward directions, between `-1` and `1`.
+    pub mie_asymmetry: f32,
+    pub ozone_absorption: Vec3,
+    /// The height of the center of the ozone layer.
+    pub ozone_center: f32,
+    pub ozone_width: f32,
+    pub ground_albedo: Vec3,
+    pub bottom_radius: f32,
+    pub top_radius: f32,
+    /// The height of the viewer above the ground.
+    pub height: f32,
+    pub sun_angular_radius: f32,
+    /// The intensity of the sun, in the units of the baked environment.
+    pub sun_intensity: f32,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            rayleigh_scattering: Vec3::new(5.802e-3, 13.558e-3, 33.1e-3),
+            rayleigh_scale_height: 8.0,
+            mie_scattering: Vec3::splat(3.996e-3),
+            mie_absorption: Vec3::splat(4.4e-3),
+            mie_scale_height: 1.2,
+            mie_asymmetry: 0.8,
+            ozone_absorption: Vec3::new(0.650e-3, 1.881e-3, 0.085e-3),
+            ozone_center: 25.0,
+            ozone_width: 30.0,
+            ground_albedo: Vec3::splat(0.3),
+            bottom_radius: 6360.0,
+            top_radius: 6460.0,
+            height: 0.2,
+            sun_angular_radius: 0.00465,
+            sun_intensity: 10.0,
+        }
+    }
+}
+
+impl Atmosphere {
+    /// Returns the uniform data of the atmosphere, matching `Atmosphere` in `atmosphere.wgsl`.
+    fn raw(&self, sun_direction: Vec3) -> [f32; 24] {
+        let pair = |v: Vec3, w: f32| [v.x, v.y, v.z, w];
+        let sun_direction = sun_direction.normalize_or_zero();
+
+        let rows = [
+            pair(self.rayleigh_scattering, -1.0 / self.rayleigh_scale_height),
+            pair(self.mie_scattering, -1.0 / self.mie_scale_height),
+            pair(self.mie_absorption, self.mie_asymmetry),
+            pair(self.ozone_absorption, self.ozone_center),
+            pair(self.ground_albedo, self.ozone_width),
+            pair(sun_direction, self.sun_intensity),
+        ];
+
+        let mut raw = [0.0; 24];
+        for (i, row) in rows.into_iter().enumerate() {
+            raw[i * 4..i * 4 + 4].copy_from_slice(&row);
+        }
+
+        raw[20..].copy_from_slice(&[
+            self.bottom_radius,
+            self.top_radius,
+            self.sun_angular_radius,
+            self.height,
+        ]);
+
+        raw
+    }
+}
+
+struct AtmospherePass {
+    bind_group_layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl AtmospherePass {
+    fn new(
+        device: &Device,
+        module: &ShaderModule,
+        entry_point: &str,
+        entries: &[BindGroupLayoutEntry],
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Environment Atmosphere Pipeline"),
+            layout: Some(&pipeline_layout),
+            module,
+            entry_point,
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    fn bind_group(&self, device: &Device, entries: &[BindGroupEntry]) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries,
+        })
+    }
+}
+
+impl BakedEnvironment {
+    pub const TRANSMITTANCE_SIZE: Extent3d = Extent3d {
+        width: 256,
+        height: 64,
+        depth_or_array_layers: 1,
+    };
+    pub const MULTISCATTERING_SIZE: Extent3d = Extent3d {
+        width: 32,
+        height: 32,
+        depth_or_array_layers: 1,
+    };
+
+    fn create_lut(device: &Device, size: Extent3d) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("Environment Atmosphere Lut"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba16Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        })
+    }
+
+    /// Bakes an environment by rendering the sky of `atmosphere`, lit by a sun in
+    /// `sun_direction`, the direction towards the sun.
+    ///
+    /// The sky is rendered into an equirectangular image `sky_size * 2` pixels wide and baked
+    /// with [`BakedEnvironment::from_eq`], which limits the stored radiance to the range `0` to
+    /// `4`.
+    pub fn from_atmosphere(
+        device: &Device,
+        queue: &Queue,
+        atmosphere: &Atmosphere,
+        sun_direction: Vec3,
+        indirect_size: u32,
+        irradiance_size: u32,
+        sky_size: u32,
+    ) -> Self {
+        let transmittance = Self::create_lut(device, Self::TRANSMITTANCE_SIZE);
+        let transmittance_view = transmittance.create_view(&Default::default());
+
+        let multiscattering = Self::create_lut(device, Self::MULTISCATTERING_SIZE);
+        let multiscattering_view = multiscattering.create_view(&Default::default());
+
+        let eq_texture = device.create_texture(&TextureDescriptor {
+            label: Some("Environment"),
+            size: Extent3d {
+                width: sky_size * 2,
+                height: sky_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba16Uint,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        });
+        let eq_view = eq_texture.create_view(&Default::default());
+
+        let lut_sampler = device.create_sampler(&SamplerDescriptor {
+            label: None,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let atmosphere_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&atmosphere.raw(sun_direction)),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let uniform_entry = BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage_entry = |binding, format| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = BindGroupLayoutEntry {
+            binding: 3,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        let module = device.create_shader_module(lumi_core::include_wgsl!("atmosphere.wgsl"));
+
+        let transmittance_pass = AtmospherePass::new(
+            device,
+            &module,
+            "transmittance",
+            &[uniform_entry, storage_entry(1, TextureFormat::Rgba16Float)],
+        );
+        let multiscattering_pass = AtmospherePass::new(
+            device,
+            &module,
+            "multiscattering",
+            &[
+                uniform_entry,
+                texture_entry(2),
+                sampler_entry,
+                storage_entry(4, TextureFormat::Rgba16Float),
+            ],
+        );
+        let sky_pass = AtmospherePass::new(
+            device,
+            &module,
+            "sky",
+            &[
+                uniform_entry,
+                texture_entry(2),
+                sampler_entry,
+                texture_entry(5),
+                storage_entry(6, TextureFormat::Rgba16Uint),
+            ],
+        );
+
+        let transmittance_bind_group = transmittance_pass.bind_group(
+            device,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: atmosphere_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&transmittance_view),
+                },
+            ],
+        );
+        let multiscattering_bind_group = multiscattering_pass.bind_group(
+            device,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: atmosphere_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&transmittance_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(&lut_sampler),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(&multiscattering_view),
+                },
+            ],
+        );
+        let sky_bind_group = sky_pass.bind_group(
+            device,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: atmosphere_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&transmittance_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(&lut_sampler),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&multiscattering_view),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: BindingResource::TextureView(&eq_view),
+                },
+            ],
+        );
+
+        let passes = [
+            (
+                &transmittance_pass,
+                &transmittance_bind_group,
+                Self::TRANSMITTANCE_SIZE.width,
+                Self::TRANSMITTANCE_SIZE.height,
+            ),
+            (
+                &multiscattering_pass,
+                &multiscattering_bind_group,
+                Self::MULTISCATTERING_SIZE.width,
+                Self::MULTISCATTERING_SIZE.height,
+            ),
+            (&sky_pass, &sky_bind_group, sky_size * 2, sky_size),
+        ];
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+
+        // each pass reads the output of the previous ones, which wgpu synchronizes between
+        // compute passes
+        for (pass, bind_group, width, height) in passes {
+            let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+            compute_pass.set_pipeline(&pass.pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                width / Self::WORKGROUP_SIZE + 1,
+                height / Self::WORKGROUP_SIZE + 1,
+                1,
+            );
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+        device.poll(Maintain::Wait);
+
+        log::trace!("Rendered atmosphere: {}/{}", 1, 1);
+
+        Self::from_eq(
+            device,
+            queue,
+            &eq_texture,
+            indirect_size,
+            irradiance_size,
+            sky_size,
+        )
+    }
+}
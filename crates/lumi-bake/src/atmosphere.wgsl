@@ -0,0 +1,323 @@
+let PI = 3.1415926535897932384626433832795;
+
+let TRANSMITTANCE_STEPS = 40u;
+let MULTISCATTERING_DIRECTIONS = 64u;
+let MULTISCATTERING_STEPS = 20u;
+let SKY_STEPS = 32u;
+
+// must match `Atmosphere::raw`
+struct Atmosphere {
+	rayleigh_scattering: vec3<f32>,
+	rayleigh_density: f32,
+	mie_scattering: vec3<f32>,
+	mie_density: f32,
+	mie_absorption: vec3<f32>,
+	mie_asymmetry: f32,
+	ozone_absorption: vec3<f32>,
+	ozone_center: f32,
+	ground_albedo: vec3<f32>,
+	ozone_width: f32,
+	sun_direction: vec3<f32>,
+	sun_intensity: f32,
+	bottom_radius: f32,
+	top_radius: f32,
+	sun_angular_radius: f32,
+	height: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> atmosphere: Atmosphere;
+
+@group(0) @binding(1)
+var transmittance_output: texture_storage_2d<rgba16float, write>;
+
+@group(0) @binding(2)
+var transmittance_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var lut_sampler: sampler;
+
+@group(0) @binding(4)
+var multiscattering_output: texture_storage_2d<rgba16float, write>;
+
+@group(0) @binding(5)
+var multiscattering_texture: texture_2d<f32>;
+
+@group(0) @binding(6)
+var eq: texture_storage_2d<rgba16uint, write>;
+
+struct Medium {
+	rayleigh: vec3<f32>,
+	mie: vec3<f32>,
+	extinction: vec3<f32>,
+}
+
+fn sample_medium(position: vec3<f32>) -> Medium {
+	let height = max(length(position) - atmosphere.bottom_radius, 0.0);
+
+	let rayleigh_density = exp(height * atmosphere.rayleigh_density);
+	let mie_density = exp(height * atmosphere.mie_density);
+	let ozone_density = max(1.0 - abs(height - atmosphere.ozone_center) / (atmosphere.ozone_width * 0.5), 0.0);
+
+	var medium: Medium;
+	medium.rayleigh = atmosphere.rayleigh_scattering * rayleigh_density;
+	medium.mie = atmosphere.mie_scattering * mie_density;
+	medium.extinction = medium.rayleigh + medium.mie;
+	medium.extinction += atmosphere.mie_absorption * mie_density;
+	medium.extinction += atmosphere.ozone_absorption * ozone_density;
+	return medium;
+}
+
+// the distance to the nearest intersection in front of `origin` with a sphere at the center of
+// the planet, negative if there is none
+fn ray_sphere(origin: vec3<f32>, direction: vec3<f32>, radius: f32) -> f32 {
+	let b = dot(origin, direction);
+	let c = dot(origin, origin) - radius * radius;
+	let discriminant = b * b - c;
+
+	if discriminant < 0.0 {
+		return -1.0;
+	}
+
+	let near = -b - sqrt(discriminant);
+	let far = -b + sqrt(discriminant);
+
+	return select(far, near, near >= 0.0);
+}
+
+fn rayleigh_phase(cos_theta: f32) -> f32 {
+	return 3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta);
+}
+
+// the cornette-shanks phase function
+fn mie_phase(cos_theta: f32, g: f32) -> f32 {
+	let g2 = g * g;
+	let k = 3.0 / (8.0 * PI) * (1.0 - g2) / (2.0 + g2);
+	return k * (1.0 + cos_theta * cos_theta) / pow(1.0 + g2 - 2.0 * g * cos_theta, 1.5);
+}
+
+// the uv of the transmittance lut, from the distance to the center of the planet `r` and the
+// cosine of the zenith angle `mu`, see bruneton's precomputed atmospheric scattering
+fn transmittance_uv(r: f32, mu: f32) -> vec2<f32> {
+	let top2 = atmosphere.top_radius * atmosphere.top_radius;
+	let bottom2 = atmosphere.bottom_radius * atmosphere.bottom_radius;
+
+	let h = sqrt(top2 - bottom2);
+	let rho = sqrt(max(r * r - bottom2, 0.0));
+
+	let discriminant = r * r * (mu * mu - 1.0) + top2;
+	let d = max(-r * mu + sqrt(max(discriminant, 0.0)), 0.0);
+	let d_min = atmosphere.top_radius - r;
+	let d_max = rho + h;
+
+	return vec2<f32>((d - d_min) / (d_max - d_min), rho / h);
+}
+
+// the inverse of `transmittance_uv`, returns `r` in x and `mu` in y
+fn transmittance_parameters(uv: vec2<f32>) -> vec2<f32> {
+	let top2 = atmosphere.top_radius * atmosphere.top_radius;
+	let bottom2 = atmosphere.bottom_radius * atmosphere.bottom_radius;
+
+	let h = sqrt(top2 - bottom2);
+	let rho = h * uv.y;
+	let r = sqrt(rho * rho + bottom2);
+
+	let d_min = atmosphere.top_radius - r;
+	let d_max = rho + h;
+	let d = d_min + uv.x * (d_max - d_min);
+
+	var mu = 1.0;
+	if d > 0.0 {
+		mu = clamp((h * h - rho * rho - d * d) / (2.0 * r * d), -1.0, 1.0);
+	}
+
+	return vec2<f32>(r, mu);
+}
+
+// the transmittance from `position` to the top of the atmosphere along `direction`, zero if the
+// ground is in the way
+fn sample_transmittance(position: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+	let r = length(position);
+	let mu = dot(position / r, direction);
+
+	// the cosine of the zenith angle of the horizon
+	let horizon = atmosphere.bottom_radius / max(r, atmosphere.bottom_radius);
+	if mu < -sqrt(1.0 - horizon * horizon) {
+		return vec3<f32>(0.0);
+	}
+
+	let uv = transmittance_uv(r, mu);
+
+	return textureSampleLevel(transmittance_texture, lut_sampler, uv, 0.0).rgb;
+}
+
+// the light scattered more than once at `position`, per unit of scattering
+fn sample_multiscattering(position: vec3<f32>, sun_direction: vec3<f32>) -> vec3<f32> {
+	let r = length(position);
+	let mu = dot(position / r, sun_direction);
+	let height = (r - atmosphere.bottom_radius) / (atmosphere.top_radius - atmosphere.bottom_radius);
+	let uv = vec2<f32>(mu * 0.5 + 0.5, saturate(height));
+
+	return textureSampleLevel(multiscattering_texture, lut_sampler, uv, 0.0).rgb;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn transmittance(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(transmittance_output);
+
+	if i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions);
+	let parameters = transmittance_parameters(uv);
+	let r = parameters.x;
+	let mu = parameters.y;
+
+	let origin = vec3<f32>(0.0, r, 0.0);
+	let direction = vec3<f32>(sqrt(1.0 - mu * mu), mu, 0.0);
+	let distance = max(ray_sphere(origin, direction, atmosphere.top_radius), 0.0);
+	let dt = distance / f32(TRANSMITTANCE_STEPS);
+
+	var optical_depth = vec3<f32>(0.0);
+	for (var i = 0u; i < TRANSMITTANCE_STEPS; i += 1u) {
+		let position = origin + direction * (f32(i) + 0.5) * dt;
+		optical_depth += sample_medium(position).extinction * dt;
+	}
+
+	textureStore(transmittance_output, vec2<i32>(global_id.xy), vec4<f32>(exp(-optical_depth), 1.0));
+}
+
+// a direction on the unit sphere, evenly distributed with the golden spiral
+fn sphere_direction(index: u32, count: u32) -> vec3<f32> {
+	let y = 1.0 - (f32(index) + 0.5) / f32(count) * 2.0;
+	let radius = sqrt(1.0 - y * y);
+	let theta = f32(index) * PI * (3.0 - sqrt(5.0));
+
+	return vec3<f32>(cos(theta) * radius, y, sin(theta) * radius);
+}
+
+// the multiple scattering approximation from hillaire's 'a scalable and production ready sky
+// and atmosphere rendering technique'
+@compute @workgroup_size(16, 16, 1)
+fn multiscattering(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(multiscattering_output);
+
+	if i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions);
+	let mu = uv.x * 2.0 - 1.0;
+	let r = mix(atmosphere.bottom_radius, atmosphere.top_radius, uv.y);
+
+	let origin = vec3<f32>(0.0, r + 0.001, 0.0);
+	let sun_direction = vec3<f32>(sqrt(1.0 - mu * mu), mu, 0.0);
+	let isotropic_phase = 1.0 / (4.0 * PI);
+
+	var luminance = vec3<f32>(0.0);
+	var transfer = vec3<f32>(0.0);
+
+	for (var i = 0u; i < MULTISCATTERING_DIRECTIONS; i += 1u) {
+		let direction = sphere_direction(i, MULTISCATTERING_DIRECTIONS);
+
+		let ground = ray_sphere(origin, direction, atmosphere.bottom_radius);
+		let top = ray_sphere(origin, direction, atmosphere.top_radius);
+		let distance = select(max(top, 0.0), ground, ground >= 0.0);
+		let dt = distance / f32(MULTISCATTERING_STEPS);
+
+		var throughput = vec3<f32>(1.0);
+		for (var j = 0u; j < MULTISCATTERING_STEPS; j += 1u) {
+			let position = origin + direction * (f32(j) + 0.5) * dt;
+			let medium = sample_medium(position);
+			let scattering = medium.rayleigh + medium.mie;
+			let step_transmittance = exp(-medium.extinction * dt);
+			let extinction = max(medium.extinction, vec3<f32>(1e-9));
+
+			let sun = sample_transmittance(position, sun_direction) * scattering * isotropic_phase;
+			luminance += throughput * (sun - sun * step_transmittance) / extinction;
+			transfer += throughput * (scattering - scattering * step_transmittance) / extinction;
+
+			throughput *= step_transmittance;
+		}
+
+		if ground >= 0.0 {
+			let position = origin + direction * ground;
+			let normal = normalize(position);
+			let sun = sample_transmittance(position, sun_direction) * saturate(dot(normal, sun_direction));
+			luminance += throughput * sun * atmosphere.ground_albedo / PI;
+		}
+	}
+
+	// the average over the sphere, integrated with the isotropic phase function
+	luminance /= f32(MULTISCATTERING_DIRECTIONS);
+	transfer /= f32(MULTISCATTERING_DIRECTIONS);
+
+	// the sum of the infinite geometric series of scattering orders
+	let multiscattering = luminance / (1.0 - transfer);
+
+	textureStore(multiscattering_output, vec2<i32>(global_id.xy), vec4<f32>(multiscattering, 1.0));
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn sky(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(eq);
+
+	if i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions);
+	let theta = (uv.x - 0.5) * 2.0 * PI;
+	let phi = -(uv.y - 0.5) * PI;
+	let direction = vec3<f32>(cos(phi) * cos(theta), sin(phi), cos(phi) * sin(theta));
+
+	let height = clamp(atmosphere.height, 0.001, atmosphere.top_radius - atmosphere.bottom_radius - 0.001);
+	let origin = vec3<f32>(0.0, atmosphere.bottom_radius + height, 0.0);
+	let sun_direction = normalize(atmosphere.sun_direction);
+
+	let cos_theta = dot(direction, sun_direction);
+	let phase_rayleigh = rayleigh_phase(cos_theta);
+	let phase_mie = mie_phase(cos_theta, atmosphere.mie_asymmetry);
+
+	let ground = ray_sphere(origin, direction, atmosphere.bottom_radius);
+	let top = ray_sphere(origin, direction, atmosphere.top_radius);
+	let distance = select(max(top, 0.0), ground, ground >= 0.0);
+	let dt = distance / f32(SKY_STEPS);
+
+	var luminance = vec3<f32>(0.0);
+	var throughput = vec3<f32>(1.0);
+
+	for (var i = 0u; i < SKY_STEPS; i += 1u) {
+		let position = origin + direction * (f32(i) + 0.5) * dt;
+		let medium = sample_medium(position);
+		let scattering = medium.rayleigh + medium.mie;
+		let step_transmittance = exp(-medium.extinction * dt);
+		let extinction = max(medium.extinction, vec3<f32>(1e-9));
+
+		let sun_transmittance = sample_transmittance(position, sun_direction);
+		let multiscattering = sample_multiscattering(position, sun_direction);
+
+		var inscattering = sun_transmittance * (medium.rayleigh * phase_rayleigh + medium.mie * phase_mie);
+		inscattering += multiscattering * scattering;
+		inscattering *= atmosphere.sun_intensity;
+
+		luminance += throughput * (inscattering - inscattering * step_transmittance) / extinction;
+		throughput *= step_transmittance;
+	}
+
+	if ground >= 0.0 {
+		let position = origin + direction * ground;
+		let normal = normalize(position);
+		let sun = sample_transmittance(position, sun_direction) * saturate(dot(normal, sun_direction));
+		luminance += throughput * sun * atmosphere.sun_intensity * atmosphere.ground_albedo / PI;
+	} else if cos_theta >= cos(atmosphere.sun_angular_radius) {
+		// the sun disk, the solid angle of the sun spreads its intensity
+		let solid_angle = PI * atmosphere.sun_angular_radius * atmosphere.sun_angular_radius;
+		luminance += throughput * atmosphere.sun_intensity / solid_angle;
+	}
+
+	// environments are stored as 16 bit integers in the range 0 to 4
+	let scaled = clamp(luminance / 4.0, vec3<f32>(0.0), vec3<f32>(1.0)) * 65535.0;
+	textureStore(eq, vec2<i32>(global_id.xy), vec4<u32>(vec3<u32>(scaled), 65535u));
+}
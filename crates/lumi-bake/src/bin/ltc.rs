@@ -0,0 +1,456 @@
+//! Fits the linearly transformed cosine tables of lumi-renderer to the GGX distribution, following
+//! the isotropic fit of "Real-Time Polygonal-Light Shading with Linearly Transformed Cosines"
+//! (Heitz et al. 2016).
+//!
+//! Writes `ltc_1`, the four non-trivial elements of the inverse matrices, and `ltc_2`, the
+//! magnitude and fresnel of the fitted distributions. Both are `SIZE` by `SIZE` Rgba16Float texels,
+//! indexed by perceptual roughness along x and `sqrt(1 - n.v)` along y.
+//!
+//! `cargo run --release -p lumi-bake --bin ltc -- crates/lumi-renderer/src`
+
+use std::{f64::consts::PI, path::PathBuf};
+
+use half::f16;
+
+const SIZE: usize = 64;
+const MIN_ROUGHNESS: f64 = 0.00001;
+/// The number of samples along each axis when integrating the distributions.
+const SAMPLES: usize = 32;
+
+type Vec3 = [f64; 3];
+/// Column major, `m[column][row]`.
+type Mat3 = [[f64; 3]; 3];
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    scale(a, 1.0 / length(a))
+}
+
+fn mul_vec3(m: &Mat3, v: Vec3) -> Vec3 {
+    [
+        m[0][0] * v[0] + m[1][0] * v[1] + m[2][0] * v[2],
+        m[0][1] * v[0] + m[1][1] * v[1] + m[2][1] * v[2],
+        m[0][2] * v[0] + m[1][2] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn mul_mat3(a: &Mat3, b: &Mat3) -> Mat3 {
+    [mul_vec3(a, b[0]), mul_vec3(a, b[1]), mul_vec3(a, b[2])]
+}
+
+fn determinant(m: &Mat3) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[2][1] * m[1][2])
+        - m[1][0] * (m[0][1] * m[2][2] - m[2][1] * m[0][2])
+        + m[2][0] * (m[0][1] * m[1][2] - m[1][1] * m[0][2])
+}
+
+fn inverse(m: &Mat3) -> Mat3 {
+    let det = determinant(m);
+    let mut inverse = [[0.0; 3]; 3];
+
+    for (c, column) in inverse.iter_mut().enumerate() {
+        for (r, value) in column.iter_mut().enumerate() {
+            let (c1, c2) = ((r + 1) % 3, (r + 2) % 3);
+            let (r1, r2) = ((c + 1) % 3, (c + 2) % 3);
+
+            *value = (m[c1][r1] * m[c2][r2] - m[c2][r1] * m[c1][r2]) / det;
+        }
+    }
+
+    inverse
+}
+
+/// The Smith lambda of GGX.
+fn lambda(alpha: f64, cos_theta: f64) -> f64 {
+    if cos_theta >= 1.0 {
+        return 0.0;
+    }
+
+    let a = 1.0 / alpha / cos_theta.acos().tan();
+    0.5 * (-1.0 + (1.0 + 1.0 / (a * a)).sqrt())
+}
+
+/// Returns the GGX brdf times the cosine, and the pdf of sampling `l`.
+fn ggx_eval(v: Vec3, l: Vec3, alpha: f64) -> (f64, f64) {
+    if v[2] <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let g2 = if l[2] <= 0.0 {
+        0.0
+    } else {
+        1.0 / (1.0 + lambda(alpha, v[2]) + lambda(alpha, l[2]))
+    };
+
+    let h = normalize(add(v, l));
+    let slope_x = h[0] / h[2];
+    let slope_y = h[1] / h[2];
+
+    let d = 1.0 / (1.0 + (slope_x * slope_x + slope_y * slope_y) / (alpha * alpha));
+    let d = d * d / (PI * alpha * alpha * h[2].powi(4));
+
+    let pdf = (d * h[2] / 4.0 / dot(v, h)).abs();
+
+    (d * g2 / 4.0 / v[2], pdf)
+}
+
+fn ggx_sample(v: Vec3, alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let phi = 2.0 * PI * u1;
+    let r = alpha * (u2 / (1.0 - u2)).sqrt();
+    let n = normalize([r * phi.cos(), r * phi.sin(), 1.0]);
+
+    sub(scale(n, 2.0 * dot(n, v)), v)
+}
+
+fn sample_uv(i: usize, j: usize) -> (f64, f64) {
+    let u1 = (i as f64 + 0.5) / SAMPLES as f64;
+    let u2 = (j as f64 + 0.5) / SAMPLES as f64;
+    (u1, u2)
+}
+
+#[derive(Clone)]
+struct Ltc {
+    m11: f64,
+    m22: f64,
+    m13: f64,
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+    amplitude: f64,
+    m: Mat3,
+    inverse_m: Mat3,
+    determinant_m: f64,
+}
+
+impl Ltc {
+    fn new() -> Self {
+        let mut ltc = Self {
+            m11: 1.0,
+            m22: 1.0,
+            m13: 0.0,
+            x: [1.0, 0.0, 0.0],
+            y: [0.0, 1.0, 0.0],
+            z: [0.0, 0.0, 1.0],
+            amplitude: 1.0,
+            m: [[0.0; 3]; 3],
+            inverse_m: [[0.0; 3]; 3],
+            determinant_m: 0.0,
+        };
+
+        ltc.update();
+        ltc
+    }
+
+    fn update(&mut self) {
+        let frame = [self.x, self.y, self.z];
+        let scale = [
+            [self.m11, 0.0, 0.0],
+            [0.0, self.m22, 0.0],
+            [self.m13, 0.0, 1.0],
+        ];
+
+        self.m = mul_mat3(&frame, &scale);
+        self.inverse_m = inverse(&self.m);
+        self.determinant_m = determinant(&self.m).abs();
+    }
+
+    fn set_parameters(&mut self, parameters: [f64; 3], isotropic: bool) {
+        let m11 = parameters[0].max(1e-7);
+        let m22 = parameters[1].max(1e-7);
+
+        if isotropic {
+            self.m11 = m11;
+            self.m22 = m11;
+            self.m13 = 0.0;
+        } else {
+            self.m11 = m11;
+            self.m22 = m22;
+            self.m13 = parameters[2];
+        }
+
+        self.update();
+    }
+
+    fn eval(&self, l: Vec3) -> f64 {
+        let original = normalize(mul_vec3(&self.inverse_m, l));
+        let transformed = mul_vec3(&self.m, original);
+
+        let length = length(transformed);
+        let jacobian = self.determinant_m / (length * length * length);
+
+        let d = original[2].max(0.0) / PI;
+        self.amplitude * d / jacobian
+    }
+
+    fn sample(&self, u1: f64, u2: f64) -> Vec3 {
+        let theta = u1.sqrt().acos();
+        let phi = 2.0 * PI * u2;
+        let direction = [
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ];
+
+        normalize(mul_vec3(&self.m, direction))
+    }
+
+    /// The error between the ltc and GGX, sampled from both distributions.
+    fn error(&self, v: Vec3, alpha: f64) -> f64 {
+        let mut error = 0.0;
+
+        for j in 0..SAMPLES {
+            for i in 0..SAMPLES {
+                let (u1, u2) = sample_uv(i, j);
+
+                for l in [self.sample(u1, u2), ggx_sample(v, alpha, u1, u2)] {
+                    let (eval_ggx, pdf_ggx) = ggx_eval(v, l, alpha);
+                    let eval_ltc = self.eval(l);
+                    let pdf_ltc = eval_ltc / self.amplitude;
+
+                    error += (eval_ggx - eval_ltc).abs().powi(3) / (pdf_ltc + pdf_ggx);
+                }
+            }
+        }
+
+        error / (SAMPLES * SAMPLES) as f64
+    }
+}
+
+/// Returns the magnitude, the fresnel and the average direction of GGX.
+fn average_terms(v: Vec3, alpha: f64) -> (f64, f64, Vec3) {
+    let mut magnitude = 0.0;
+    let mut fresnel = 0.0;
+    let mut direction = [0.0; 3];
+
+    for j in 0..SAMPLES {
+        for i in 0..SAMPLES {
+            let (u1, u2) = sample_uv(i, j);
+
+            let l = ggx_sample(v, alpha, u1, u2);
+            let (eval, pdf) = ggx_eval(v, l, alpha);
+
+            if pdf > 0.0 {
+                let weight = eval / pdf;
+                let h = normalize(add(v, l));
+
+                magnitude += weight;
+                fresnel += weight * (1.0 - dot(v, h).max(0.0)).powi(5);
+                direction = add(direction, scale(l, weight));
+            }
+        }
+    }
+
+    let count = (SAMPLES * SAMPLES) as f64;
+
+    // the average direction lies in the plane of v and n
+    direction[1] = 0.0;
+
+    (magnitude / count, fresnel / count, normalize(direction))
+}
+
+/// Minimizes `f` with the Nelder-Mead simplex method.
+fn nelder_mead(
+    start: [f64; 3],
+    delta: f64,
+    tolerance: f64,
+    max_iterations: usize,
+    mut f: impl FnMut([f64; 3]) -> f64,
+) -> [f64; 3] {
+    let mut simplex = [start; 4];
+    for (i, point) in simplex.iter_mut().enumerate().skip(1) {
+        point[i - 1] += delta;
+    }
+
+    let mut values = simplex.map(&mut f);
+
+    let min_index = |values: &[f64; 4]| {
+        (0..4)
+            .min_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap())
+            .unwrap()
+    };
+
+    for _ in 0..max_iterations {
+        let lowest = min_index(&values);
+        let highest = (0..4).fold(0, |hi, i| if values[i] > values[hi] { i } else { hi });
+        let next_highest = (0..4)
+            .filter(|&i| i != highest)
+            .max_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap())
+            .unwrap();
+
+        let a = values[lowest].abs();
+        let b = values[highest].abs();
+        if 2.0 * (a - b).abs() < (a + b) * tolerance {
+            break;
+        }
+
+        // the centroid of all points but the highest
+        let mut centroid = [0.0; 3];
+        for (i, point) in simplex.iter().enumerate() {
+            if i != highest {
+                for d in 0..3 {
+                    centroid[d] += point[d] / 3.0;
+                }
+            }
+        }
+
+        let lerp = |t: f64, point: [f64; 3]| {
+            let mut result = [0.0; 3];
+            for d in 0..3 {
+                result[d] = centroid[d] + t * (point[d] - centroid[d]);
+            }
+            result
+        };
+
+        let reflected = lerp(-1.0, simplex[highest]);
+        let reflected_value = f(reflected);
+
+        if reflected_value < values[next_highest] {
+            if reflected_value < values[lowest] {
+                let expanded = lerp(-2.0, simplex[highest]);
+                let expanded_value = f(expanded);
+
+                if expanded_value < reflected_value {
+                    simplex[highest] = expanded;
+                    values[highest] = expanded_value;
+                    continue;
+                }
+            }
+
+            simplex[highest] = reflected;
+            values[highest] = reflected_value;
+            continue;
+        }
+
+        let contracted = lerp(0.5, simplex[highest]);
+        let contracted_value = f(contracted);
+
+        if contracted_value < values[highest] {
+            simplex[highest] = contracted;
+            values[highest] = contracted_value;
+            continue;
+        }
+
+        // shrink towards the lowest point
+        for i in 0..4 {
+            if i != lowest {
+                let lowest_point = simplex[lowest];
+                for (value, lowest_value) in simplex[i].iter_mut().zip(lowest_point) {
+                    *value = 0.5 * (lowest_value + *value);
+                }
+
+                values[i] = f(simplex[i]);
+            }
+        }
+    }
+
+    simplex[min_index(&values)]
+}
+
+fn main() {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| String::from(".")));
+
+    let mut matrices = vec![[[0.0; 3]; 3]; SIZE * SIZE];
+    let mut magnitudes = vec![[0.0; 2]; SIZE * SIZE];
+
+    let mut ltc = Ltc::new();
+
+    // starts at the roughest distribution, which is closest to a cosine, and uses each fit as
+    // the starting point of the next
+    for a in (0..SIZE).rev() {
+        for t in 0..SIZE {
+            let x = t as f64 / (SIZE - 1) as f64;
+            let theta = f64::min(1.57, (1.0 - x * x).acos());
+            let v = [theta.sin(), 0.0, theta.cos()];
+
+            let roughness = a as f64 / (SIZE - 1) as f64;
+            let alpha = f64::max(roughness * roughness, MIN_ROUGHNESS);
+
+            let (magnitude, fresnel, average_direction) = average_terms(v, alpha);
+            ltc.amplitude = magnitude;
+
+            // the distribution is isotropic when viewed from straight above
+            let isotropic = t == 0;
+
+            if isotropic {
+                ltc.x = [1.0, 0.0, 0.0];
+                ltc.y = [0.0, 1.0, 0.0];
+                ltc.z = [0.0, 0.0, 1.0];
+
+                if a == SIZE - 1 {
+                    ltc.m11 = 1.0;
+                    ltc.m22 = 1.0;
+                } else {
+                    ltc.m11 = matrices[a + 1][0][0];
+                    ltc.m22 = matrices[a + 1][1][1];
+                }
+
+                ltc.m13 = 0.0;
+            } else {
+                let l = average_direction;
+                ltc.x = [l[2], 0.0, -l[0]];
+                ltc.y = [0.0, 1.0, 0.0];
+                ltc.z = l;
+            }
+
+            ltc.update();
+
+            let start = [ltc.m11, ltc.m22, ltc.m13];
+            let mut fit = ltc.clone();
+            let parameters = nelder_mead(start, 0.05, 1e-5, 100, |parameters| {
+                fit.set_parameters(parameters, isotropic);
+                fit.error(v, alpha)
+            });
+            ltc.set_parameters(parameters, isotropic);
+
+            let mut m = ltc.m;
+            m[0][1] = 0.0;
+            m[1][0] = 0.0;
+            m[2][1] = 0.0;
+            m[1][2] = 0.0;
+
+            matrices[a + t * SIZE] = m;
+            magnitudes[a + t * SIZE] = [magnitude, fresnel];
+        }
+    }
+
+    let mut ltc_1 = Vec::with_capacity(SIZE * SIZE * 8);
+    let mut ltc_2 = Vec::with_capacity(SIZE * SIZE * 8);
+
+    for (m, [magnitude, fresnel]) in matrices.iter().zip(magnitudes) {
+        let inverse = inverse(m);
+
+        // normalized so the middle element is 1
+        let s = inverse[1][1];
+        let matrix = [inverse[0][0], inverse[0][2], inverse[2][0], inverse[2][2]].map(|x| x / s);
+
+        for value in matrix {
+            ltc_1.extend_from_slice(&f16::from_f32(value as f32).to_le_bytes());
+        }
+
+        for value in [magnitude, fresnel, 0.0, 0.0] {
+            ltc_2.extend_from_slice(&f16::from_f32(value as f32).to_le_bytes());
+        }
+    }
+
+    std::fs::write(dir.join("ltc_1"), ltc_1).unwrap();
+    std::fs::write(dir.join("ltc_2"), ltc_2).unwrap();
+}
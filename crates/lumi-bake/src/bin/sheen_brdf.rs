@@ -0,0 +1,81 @@
+//! Integrates the DFG term of the Charlie sheen distribution with Ashikhmin visibility, written
+//! to `integrated_sheen_brdf` in lumi-renderer.
+//!
+//! The table is `SIZE` by `SIZE` R16Float texels, indexed by `n.v` along x and perceptual
+//! roughness along y.
+//!
+//! `cargo run --release -p lumi-bake --bin sheen_brdf -- crates/lumi-renderer/src`
+
+use std::{f64::consts::PI, path::PathBuf};
+
+use half::f16;
+
+const SIZE: usize = 64;
+const SAMPLES: u32 = 512;
+
+fn hammersley(i: u32, n: u32) -> (f64, f64) {
+    (i as f64 / n as f64, i.reverse_bits() as f64 / 4294967296.0)
+}
+
+/// Uniformly distributed directions on the hemisphere around z.
+fn hemisphere_samples() -> Vec<[f64; 3]> {
+    (0..SAMPLES)
+        .map(|i| {
+            let (u, v) = hammersley(i, SAMPLES);
+
+            let phi = 2.0 * PI * u;
+            let cos_theta = 1.0 - v;
+            let sin_theta = f64::max(1.0 - cos_theta * cos_theta, 0.0).sqrt();
+
+            [sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta]
+        })
+        .collect()
+}
+
+fn integrate(samples: &[[f64; 3]], perceptual_roughness: f64, nov: f64) -> f64 {
+    // matches the minimum roughness of the shaders
+    let roughness = f64::max(perceptual_roughness * perceptual_roughness, 0.089 * 0.089);
+    let inv_roughness = 1.0 / roughness;
+
+    let v = [(1.0 - nov * nov).sqrt(), nov];
+
+    let mut sum = 0.0;
+
+    for &[hx, _, hz] in samples {
+        let voh = v[0] * hx + v[1] * hz;
+        let nol = 2.0 * voh * hz - v[1];
+
+        if nol <= 0.0 || voh <= 0.0 {
+            continue;
+        }
+
+        let sin2h = f64::max(1.0 - hz * hz, 0.0078125);
+        let d = (2.0 + inv_roughness) * sin2h.powf(inv_roughness * 0.5) / (2.0 * PI);
+        let visibility = 1.0 / (4.0 * (nol + nov - nol * nov));
+
+        sum += visibility * d * nol * voh;
+    }
+
+    // the pdf of the half vectors is 1 / 2pi, and 4 v.h converts them to light directions
+    sum * 4.0 * 2.0 * PI / SAMPLES as f64
+}
+
+fn main() {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| String::from(".")));
+    let samples = hemisphere_samples();
+
+    let mut data = Vec::with_capacity(SIZE * SIZE * 2);
+
+    for y in 0..SIZE {
+        let perceptual_roughness = (y as f64 + 0.5) / SIZE as f64;
+
+        for x in 0..SIZE {
+            let nov = (x as f64 + 0.5) / SIZE as f64;
+            let value = integrate(&samples, perceptual_roughness, nov);
+
+            data.extend_from_slice(&f16::from_f64(value).to_le_bytes());
+        }
+    }
+
+    std::fs::write(dir.join("integrated_sheen_brdf"), data).unwrap();
+}
@@ -0,0 +1,72 @@
+let PI = 3.1415926535897932384626433832795;
+
+@group(0) @binding(0)
+var faces: texture_2d_array<f32>;
+
+@group(0) @binding(1)
+var faces_sampler: sampler;
+
+@group(0) @binding(2)
+var eq: texture_storage_2d<rgba16uint, write>;
+
+// must match `BakedEnvironment::CUBE_FACES`
+fn face_forward(face: i32) -> vec3<f32> {
+	switch face {
+		case 0 { return vec3<f32>(1.0, 0.0, 0.0); }
+		case 1 { return vec3<f32>(-1.0, 0.0, 0.0); }
+		case 2 { return vec3<f32>(0.0, 1.0, 0.0); }
+		case 3 { return vec3<f32>(0.0, -1.0, 0.0); }
+		case 4 { return vec3<f32>(0.0, 0.0, 1.0); }
+		default { return vec3<f32>(0.0, 0.0, -1.0); }
+	}
+}
+
+fn face_up(face: i32) -> vec3<f32> {
+	switch face {
+		case 2 { return vec3<f32>(0.0, 0.0, 1.0); }
+		case 3 { return vec3<f32>(0.0, 0.0, -1.0); }
+		default { return vec3<f32>(0.0, 1.0, 0.0); }
+	}
+}
+
+fn direction_face(direction: vec3<f32>) -> i32 {
+	let a = abs(direction);
+
+	if a.x >= a.y && a.x >= a.z {
+		return select(1, 0, direction.x > 0.0);
+	} else if a.y >= a.z {
+		return select(3, 2, direction.y > 0.0);
+	} else {
+		return select(5, 4, direction.z > 0.0);
+	}
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn cube_to_eq(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(eq);
+
+	if i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions);
+	let theta = (uv.x - 0.5) * 2.0 * PI;
+	let phi = -(uv.y - 0.5) * PI;
+	let direction = vec3<f32>(cos(phi) * cos(theta), sin(phi), cos(phi) * sin(theta));
+
+	let face = direction_face(direction);
+	let forward = face_forward(face);
+	let up = face_up(face);
+	let right = cross(forward, up);
+
+	let z = dot(direction, forward);
+	let x = dot(direction, right) / z;
+	let y = dot(direction, up) / z;
+	let face_uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+
+	let color = textureSampleLevel(faces, faces_sampler, face_uv, face, 0.0);
+
+	// environments are stored as 16 bit integers in the range 0 to 4
+	let scaled = clamp(color.rgb / 4.0, vec3<f32>(0.0), vec3<f32>(1.0)) * 65535.0;
+	textureStore(eq, vec2<i32>(global_id.xy), vec4<u32>(vec3<u32>(scaled), 65535u));
+}
@@ -9,13 +9,13 @@ use lumi_core::{
     util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferDescriptor,
     BufferInitDescriptor, BufferUsages, CommandEncoderDescriptor, ComputePipelineDescriptor,
-    Device, Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain, MapMode,
-    Origin3d, PipelineLayoutDescriptor, Queue, ShaderStages, SharedDevice, SharedTexture,
-    SharedTextureView, StorageTextureAccess, Texture, TextureAspect, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor,
-    TextureViewDimension,
+    Device, Extent3d, FilterMode, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain,
+    MapMode, Origin3d, PipelineLayoutDescriptor, Queue, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, SharedDevice, SharedTexture, SharedTextureView, StorageTextureAccess, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension,
 };
-use lumi_util::bytemuck;
+use lumi_util::{bytemuck, math::Vec3};
 use tracing_log::log;
 
 pub struct EnvironmentData {
@@ -408,7 +408,19 @@ impl BakedEnvironment {
 }
 
 impl BakedEnvironment {
-    const WORKGROUP_SIZE: u32 = 16;
+    pub(crate) const WORKGROUP_SIZE: u32 = 16;
+
+    /// The forward and up directions of the faces passed to [`BakedEnvironment::from_faces`].
+    ///
+    /// The right direction of each face is `forward.cross(up)`.
+    pub const CUBE_FACES: [(Vec3, Vec3); 6] = [
+        (Vec3::X, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y),
+    ];
 
     #[cfg(feature = "image")]
     pub fn open_from_eq(
@@ -475,6 +487,139 @@ impl BakedEnvironment {
         )
     }
 
+    /// Bakes an environment from the six faces of a cube, rendered in the directions given by
+    /// [`BakedEnvironment::CUBE_FACES`] and stored in the layers of `faces`, each `face_size`
+    /// pixels wide.
+    ///
+    /// The faces are converted to an equirectangular image and baked with
+    /// [`BakedEnvironment::from_eq`], which limits the stored radiance to the range `0` to `4`.
+    pub fn from_faces(
+        device: &Device,
+        queue: &Queue,
+        faces: &Texture,
+        face_size: u32,
+        indirect_size: u32,
+        irradiance_size: u32,
+        sky_size: u32,
+    ) -> Self {
+        let eq_texture = device.create_texture(&TextureDescriptor {
+            label: Some("Environment"),
+            size: Extent3d {
+                width: face_size * 2,
+                height: face_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba16Uint,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        });
+        let eq_view = eq_texture.create_view(&Default::default());
+
+        let faces_view = faces.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let faces_sampler = device.create_sampler(&SamplerDescriptor {
+            label: None,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: TextureFormat::Rgba16Uint,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Environment Cube To Eq Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &device.create_shader_module(lumi_core::include_wgsl!("cube_to_eq.wgsl")),
+            entry_point: "cube_to_eq",
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&faces_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&faces_sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&eq_view),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+        compute_pass.set_pipeline(&pipeline);
+
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            face_size * 2 / Self::WORKGROUP_SIZE + 1,
+            face_size / Self::WORKGROUP_SIZE + 1,
+            1,
+        );
+
+        drop(compute_pass);
+
+        queue.submit(std::iter::once(encoder.finish()));
+        device.poll(Maintain::Wait);
+
+        log::trace!("Converted cube faces: {}/{}", 1, 1);
+
+        Self::from_eq(
+            device,
+            queue,
+            &eq_texture,
+            indirect_size,
+            irradiance_size,
+            sky_size,
+        )
+    }
+
     pub fn from_eq(
         device: &Device,
         queue: &Queue,
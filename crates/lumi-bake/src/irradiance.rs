@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    io::{self, prelude::*},
+    path::Path,
+};
+
+use half::f16;
+use lumi_core::{Device, Queue, Texture};
+use lumi_util::math::{UVec3, Vec3};
+
+use crate::BakedEnvironment;
+
+/// L2 spherical harmonics of the radiance around a point, with 9 rgb coefficients.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SphericalHarmonics {
+    pub coefficients: [Vec3; 9],
+}
+
+impl SphericalHarmonics {
+    /// The number of rgba texels needed to store the coefficients.
+    pub const TEXELS: u32 = 7;
+
+    /// Evaluates the basis functions in `direction`.
+    pub fn basis(direction: Vec3) -> [f32; 9] {
+        let Vec3 { x, y, z } = direction;
+
+        [
+            0.282095,
+            0.488603 * y,
+            0.488603 * z,
+            0.488603 * x,
+            1.092548 * x * y,
+            1.092548 * y * z,
+            0.315392 * (3.0 * z * z - 1.0),
+            1.092548 * x * z,
+            0.546274 * (x * x - y * y),
+        ]
+    }
+
+    /// Projects the six faces of a cube, rendered in the directions given by
+    /// [`BakedEnvironment::CUBE_FACES`] and stored in the layers of `faces`, each `face_size`
+    /// pixels wide.
+    ///
+    /// `faces` must be [`TextureFormat::Rgba16Float`](lumi_core::TextureFormat::Rgba16Float) and
+    /// have [`TextureUsages::COPY_SRC`](lumi_core::TextureUsages::COPY_SRC).
+    pub fn from_faces(device: &Device, queue: &Queue, faces: &Texture, face_size: u32) -> Self {
+        let data = BakedEnvironment::read_texture(device, queue, faces, face_size, face_size, 1);
+
+        let mut coefficients = [Vec3::ZERO; 9];
+        let mut total_weight = 0.0;
+
+        let texel = |index: usize| {
+            let channel = |channel: usize| {
+                let offset = index * 8 + channel * 2;
+                f16::from_le_bytes([data[offset], data[offset + 1]]).to_f32()
+            };
+
+            Vec3::new(channel(0), channel(1), channel(2))
+        };
+
+        for (face, &(forward, up)) in BakedEnvironment::CUBE_FACES.iter().enumerate() {
+            let right = forward.cross(up);
+
+            for v in 0..face_size {
+                for u in 0..face_size {
+                    let x = (u as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let y = 1.0 - (v as f32 + 0.5) / face_size as f32 * 2.0;
+
+                    // the solid angle of the texel
+                    let weight = 1.0 / f32::powf(1.0 + x * x + y * y, 1.5);
+                    let direction = (forward + right * x + up * y).normalize();
+
+                    let index = ((face as u32 * face_size + v) * face_size + u) as usize;
+                    let color = texel(index);
+
+                    for (coefficient, basis) in coefficients.iter_mut().zip(Self::basis(direction))
+                    {
+                        *coefficient += color * basis * weight;
+                    }
+
+                    total_weight += weight;
+                }
+            }
+        }
+
+        let scale = 4.0 * std::f32::consts::PI / total_weight;
+        for coefficient in coefficients.iter_mut() {
+            *coefficient *= scale;
+        }
+
+        Self { coefficients }
+    }
+
+    /// Returns the coefficients packed into [`SphericalHarmonics::TEXELS`] rgba texels.
+    pub fn texels(&self) -> [[f32; 4]; Self::TEXELS as usize] {
+        let mut texels = [[0.0; 4]; Self::TEXELS as usize];
+
+        for (i, coefficient) in self.coefficients.iter().enumerate() {
+            for (j, value) in coefficient.to_array().into_iter().enumerate() {
+                let index = i * 3 + j;
+                texels[index / 4][index % 4] = value;
+            }
+        }
+
+        texels
+    }
+}
+
+/// The spherical harmonics of a grid of probes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IrradianceVolumeData {
+    /// The number of probes along each axis.
+    pub resolution: UVec3,
+    /// The exposure of the camera the probes were captured with.
+    pub exposure: f32,
+    /// The probes, ordered along x, then y, then z.
+    pub probes: Vec<SphericalHarmonics>,
+}
+
+impl IrradianceVolumeData {
+    pub fn probe_count(&self) -> usize {
+        (self.resolution.x * self.resolution.y * self.resolution.z) as usize
+    }
+
+    pub fn load<T: Read>(mut source: T) -> io::Result<Self> {
+        macro_rules! read {
+            ($source:expr, $type:ty) => {{
+                let mut buf = [0; std::mem::size_of::<$type>()];
+                $source.read_exact(&mut buf)?;
+                <$type>::from_le_bytes(buf)
+            }};
+        }
+
+        let resolution = UVec3::new(read!(source, u32), read!(source, u32), read!(source, u32));
+        let exposure = read!(source, f32);
+
+        let mut data = Self {
+            resolution,
+            exposure,
+            probes: Vec::new(),
+        };
+
+        for _ in 0..data.probe_count() {
+            let mut probe = SphericalHarmonics::default();
+
+            for coefficient in probe.coefficients.iter_mut() {
+                *coefficient =
+                    Vec3::new(read!(source, f32), read!(source, f32), read!(source, f32));
+            }
+
+            data.probes.push(probe);
+        }
+
+        Ok(data)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::load(bytes)
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::load(fs::File::open(path)?)
+    }
+
+    pub fn save<T: Write>(&self, mut dest: T) -> io::Result<()> {
+        macro_rules! write {
+            ($dest:expr, $value:expr) => {{
+                let buf = $value.to_le_bytes();
+                $dest.write_all(&buf)?;
+            }};
+        }
+
+        write!(dest, self.resolution.x);
+        write!(dest, self.resolution.y);
+        write!(dest, self.resolution.z);
+        write!(dest, self.exposure);
+
+        for probe in self.probes.iter() {
+            for coefficient in probe.coefficients.iter() {
+                write!(dest, coefficient.x);
+                write!(dest, coefficient.y);
+                write!(dest, coefficient.z);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.save(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Returns the probes as an `Rgba16Float` 3D texture, `resolution.x` by `resolution.y` by
+    /// `resolution.z * SphericalHarmonics::TEXELS`.
+    ///
+    /// Each slab of `resolution.z` layers holds one texel of every probe.
+    pub fn texture_data(&self) -> Vec<u8> {
+        let texels = self
+            .probes
+            .iter()
+            .map(SphericalHarmonics::texels)
+            .collect::<Vec<_>>();
+
+        let mut data = Vec::with_capacity(texels.len() * SphericalHarmonics::TEXELS as usize * 8);
+
+        for slab in 0..SphericalHarmonics::TEXELS as usize {
+            for probe in texels.iter() {
+                for value in probe[slab] {
+                    data.extend_from_slice(&f16::from_f32(value).to_le_bytes());
+                }
+            }
+        }
+
+        data
+    }
+}
@@ -1,5 +1,11 @@
+mod atmosphere;
 mod environment;
 mod instance;
+mod irradiance;
+mod lightmap;
 
+pub use atmosphere::*;
 pub use environment::*;
 pub use instance::*;
+pub use irradiance::*;
+pub use lightmap::*;
@@ -0,0 +1,715 @@
+use std::{
+    fs,
+    io::{self, prelude::*},
+    path::Path,
+};
+
+use half::f16;
+use lumi_core::{
+    util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor,
+    BufferInitDescriptor, BufferUsages, CommandEncoderDescriptor, ComputePipelineDescriptor,
+    Device, ImageData, Maintain, MapMode, PipelineLayoutDescriptor, Queue, ShaderStages,
+    TextureFormat,
+};
+use lumi_util::{
+    bytemuck,
+    math::{Mat3, Mat4, UVec2, Vec2, Vec3},
+};
+use tracing_log::log;
+
+/// A mesh in the scene of a [`LightmapBaker`].
+///
+/// Every mesh occludes and bounces light, only meshes with a
+/// [`LightmapMesh::lightmap_size`] get a lightmap.
+#[derive(Clone, Debug)]
+pub struct LightmapMesh {
+    pub positions: Vec<Vec3>,
+    /// The vertex normals, the face normals are used when empty.
+    pub normals: Vec<Vec3>,
+    /// The lightmap uvs, usually the `uv_1` attribute of the mesh.
+    pub uvs: Vec<Vec2>,
+    /// The indices of the triangles, the vertices are used in order when empty.
+    pub indices: Vec<u32>,
+    pub transform: Mat4,
+    /// The diffuse albedo of the surface.
+    pub albedo: Vec3,
+    /// The radiance emitted by the surface, in nits.
+    pub emissive: Vec3,
+    /// The size in texels of the lightmap baked for the mesh.
+    pub lightmap_size: Option<UVec2>,
+}
+
+impl Default for LightmapMesh {
+    fn default() -> Self {
+        Self {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            transform: Mat4::IDENTITY,
+            albedo: Vec3::splat(0.8),
+            emissive: Vec3::ZERO,
+            lightmap_size: None,
+        }
+    }
+}
+
+impl LightmapMesh {
+    fn triangle_count(&self) -> usize {
+        if self.indices.is_empty() {
+            self.positions.len() / 3
+        } else {
+            self.indices.len() / 3
+        }
+    }
+
+    fn vertex_index(&self, triangle: usize, vertex: usize) -> usize {
+        if self.indices.is_empty() {
+            triangle * 3 + vertex
+        } else {
+            self.indices[triangle * 3 + vertex] as usize
+        }
+    }
+}
+
+/// A light in the scene of a [`LightmapBaker`], in the same units as the lights of the renderer.
+#[derive(Clone, Copy, Debug)]
+pub enum LightmapLight {
+    Directional {
+        /// The direction the light travels in.
+        direction: Vec3,
+        color: Vec3,
+        /// The illuminance in lux.
+        illuminance: f32,
+    },
+    Point {
+        position: Vec3,
+        color: Vec3,
+        /// The luminous power in lumens.
+        intensity: f32,
+        range: f32,
+    },
+}
+
+impl LightmapLight {
+    /// Returns the raw data of the light, matching `Light` in `lightmap.wgsl`.
+    fn raw(&self) -> [f32; 8] {
+        match *self {
+            Self::Directional {
+                direction,
+                color,
+                illuminance,
+            } => {
+                let l = -direction.normalize_or_zero();
+                let color = color * illuminance;
+                [l.x, l.y, l.z, 0.0, color.x, color.y, color.z, 0.0]
+            }
+            Self::Point {
+                position,
+                color,
+                intensity,
+                range,
+            } => {
+                let color = color * intensity / (4.0 * std::f32::consts::PI);
+                let p = position;
+                [p.x, p.y, p.z, 1.0, color.x, color.y, color.z, range]
+            }
+        }
+    }
+}
+
+/// The diffuse indirect light of a mesh, stored in the texels of its lightmap.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightmapData {
+    pub width: u32,
+    pub height: u32,
+    /// The irradiance divided by pi of each texel, ordered by row.
+    pub texels: Vec<Vec3>,
+}
+
+impl LightmapData {
+    pub fn load<T: Read>(mut source: T) -> io::Result<Self> {
+        macro_rules! read {
+            ($source:expr, $type:ty) => {{
+                let mut buf = [0; std::mem::size_of::<$type>()];
+                $source.read_exact(&mut buf)?;
+                <$type>::from_le_bytes(buf)
+            }};
+        }
+
+        let width = read!(source, u32);
+        let height = read!(source, u32);
+
+        let mut texels = Vec::with_capacity((width * height) as usize);
+        for _ in 0..width * height {
+            let texel = Vec3::new(read!(source, f32), read!(source, f32), read!(source, f32));
+            texels.push(texel);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::load(bytes)
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::load(fs::File::open(path)?)
+    }
+
+    pub fn save<T: Write>(&self, mut dest: T) -> io::Result<()> {
+        macro_rules! write {
+            ($dest:expr, $value:expr) => {{
+                let buf = $value.to_le_bytes();
+                $dest.write_all(&buf)?;
+            }};
+        }
+
+        write!(dest, self.width);
+        write!(dest, self.height);
+
+        for texel in self.texels.iter() {
+            write!(dest, texel.x);
+            write!(dest, texel.y);
+            write!(dest, texel.z);
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.save(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Returns the lightmap as an `Rgba16Float` image.
+    pub fn image_data(&self) -> ImageData {
+        let mut data = Vec::with_capacity(self.texels.len() * 8);
+
+        for texel in self.texels.iter() {
+            for value in [texel.x, texel.y, texel.z, 1.0] {
+                data.extend_from_slice(&f16::from_f32(value).to_le_bytes());
+            }
+        }
+
+        ImageData::with_format(self.width, self.height, data, TextureFormat::Rgba16Float)
+    }
+
+    /// Fills texels not covered by the mesh with the average of their covered neighbors, so
+    /// that filtering at the edges of uv islands doesn't bleed in black.
+    fn dilate(&mut self, covered: &mut [bool], iterations: u32) {
+        let width = self.width as i32;
+        let height = self.height as i32;
+
+        for _ in 0..iterations {
+            let mut filled = Vec::new();
+
+            for y in 0..height {
+                for x in 0..width {
+                    let index = (y * width + x) as usize;
+
+                    if covered[index] {
+                        continue;
+                    }
+
+                    let mut sum = Vec3::ZERO;
+                    let mut count = 0;
+
+                    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        let (nx, ny) = (x + dx, y + dy);
+
+                        if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                            continue;
+                        }
+
+                        let neighbor = (ny * width + nx) as usize;
+                        if covered[neighbor] {
+                            sum += self.texels[neighbor];
+                            count += 1;
+                        }
+                    }
+
+                    if count > 0 {
+                        filled.push((index, sum / count as f32));
+                    }
+                }
+            }
+
+            for (index, texel) in filled {
+                self.texels[index] = texel;
+                covered[index] = true;
+            }
+        }
+    }
+}
+
+/// A triangle of the scene in world space.
+#[derive(Clone, Copy)]
+struct BvhTriangle {
+    vertices: [Vec3; 3],
+    mesh: u32,
+}
+
+impl BvhTriangle {
+    fn centroid(&self) -> Vec3 {
+        (self.vertices[0] + self.vertices[1] + self.vertices[2]) / 3.0
+    }
+
+    /// Returns the raw data of the triangle, matching `Triangle` in `lightmap.wgsl`.
+    fn raw(&self) -> [f32; 12] {
+        let [a, b, c] = self.vertices;
+        let mesh = f32::from_bits(self.mesh);
+
+        [a.x, a.y, a.z, mesh, b.x, b.y, b.z, 0.0, c.x, c.y, c.z, 0.0]
+    }
+}
+
+/// A bounding volume hierarchy over the triangles of the scene, stored depth first.
+struct Bvh {
+    /// The raw nodes, matching `Node` in `lightmap.wgsl`.
+    nodes: Vec<[f32; 8]>,
+    triangles: Vec<BvhTriangle>,
+}
+
+impl Bvh {
+    const LEAF_SIZE: usize = 4;
+
+    fn new(mut triangles: Vec<BvhTriangle>) -> Self {
+        let mut nodes = Vec::new();
+
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            Self::build(&mut nodes, &mut triangles, 0, len);
+        } else {
+            // an empty node that can't be hit
+            let empty = f32::from_bits(0);
+            nodes.push([1.0, 1.0, 1.0, empty, -1.0, -1.0, -1.0, empty]);
+        }
+
+        Self { nodes, triangles }
+    }
+
+    fn build(nodes: &mut Vec<[f32; 8]>, triangles: &mut [BvhTriangle], first: usize, len: usize) {
+        let slice = &mut triangles[first..first + len];
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        let mut centroid_min = Vec3::splat(f32::MAX);
+        let mut centroid_max = Vec3::splat(f32::MIN);
+
+        for triangle in slice.iter() {
+            for vertex in triangle.vertices {
+                min = min.min(vertex);
+                max = max.max(vertex);
+            }
+
+            centroid_min = centroid_min.min(triangle.centroid());
+            centroid_max = centroid_max.max(triangle.centroid());
+        }
+
+        let node = nodes.len();
+        nodes.push([min.x, min.y, min.z, 0.0, max.x, max.y, max.z, 0.0]);
+
+        if len <= Self::LEAF_SIZE {
+            nodes[node][3] = f32::from_bits(first as u32);
+            nodes[node][7] = f32::from_bits(len as u32);
+            return;
+        }
+
+        // split at the median along the axis where the centroids are spread the most
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        let half = len / 2;
+        slice.select_nth_unstable_by(half, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        Self::build(nodes, triangles, first, half);
+
+        nodes[node][3] = f32::from_bits(nodes.len() as u32);
+        nodes[node][7] = f32::from_bits(0);
+
+        Self::build(nodes, triangles, first + half, len - half);
+    }
+}
+
+/// Bakes [`LightmapData`] for static meshes by path tracing on the gpu.
+///
+/// The lightmaps hold the diffuse indirect light, light arriving from the sky, emissive surfaces
+/// and light bounced off other surfaces, but not the light arriving directly from the lights,
+/// which is still rendered at runtime.
+#[derive(Clone, Debug)]
+pub struct LightmapBaker {
+    pub meshes: Vec<LightmapMesh>,
+    pub lights: Vec<LightmapLight>,
+    /// The radiance of the sky in nits, arriving from every direction not blocked by a mesh.
+    pub sky: Vec3,
+    /// The number of paths traced for each texel.
+    pub samples: u32,
+    /// The number of times paths bounce off surfaces.
+    pub bounces: u32,
+}
+
+impl Default for LightmapBaker {
+    fn default() -> Self {
+        Self {
+            meshes: Vec::new(),
+            lights: Vec::new(),
+            sky: Vec3::ZERO,
+            samples: 256,
+            bounces: 3,
+        }
+    }
+}
+
+impl LightmapBaker {
+    pub const WORKGROUP_SIZE: u32 = 64;
+    /// The number of samples traced per texel in a single dispatch.
+    pub const BATCH_SIZE: u32 = 16;
+    const MAX_WORKGROUPS: u32 = 32768;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_mesh(&mut self, mesh: LightmapMesh) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    pub fn add_light(&mut self, light: LightmapLight) {
+        self.lights.push(light);
+    }
+
+    /// Returns the raw parameters, matching `Params` in `lightmap.wgsl`.
+    fn raw_params(&self, texel_count: u32, sample_offset: u32, samples: u32) -> [u32; 8] {
+        [
+            self.sky.x.to_bits(),
+            self.sky.y.to_bits(),
+            self.sky.z.to_bits(),
+            texel_count,
+            self.lights.len() as u32,
+            sample_offset,
+            samples,
+            self.bounces,
+        ]
+    }
+
+    fn triangles(&self) -> Vec<BvhTriangle> {
+        let mut triangles = Vec::new();
+
+        for (index, mesh) in self.meshes.iter().enumerate() {
+            for triangle in 0..mesh.triangle_count() {
+                let vertex = |vertex: usize| {
+                    let position = mesh.positions[mesh.vertex_index(triangle, vertex)];
+                    mesh.transform.transform_point3(position)
+                };
+
+                triangles.push(BvhTriangle {
+                    vertices: [vertex(0), vertex(1), vertex(2)],
+                    mesh: index as u32,
+                });
+            }
+        }
+
+        triangles
+    }
+
+    /// Rasterizes `mesh` in its lightmap uvs, returning the raw world space position and normal
+    /// of each texel, matching `Texel` in `lightmap.wgsl`.
+    fn texels(mesh: &LightmapMesh, size: UVec2) -> Vec<[f32; 8]> {
+        let mut texels = vec![[0.0; 8]; (size.x * size.y) as usize];
+        let normal_matrix = Mat3::from_mat4(mesh.transform).inverse().transpose();
+
+        for triangle in 0..mesh.triangle_count() {
+            let indices = [0, 1, 2].map(|vertex| mesh.vertex_index(triangle, vertex));
+
+            let uvs = indices.map(|index| mesh.uvs[index] * size.as_vec2());
+            let positions =
+                indices.map(|index| mesh.transform.transform_point3(mesh.positions[index]));
+            let face_normal = (positions[1] - positions[0]).cross(positions[2] - positions[0]);
+            let normals = indices.map(|index| match mesh.normals.get(index) {
+                Some(&normal) => normal_matrix * normal,
+                None => face_normal,
+            });
+
+            let area = (uvs[1] - uvs[0]).perp_dot(uvs[2] - uvs[0]);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let min = uvs[0].min(uvs[1]).min(uvs[2]).floor().max(Vec2::ZERO);
+            let max = uvs[0].max(uvs[1]).max(uvs[2]).ceil().min(size.as_vec2());
+
+            for y in min.y as u32..max.y as u32 {
+                for x in min.x as u32..max.x as u32 {
+                    let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+                    let w0 = (uvs[2] - uvs[1]).perp_dot(p - uvs[1]) / area;
+                    let w1 = (uvs[0] - uvs[2]).perp_dot(p - uvs[2]) / area;
+                    let w2 = 1.0 - w0 - w1;
+
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+
+                    let position = positions[0] * w0 + positions[1] * w1 + positions[2] * w2;
+                    let normal =
+                        (normals[0] * w0 + normals[1] * w1 + normals[2] * w2).normalize_or_zero();
+
+                    texels[(y * size.x + x) as usize] = [
+                        position.x, position.y, position.z, 1.0, normal.x, normal.y, normal.z, 0.0,
+                    ];
+                }
+            }
+        }
+
+        texels
+    }
+
+    fn read_buffer(device: &Device, queue: &Queue, buffer: &Buffer, size: u64) -> Vec<f32> {
+        let read_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Read Lightmap"),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Read Lightmap"),
+        });
+        encoder.copy_buffer_to_buffer(buffer, 0, &read_buffer, 0, size);
+
+        queue.submit(std::iter::once(encoder.finish()));
+        device.poll(Maintain::Wait);
+
+        read_buffer.slice(..).map_async(MapMode::Read, |_| {});
+        device.poll(Maintain::Wait);
+
+        let mapped = read_buffer.slice(..).get_mapped_range();
+        bytemuck::cast_slice(&mapped).to_vec()
+    }
+
+    /// Bakes the lightmaps of the meshes, returning the lightmap of each mesh in
+    /// [`LightmapBaker::meshes`], or `None` for meshes without a
+    /// [`LightmapMesh::lightmap_size`].
+    pub fn bake(&self, device: &Device, queue: &Queue) -> Vec<Option<LightmapData>> {
+        let bvh = Bvh::new(self.triangles());
+
+        let mut triangles = bvh
+            .triangles
+            .iter()
+            .map(BvhTriangle::raw)
+            .collect::<Vec<_>>();
+        if triangles.is_empty() {
+            triangles.push([0.0; 12]);
+        }
+
+        let materials = (self.meshes.iter())
+            .map(|mesh| {
+                let (albedo, emissive) = (mesh.albedo, mesh.emissive);
+                [
+                    albedo.x, albedo.y, albedo.z, 0.0, emissive.x, emissive.y, emissive.z, 0.0,
+                ]
+            })
+            .chain(std::iter::once([0.0; 8]))
+            .collect::<Vec<_>>();
+
+        let lights = (self.lights.iter().map(LightmapLight::raw))
+            .chain(std::iter::once([0.0; 8]))
+            .collect::<Vec<_>>();
+
+        let storage_buffer = |label: &str, contents: &[u8]| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: BufferUsages::STORAGE,
+            })
+        };
+
+        let triangle_buffer =
+            storage_buffer("Lightmap Triangles", bytemuck::cast_slice(&triangles));
+        let material_buffer =
+            storage_buffer("Lightmap Materials", bytemuck::cast_slice(&materials));
+        let node_buffer = storage_buffer("Lightmap Nodes", bytemuck::cast_slice(&bvh.nodes));
+        let light_buffer = storage_buffer("Lightmap Lights", bytemuck::cast_slice(&lights));
+
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Lightmap Params"),
+            size: std::mem::size_of::<[u32; 8]>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding: u32, read_only: bool| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, true),
+                storage_entry(5, true),
+                storage_entry(6, false),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Lightmap Bake Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &device.create_shader_module(lumi_core::include_wgsl!("lightmap.wgsl")),
+            entry_point: "bake",
+        });
+
+        let mut lightmaps = Vec::with_capacity(self.meshes.len());
+
+        for (index, mesh) in self.meshes.iter().enumerate() {
+            let size = match mesh.lightmap_size {
+                Some(size) if size.x > 0 && size.y > 0 => size,
+                _ => {
+                    lightmaps.push(None);
+                    continue;
+                }
+            };
+
+            let texel_count = size.x * size.y;
+            let texels = Self::texels(mesh, size);
+
+            let texel_buffer = storage_buffer("Lightmap Texels", bytemuck::cast_slice(&texels));
+            let output_size = texel_count as u64 * 16;
+            let output_buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("Lightmap Output"),
+                size: output_size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: triangle_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: material_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: node_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: light_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 5,
+                        resource: texel_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 6,
+                        resource: output_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let workgroups = (texel_count + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE;
+            let workgroups_x = u32::min(workgroups, Self::MAX_WORKGROUPS);
+            let workgroups_y = (workgroups + workgroups_x - 1) / workgroups_x;
+
+            // the samples are split into batches, to keep each submission short
+            for sample_offset in (0..self.samples).step_by(Self::BATCH_SIZE as usize) {
+                let samples = u32::min(Self::BATCH_SIZE, self.samples - sample_offset);
+                let params = self.raw_params(texel_count, sample_offset, samples);
+                queue.write_buffer(&params_buffer, 0, bytemuck::cast_slice(&params));
+
+                let mut encoder = device.create_command_encoder(&Default::default());
+                let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+
+                compute_pass.set_pipeline(&pipeline);
+                compute_pass.set_bind_group(0, &bind_group, &[]);
+                compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+
+                drop(compute_pass);
+
+                queue.submit(std::iter::once(encoder.finish()));
+                device.poll(Maintain::Wait);
+
+                log::trace!(
+                    "Baked lightmap {}: {}/{}",
+                    index,
+                    sample_offset + samples,
+                    self.samples
+                );
+            }
+
+            let output = Self::read_buffer(device, queue, &output_buffer, output_size);
+
+            let mut lightmap = LightmapData {
+                width: size.x,
+                height: size.y,
+                texels: Vec::with_capacity(texel_count as usize),
+            };
+
+            let mut covered = Vec::with_capacity(texel_count as usize);
+
+            for (texel, output) in texels.iter().zip(output.chunks_exact(4)) {
+                let samples = f32::max(output[3], 1.0);
+                lightmap
+                    .texels
+                    .push(Vec3::new(output[0], output[1], output[2]) / samples);
+                covered.push(texel[3] != 0.0);
+            }
+
+            lightmap.dilate(&mut covered, 2);
+            lightmaps.push(Some(lightmap));
+        }
+
+        lightmaps
+    }
+}
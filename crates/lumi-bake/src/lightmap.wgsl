@@ -0,0 +1,311 @@
+let PI = 3.1415926535897932384626433832795;
+
+let WORKGROUP_SIZE = 64u;
+let STACK_SIZE = 32u;
+let RAY_OFFSET = 0.001;
+
+// must match `LightmapBaker::raw_params`
+struct Params {
+	sky: vec3<f32>,
+	texel_count: u32,
+	light_count: u32,
+	sample_offset: u32,
+	samples: u32,
+	bounces: u32,
+}
+
+struct Triangle {
+	// the mesh index is stored in the w of `a`
+	a: vec4<f32>,
+	b: vec4<f32>,
+	c: vec4<f32>,
+}
+
+struct Material {
+	albedo: vec4<f32>,
+	emissive: vec4<f32>,
+}
+
+// nodes are stored depth first, the left child of an inner node directly follows it
+struct Node {
+	min: vec3<f32>,
+	// the first triangle of a leaf, or the right child of an inner node
+	index: u32,
+	max: vec3<f32>,
+	// the number of triangles in a leaf, zero for inner nodes
+	count: u32,
+}
+
+// a light is directional when `position.w` is zero, where `position.xyz` is the direction
+// towards the light, otherwise it's a point light with a range of `color.w`
+struct Light {
+	position: vec4<f32>,
+	color: vec4<f32>,
+}
+
+// the position and normal of a texel of the lightmap, texels not covered by the mesh have a
+// `position.w` of zero
+struct Texel {
+	position: vec4<f32>,
+	normal: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var<storage, read> triangles: array<Triangle>;
+
+@group(0) @binding(2)
+var<storage, read> materials: array<Material>;
+
+@group(0) @binding(3)
+var<storage, read> nodes: array<Node>;
+
+@group(0) @binding(4)
+var<storage, read> lights: array<Light>;
+
+@group(0) @binding(5)
+var<storage, read> texels: array<Texel>;
+
+@group(0) @binding(6)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct Hit {
+	distance: f32,
+	index: u32,
+}
+
+// pcg hash, see https://www.jcgt.org/published/0009/03/02/
+fn hash(value: u32) -> u32 {
+	let state = value * 747796405u + 2891336453u;
+	let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+	return (word >> 22u) ^ word;
+}
+
+fn random(seed: ptr<function, u32>) -> f32 {
+	*seed = hash(*seed);
+	return f32(*seed) / 4294967295.0;
+}
+
+// a direction around `n`, distributed by the cosine of the angle to `n`
+fn sample_cosine(n: vec3<f32>, seed: ptr<function, u32>) -> vec3<f32> {
+	let u = random(seed);
+	let v = random(seed);
+
+	let phi = 2.0 * PI * u;
+	let r = sqrt(v);
+
+	let up = select(vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(1.0, 0.0, 0.0), abs(n.z) > 0.999);
+	let tangent = normalize(cross(up, n));
+	let bitangent = cross(n, tangent);
+
+	return normalize(tangent * cos(phi) * r + bitangent * sin(phi) * r + n * sqrt(1.0 - v));
+}
+
+fn intersect_aabb(origin: vec3<f32>, inv_direction: vec3<f32>, node: Node, max_distance: f32) -> bool {
+	let t0 = (node.min - origin) * inv_direction;
+	let t1 = (node.max - origin) * inv_direction;
+
+	let near = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), min(t0.z, t1.z));
+	let far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), max(t0.z, t1.z));
+
+	return near <= far && far >= 0.0 && near < max_distance;
+}
+
+// moller-trumbore, returns a negative distance on a miss
+fn intersect_triangle(origin: vec3<f32>, direction: vec3<f32>, tri: Triangle) -> f32 {
+	let edge_1 = tri.b.xyz - tri.a.xyz;
+	let edge_2 = tri.c.xyz - tri.a.xyz;
+
+	let p = cross(direction, edge_2);
+	let determinant = dot(edge_1, p);
+
+	if abs(determinant) < 0.0000001 {
+		return -1.0;
+	}
+
+	let inv_determinant = 1.0 / determinant;
+	let s = origin - tri.a.xyz;
+	let u = dot(s, p) * inv_determinant;
+
+	if u < 0.0 || u > 1.0 {
+		return -1.0;
+	}
+
+	let q = cross(s, edge_1);
+	let v = dot(direction, q) * inv_determinant;
+
+	if v < 0.0 || u + v > 1.0 {
+		return -1.0;
+	}
+
+	return dot(edge_2, q) * inv_determinant;
+}
+
+// finds the closest triangle along the ray, or any triangle when `any_hit` is set
+fn trace(origin: vec3<f32>, direction: vec3<f32>, max_distance: f32, any_hit: bool) -> Hit {
+	var hit: Hit;
+	hit.distance = max_distance;
+	hit.index = 0xffffffffu;
+
+	let inv_direction = 1.0 / direction;
+
+	var stack: array<u32, 32>;
+	var stack_size = 1u;
+	stack[0] = 0u;
+
+	while stack_size > 0u {
+		stack_size -= 1u;
+		let node = nodes[stack[stack_size]];
+		let index = stack[stack_size];
+
+		if !intersect_aabb(origin, inv_direction, node, hit.distance) {
+			continue;
+		}
+
+		if node.count == 0u {
+			if stack_size + 2u > STACK_SIZE {
+				continue;
+			}
+
+			stack[stack_size] = node.index;
+			stack[stack_size + 1u] = index + 1u;
+			stack_size += 2u;
+			continue;
+		}
+
+		for (var i = node.index; i < node.index + node.count; i += 1u) {
+			let distance = intersect_triangle(origin, direction, triangles[i]);
+
+			if distance > 0.0 && distance < hit.distance {
+				hit.distance = distance;
+				hit.index = i;
+
+				if any_hit {
+					return hit;
+				}
+			}
+		}
+	}
+
+	return hit;
+}
+
+fn is_visible(origin: vec3<f32>, direction: vec3<f32>, distance: f32) -> bool {
+	return trace(origin, direction, distance, true).index == 0xffffffffu;
+}
+
+// the irradiance from the lights at `position`
+fn direct_irradiance(position: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+	var irradiance = vec3<f32>(0.0);
+
+	for (var i = 0u; i < params.light_count; i += 1u) {
+		let light = lights[i];
+
+		if light.position.w == 0.0 {
+			let l = light.position.xyz;
+			let nol = dot(n, l);
+
+			if nol > 0.0 && is_visible(position, l, 3.402823e+38) {
+				irradiance += light.color.rgb * nol;
+			}
+
+			continue;
+		}
+
+		let to_light = light.position.xyz - position;
+		let distance_squared = dot(to_light, to_light);
+		let distance = sqrt(distance_squared);
+		let l = to_light / distance;
+		let nol = dot(n, l);
+
+		if nol <= 0.0 || distance >= light.color.w {
+			continue;
+		}
+
+		let factor = distance_squared / (light.color.w * light.color.w);
+		let attenuation = pow(saturate(1.0 - factor * factor), 2.0) / max(distance_squared, 0.0001);
+
+		if is_visible(position, l, distance) {
+			irradiance += light.color.rgb * attenuation * nol;
+		}
+	}
+
+	return irradiance;
+}
+
+// the radiance arriving at `origin` from `direction`, excluding the lights themselves
+fn trace_path(origin: vec3<f32>, direction: vec3<f32>, seed: ptr<function, u32>) -> vec3<f32> {
+	var radiance = vec3<f32>(0.0);
+	var throughput = vec3<f32>(1.0);
+	var ray_origin = origin;
+	var ray_direction = direction;
+
+	for (var bounce = 0u; bounce <= params.bounces; bounce += 1u) {
+		let hit = trace(ray_origin, ray_direction, 3.402823e+38, false);
+
+		if hit.index == 0xffffffffu {
+			radiance += throughput * params.sky;
+			break;
+		}
+
+		let hit_triangle = triangles[hit.index];
+		let material = materials[bitcast<u32>(hit_triangle.a.w)];
+
+		var n = normalize(cross(hit_triangle.b.xyz - hit_triangle.a.xyz, hit_triangle.c.xyz - hit_triangle.a.xyz));
+		n = select(n, -n, dot(n, ray_direction) > 0.0);
+
+		let position = ray_origin + ray_direction * hit.distance + n * RAY_OFFSET;
+
+		// diffuse surfaces reflect albedo / pi of the irradiance
+		radiance += throughput * material.emissive.rgb;
+		radiance += throughput * material.albedo.rgb / PI * direct_irradiance(position, n);
+
+		// with cosine weighted directions the cosine and pdf cancel out to pi
+		throughput *= material.albedo.rgb;
+
+		if bounce == params.bounces || all(throughput <= vec3<f32>(0.0)) {
+			break;
+		}
+
+		ray_origin = position;
+		ray_direction = sample_cosine(n, seed);
+	}
+
+	return radiance;
+}
+
+// accumulates `params.samples` paths per texel into `output`, the average radiance of the paths
+// is the irradiance divided by pi
+@compute @workgroup_size(64, 1, 1)
+fn bake(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+	let index = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+
+	if index >= params.texel_count {
+		return;
+	}
+
+	let texel = texels[index];
+
+	if texel.position.w == 0.0 {
+		return;
+	}
+
+	let n = texel.normal.xyz;
+	let origin = texel.position.xyz + n * RAY_OFFSET;
+
+	var radiance = vec3<f32>(0.0);
+
+	for (var i = 0u; i < params.samples; i += 1u) {
+		var seed = hash(index ^ hash(params.sample_offset + i));
+		let direction = sample_cosine(n, &seed);
+
+		radiance += trace_path(origin, direction, &seed);
+	}
+
+	output[index] += vec4<f32>(radiance, f32(params.samples));
+}
@@ -1,9 +1,10 @@
 use std::path::Path;
 
+use gltf::json::Value;
 use lumi_core::{FilterMode, Image, ImageData, TextureFormat};
-use lumi_material::{Primitive, Primitives, StandardMaterial};
+use lumi_material::{AlphaMode, Primitives, StandardMaterial, UnlitMaterial};
 use lumi_mesh::Mesh;
-use lumi_util::math::Mat4;
+use lumi_util::math::{Mat4, Vec3, Vec4};
 
 fn wrapping_to_address(mode: gltf::texture::WrappingMode) -> lumi_core::AddressMode {
     match mode {
@@ -17,7 +18,12 @@ pub struct GltfData {
     pub document: gltf::Document,
     pub textures: Vec<Image>,
     pub materials: Vec<StandardMaterial>,
+    /// The materials using `KHR_materials_unlit`, indexed like `materials`.
+    pub unlit_materials: Vec<Option<UnlitMaterial>>,
+    /// The primitives of every mesh not using an unlit material.
     pub meshes: Vec<Primitives>,
+    /// The primitives of every mesh using an unlit material.
+    pub unlit_meshes: Vec<Primitives<UnlitMaterial>>,
 }
 
 impl GltfData {
@@ -30,7 +36,9 @@ impl GltfData {
             document,
             textures: Vec::new(),
             materials: Vec::new(),
+            unlit_materials: Vec::new(),
             meshes: Vec::new(),
+            unlit_meshes: Vec::new(),
         };
 
         for texture in this.document.textures() {
@@ -39,13 +47,17 @@ impl GltfData {
         }
 
         for material in this.document.materials() {
+            let unlit = Self::load_unlit_material(&material);
+            this.unlit_materials.push(unlit);
+
             let material = Self::load_material(material, &this.textures);
             this.materials.push(material);
         }
 
         for mesh in this.document.meshes() {
-            let mesh = this.load_mesh(mesh, buffer_data);
+            let (mesh, unlit_mesh) = this.load_mesh(mesh, buffer_data);
             this.meshes.push(mesh);
+            this.unlit_meshes.push(unlit_mesh);
         }
 
         this
@@ -57,28 +69,38 @@ impl GltfData {
         Ok(Self::new(document, &buffers, &images))
     }
 
+    /// Creates the primitives of the default scene, except those using unlit materials.
     pub fn create_primitives(&self) -> Primitives {
-        let mut primitives = Primitives::default();
+        self.create_scene_primitives(&self.meshes)
+    }
+
+    /// Creates the primitives of the default scene using unlit materials.
+    pub fn create_unlit_primitives(&self) -> Primitives<UnlitMaterial> {
+        self.create_scene_primitives(&self.unlit_meshes)
+    }
+
+    fn create_scene_primitives<T: Clone>(&self, meshes: &[Primitives<T>]) -> Primitives<T> {
+        let mut primitives = Primitives::new();
 
         if let Some(scene) = self.document.default_scene() {
             for node in scene.nodes() {
-                self.append_mesh_node(&mut primitives, node, Mat4::IDENTITY);
+                Self::append_mesh_node(meshes, &mut primitives, node, Mat4::IDENTITY);
             }
         }
 
         primitives
     }
 
-    fn append_mesh_node(
-        &self,
-        primitives: &mut Primitives,
+    fn append_mesh_node<T: Clone>(
+        meshes: &[Primitives<T>],
+        primitives: &mut Primitives<T>,
         node: gltf::Node,
         global_transform: Mat4,
     ) {
         let transform = global_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
 
         if let Some(mesh) = node.mesh() {
-            let mesh = &self.meshes[mesh.index()];
+            let mesh = &meshes[mesh.index()];
 
             for primitive in mesh.primitives.iter() {
                 let mut mesh = primitive.mesh.clone();
@@ -89,7 +111,7 @@ impl GltfData {
         }
 
         for child in node.children() {
-            self.append_mesh_node(primitives, child, transform);
+            Self::append_mesh_node(meshes, primitives, child, transform);
         }
     }
 
@@ -178,31 +200,159 @@ impl GltfData {
             standard.emissive_map = Some(image);
         }
 
+        if let Some(occlusion) = material.occlusion_texture() {
+            let image = textures[occlusion.texture().index()].clone();
+            standard.occlusion_texture = Some(image);
+            standard.occlusion_strength = occlusion.strength();
+        }
+
         if let Some(transmission) = material.transmission() {
             standard.transmission = transmission.transmission_factor();
+
+            if let Some(texture) = transmission.transmission_texture() {
+                let image = textures[texture.texture().index()].clone();
+                standard.transmission_texture = Some(image);
+            }
+        }
+
+        if let Some(clearcoat) = material.extension_value("KHR_materials_clearcoat") {
+            let factor = |name: &str| clearcoat.get(name).and_then(Value::as_f64);
+            let texture = |name: &str| {
+                let index = clearcoat.get(name)?.get("index")?.as_u64()?;
+                textures.get(index as usize).cloned()
+            };
+
+            standard.clearcoat = factor("clearcoatFactor").unwrap_or(0.0) as f32;
+            standard.clearcoat_roughness = factor("clearcoatRoughnessFactor").unwrap_or(0.0) as f32;
+            standard.clearcoat_texture = texture("clearcoatTexture");
+            standard.clearcoat_roughness_texture = texture("clearcoatRoughnessTexture");
+            standard.clearcoat_normal_map = texture("clearcoatNormalTexture");
+        }
+
+        if let Some(sheen) = material.extension_value("KHR_materials_sheen") {
+            let factor = |name: &str| sheen.get(name).and_then(Value::as_f64);
+            let texture = |name: &str| {
+                let index = sheen.get(name)?.get("index")?.as_u64()?;
+                textures.get(index as usize).cloned()
+            };
+
+            if let Some(color) = sheen.get("sheenColorFactor").and_then(Value::as_array) {
+                let channel = |i: usize| color.get(i).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+                standard.sheen_color = Vec3::new(channel(0), channel(1), channel(2));
+            }
+
+            standard.sheen_roughness = factor("sheenRoughnessFactor").unwrap_or(0.0) as f32;
+            standard.sheen_color_texture = texture("sheenColorTexture");
+            standard.sheen_roughness_texture = texture("sheenRoughnessTexture");
+        }
+
+        if let Some(anisotropy) = material.extension_value("KHR_materials_anisotropy") {
+            let factor = |name: &str| anisotropy.get(name).and_then(Value::as_f64);
+            let texture = |name: &str| {
+                let index = anisotropy.get(name)?.get("index")?.as_u64()?;
+                textures.get(index as usize).cloned()
+            };
+
+            standard.anisotropy = factor("anisotropyStrength").unwrap_or(0.0) as f32;
+            standard.anisotropy_rotation = factor("anisotropyRotation").unwrap_or(0.0) as f32;
+            standard.anisotropy_texture = texture("anisotropyTexture");
         }
 
+        if let Some(volume) = material.volume() {
+            standard.thickness = volume.thickness_factor();
+
+            if let Some(texture) = volume.thickness_texture() {
+                let image = textures[texture.texture().index()].clone();
+                standard.thickness_texture = Some(image);
+            }
+
+            // white light takes on the attenuation color after traveling the attenuation distance
+            let attenuation = volume.attenuation_color().map(|c| -f32::ln(c.max(0.0001)));
+            standard.absorption = Vec3::from(attenuation) / volume.attenuation_distance();
+        }
+
+        standard.ior = material.ior().unwrap_or(1.5);
+
+        // the reflectance of dielectrics is derived from the ior, scaled by the specular factor
+        let mut f0 = f32::powi((standard.ior - 1.0) / (standard.ior + 1.0), 2);
+
+        if let Some(specular) = material.specular() {
+            f0 *= specular.specular_factor();
+            standard.specular_color = specular.specular_color_factor().into();
+
+            if let Some(texture) = specular.specular_texture() {
+                let image = textures[texture.texture().index()].clone();
+                standard.specular_texture = Some(image);
+            }
+
+            if let Some(texture) = specular.specular_color_texture() {
+                let image = textures[texture.texture().index()].clone();
+                standard.specular_color_texture = Some(image);
+            }
+        }
+
+        standard.reflectance = f32::sqrt(f0 / 0.16);
+
         standard.base_color = pbr.base_color_factor().into();
-        standard.alpha_cutoff = material.alpha_cutoff().unwrap_or(-1.0);
+        standard.alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+        standard.alpha_cutoff = match standard.alpha_mode {
+            AlphaMode::Mask => material.alpha_cutoff().unwrap_or(0.5),
+            _ => -1.0,
+        };
+        standard.double_sided = material.double_sided();
         standard.metallic = pbr.metallic_factor();
         standard.roughness = pbr.roughness_factor();
         standard.emissive = material.emissive_factor().into();
 
+        if let Some(strength) = material.emissive_strength() {
+            standard.emissive_factor *= strength;
+        }
+
         standard
     }
 
-    fn load_mesh(&self, mesh: gltf::Mesh, data: &[gltf::buffer::Data]) -> Primitives {
-        let mut primitives = Primitives::default();
+    fn load_unlit_material(material: &gltf::Material) -> Option<UnlitMaterial> {
+        if !material.unlit() {
+            return None;
+        }
+
+        let base_color = Vec4::from(material.pbr_metallic_roughness().base_color_factor());
+        Some(UnlitMaterial::new(base_color.truncate()))
+    }
+
+    fn load_mesh(
+        &self,
+        mesh: gltf::Mesh,
+        data: &[gltf::buffer::Data],
+    ) -> (Primitives, Primitives<UnlitMaterial>) {
+        let mut primitives = Primitives::new();
+        let mut unlit_primitives = Primitives::new();
 
         for primitive in mesh.primitives() {
-            let primitive = self.load_primitive(primitive, data);
-            primitives.primitives.push(primitive);
+            let index = primitive.material().index();
+            let mesh = Self::load_primitive(primitive, data);
+
+            match index.and_then(|index| self.unlit_materials[index]) {
+                Some(material) => unlit_primitives.add(material, mesh),
+                None => {
+                    let material = match index {
+                        Some(index) => self.materials[index].clone(),
+                        None => Default::default(),
+                    };
+
+                    primitives.add(material, mesh);
+                }
+            }
         }
 
-        primitives
+        (primitives, unlit_primitives)
     }
 
-    fn load_primitive(&self, primitive: gltf::Primitive, data: &[gltf::buffer::Data]) -> Primitive {
+    fn load_primitive(primitive: gltf::Primitive, data: &[gltf::buffer::Data]) -> Mesh {
         let reader = primitive.reader(|buffer| Some(&data[buffer.index()]));
 
         let mut mesh = Mesh::new();
@@ -219,6 +369,10 @@ impl GltfData {
             mesh.insert_attribute(Mesh::UV_0, vec![[0.0, 0.0]; len]);
         }
 
+        if let Some(uvs) = reader.read_tex_coords(1) {
+            mesh.insert_attribute(Mesh::UV_1, uvs.into_f32().collect::<Vec<_>>());
+        }
+
         if let Some(normals) = reader.read_normals() {
             mesh.insert_attribute(Mesh::NORMAL, normals.collect::<Vec<_>>());
         }
@@ -230,14 +384,6 @@ impl GltfData {
             mesh.insert_indices(indices.into_u32().collect::<Vec<_>>());
         }
 
-        let material = if let Some(index) = primitive.material().index() {
-            self.materials[index].clone()
-        } else {
-            Default::default()
-        };
-
-        let primitive = Primitive { mesh, material };
-
-        primitive
+        mesh
     }
 }
@@ -1,7 +1,7 @@
 mod data;
 
 pub use data::*;
-use lumi_material::Primitives;
+use lumi_material::{Primitives, UnlitMaterial};
 
 use std::path::Path;
 
@@ -15,3 +15,10 @@ impl OpenGltfExt for Primitives {
         Ok(data.create_primitives())
     }
 }
+
+impl OpenGltfExt for Primitives<UnlitMaterial> {
+    fn open_gltf(path: impl AsRef<Path>) -> Result<Self, gltf::Error> {
+        let data = GltfData::open(path)?;
+        Ok(data.create_unlit_primitives())
+    }
+}
@@ -292,6 +292,7 @@ impl BindingAttribute {
         if let Some(view_dimension) = self.view_dimension {
             changes.push(quote! {match &mut entry.ty {
                 #lumi_bind::BindingType::Texture { view_dimension, .. } => *view_dimension = #view_dimension,
+                #lumi_bind::BindingType::StorageTexture { view_dimension, .. } => *view_dimension = #view_dimension,
                 _ => {}
             }});
         }
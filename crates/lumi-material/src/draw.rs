@@ -5,14 +5,17 @@ use lumi_bind::Binding;
 use lumi_id::Id;
 use lumi_mesh::Mesh;
 use lumi_renderer::{
-    Draw, Entity, Extract, IntegratedBrdf, OpaqueDraws, PreparedCamera, PreparedEnvironment,
-    PreparedLights, PreparedMeshes, PreparedShadows, PreparedTransform, Query, RenderDevice,
-    RenderQueue, ScreenSpaceTarget, TransparentDraws, View, Without,
+    Draw, Entity, Extract, IntegratedBrdf, Lightmap, LtcTables, OpaqueDraws, PreparedCamera,
+    PreparedClusters, PreparedEnvironment, PreparedIrradianceVolumes, PreparedLightTextures,
+    PreparedLightmap, PreparedLights, PreparedMeshes, PreparedProbes, PreparedReflectionProbes,
+    PreparedShadows, PreparedTransform, Query, RenderDevice, RenderQueue, ScreenSpaceTarget,
+    TransparentDraws, View, Without,
 };
 use lumi_shader::ShaderProcessor;
 use lumi_util::{smallvec::SmallVec, HashMap};
 use shiv::{
-    query::{Changed, QueryItem, ReadOnlyWorldQuery},
+    change_detection::Mut,
+    query::{Changed, Or, QueryItem, ReadOnlyWorldQuery, With},
     storage::SparseArray,
     system::{Commands, Res, ResMut, SystemParam},
     world::Component,
@@ -70,9 +73,19 @@ pub struct MaterialRenderStates {
 pub fn extract_material_system<T: ExtractMaterials>(
     mut commands: Commands,
     extract_query: Extract<Query<(Entity, &T), Changed<T>>>,
+    lightmap_query: Extract<Query<(), With<Lightmap>>>,
     mut material_query: Query<&mut T>,
     state_query: Query<(), Without<MaterialRenderStates>>,
+    prepared_lightmap_query: Query<Entity, (With<T>, With<PreparedLightmap>)>,
 ) {
+    // the pipeline of a material changes when its lightmap is removed, which isn't detected by
+    // the filters in prepare_material_system
+    for entity in prepared_lightmap_query.iter() {
+        if !lightmap_query.contains(entity) {
+            Mut::set_changed(&mut material_query.get_mut(entity).unwrap());
+        }
+    }
+
     for (entity, extract) in extract_query.iter() {
         let extracted = extract.extract();
 
@@ -93,9 +106,14 @@ pub fn extract_material_system<T: ExtractMaterials>(
 #[derive(SystemParam)]
 pub struct PreparedParams<'w> {
     pub lights: Res<'w, PreparedLights>,
+    pub light_textures: Res<'w, PreparedLightTextures>,
     pub shadows: Res<'w, PreparedShadows>,
     pub environment: Res<'w, PreparedEnvironment>,
+    pub probes: Res<'w, PreparedProbes>,
+    pub reflection_probes: Res<'w, PreparedReflectionProbes>,
+    pub irradiance_volumes: Res<'w, PreparedIrradianceVolumes>,
     pub integrated_brdf: Res<'w, IntegratedBrdf>,
+    pub ltc_tables: Res<'w, LtcTables>,
 }
 
 pub fn prepare_material_system<T: ExtractMaterials>(
@@ -105,19 +123,31 @@ pub fn prepare_material_system<T: ExtractMaterials>(
     prepared: PreparedParams,
     mut shader_processor: ResMut<ShaderProcessor>,
     mut pipelines: ResMut<PreparedMaterialPipelines>,
-    camera_query: Query<(&PreparedCamera, &ScreenSpaceTarget)>,
-    query: Query<(Entity, &T, &PreparedTransform), Changed<T>>,
+    camera_query: Query<(&PreparedCamera, &PreparedClusters, &ScreenSpaceTarget)>,
+    query: Query<
+        (Entity, &T, &PreparedTransform, Option<&PreparedLightmap>),
+        Or<(Changed<T>, Changed<PreparedLightmap>)>,
+    >,
+    all_query: Query<(Entity, &T, &PreparedTransform, Option<&PreparedLightmap>)>,
     mut state_query: Query<&mut MaterialRenderStates>,
     changed_screen_space: Query<Entity, Changed<ScreenSpaceTarget>>,
 ) {
-    let (prepared_camera, screen_space_target) = camera_query.get(view.camera).unwrap();
+    let (prepared_camera, prepared_clusters, screen_space_target) =
+        camera_query.get(view.camera).unwrap();
     let screen_space_bindings = screen_space_target.bindings();
 
     let screen_space_changed = changed_screen_space.contains(view.camera);
 
-    for (entity, extract, transform) in query.iter() {
+    let mut prepare = |entity: Entity,
+                       extract: &T,
+                       transform: &PreparedTransform,
+                       lightmap: Option<&PreparedLightmap>| {
         for (i, material) in extract.iter().enumerate() {
-            let key = PreparedMaterialPipelineKey::new(material, view.frame_buffer.sample_count());
+            let key = PreparedMaterialPipelineKey::new(
+                material,
+                view.frame_buffer.sample_count(),
+                lightmap.is_some(),
+            );
 
             let pipeline =
                 pipelines.get_or_create::<T::Material>(&device, &key, &mut shader_processor);
@@ -129,11 +159,17 @@ pub fn prepare_material_system<T: ExtractMaterials>(
                 let mut bindings = pipeline.bindings_layout.create_bindings(&device);
 
                 bindings.bind(&device, &queue, prepared_camera);
+                bindings.bind(&device, &queue, prepared_clusters);
                 bindings.bind(&device, &queue, prepared.integrated_brdf.deref());
+                bindings.bind(&device, &queue, prepared.ltc_tables.deref());
                 bindings.bind(&device, &queue, transform);
 
                 bindings.bind(&device, &queue, prepared.lights.deref());
+                bindings.bind(&device, &queue, prepared.light_textures.deref());
                 bindings.bind(&device, &queue, prepared.environment.deref());
+                bindings.bind(&device, &queue, prepared.probes.deref());
+                bindings.bind(&device, &queue, prepared.reflection_probes.deref());
+                bindings.bind(&device, &queue, prepared.irradiance_volumes.deref());
                 bindings.bind(&device, &queue, prepared.shadows.deref());
                 bindings.bind(&device, &queue, &screen_space_bindings);
 
@@ -144,18 +180,44 @@ pub fn prepare_material_system<T: ExtractMaterials>(
             let bindings = state.get_mut(&view.camera).unwrap();
 
             bindings.bind(&device, &queue, material);
+
+            if let Some(lightmap) = lightmap {
+                bindings.bind(&device, &queue, lightmap);
+            }
+        }
+    };
+
+    // the screen space target changes when a camera is first rendered or its frame buffer is
+    // resized, every material then needs bindings for the camera
+    if screen_space_changed {
+        for (entity, extract, transform, lightmap) in all_query.iter() {
+            prepare(entity, extract, transform, lightmap);
+        }
+    } else {
+        for (entity, extract, transform, lightmap) in query.iter() {
+            prepare(entity, extract, transform, lightmap);
         }
     }
 
     let mut update_bindings = false;
 
     let lights_changed = prepared.lights.bindings_changed;
+    let light_textures_changed = prepared.light_textures.bindings_changed;
     let shadows_changed = prepared.shadows.bindings_changed;
     let environment_changed = prepared.environment.is_changed();
+    let probes_changed = prepared.probes.bindings_changed;
+    let reflection_probes_changed = prepared.reflection_probes.bindings_changed;
+    let irradiance_volumes_changed = prepared.irradiance_volumes.bindings_changed;
+    let clusters_changed = prepared_clusters.bindings_changed;
 
     update_bindings |= lights_changed;
+    update_bindings |= light_textures_changed;
     update_bindings |= shadows_changed;
     update_bindings |= environment_changed;
+    update_bindings |= probes_changed;
+    update_bindings |= reflection_probes_changed;
+    update_bindings |= irradiance_volumes_changed;
+    update_bindings |= clusters_changed;
     update_bindings |= screen_space_changed;
 
     if update_bindings {
@@ -167,6 +229,10 @@ pub fn prepare_material_system<T: ExtractMaterials>(
                     bindings.bind(&device, &queue, prepared.lights.deref());
                 }
 
+                if light_textures_changed {
+                    bindings.bind(&device, &queue, prepared.light_textures.deref());
+                }
+
                 if shadows_changed {
                     bindings.bind(&device, &queue, prepared.shadows.deref());
                 }
@@ -175,9 +241,25 @@ pub fn prepare_material_system<T: ExtractMaterials>(
                     bindings.bind(&device, &queue, prepared.environment.deref());
                 }
 
+                if probes_changed {
+                    bindings.bind(&device, &queue, prepared.probes.deref());
+                }
+
+                if reflection_probes_changed {
+                    bindings.bind(&device, &queue, prepared.reflection_probes.deref());
+                }
+
+                if irradiance_volumes_changed {
+                    bindings.bind(&device, &queue, prepared.irradiance_volumes.deref());
+                }
+
                 if screen_space_changed {
                     bindings.bind(&device, &queue, &screen_space_bindings);
                 }
+
+                if clusters_changed {
+                    bindings.bind(&device, &queue, prepared_clusters);
+                }
             }
         }
     }
@@ -206,13 +288,33 @@ pub fn draw_material_system<T: ExtractMaterials>(
     mut opaque_draws: ResMut<OpaqueDraws>,
     mut transparent_draws: ResMut<TransparentDraws>,
     pipelines: Res<PreparedMaterialPipelines>,
-    query: Query<(T::MeshQuery, &PreparedTransform, &MaterialRenderStates)>,
+    camera_query: Query<&PreparedTransform>,
+    query: Query<(
+        T::MeshQuery,
+        &PreparedTransform,
+        &MaterialRenderStates,
+        Option<&PreparedLightmap>,
+    )>,
 ) {
-    for (extract, transform, states) in query.iter() {
+    let camera_layers = match camera_query.get(view.camera) {
+        Some(camera) => camera.render_layers,
+        None => Default::default(),
+    };
+
+    for (extract, transform, states, lightmap) in query.iter() {
+        // skip meshes not visible to the camera
+        if !camera_layers.intersects(&transform.render_layers) {
+            continue;
+        }
+
         for (i, (material, mesh)) in T::mesh_iter(&extract).enumerate() {
             let state = states.get(i).unwrap();
 
-            let key = PreparedMaterialPipelineKey::new(material, view.frame_buffer.sample_count());
+            let key = PreparedMaterialPipelineKey::new(
+                material,
+                view.frame_buffer.sample_count(),
+                lightmap.is_some(),
+            );
             let pipeline = pipelines.get(key.id()).unwrap();
 
             let resolve_pipeline = if material.is_translucent() {
@@ -0,0 +1,300 @@
+use std::mem;
+
+use lumi_mesh::{Mesh, MeshId};
+use lumi_renderer::{
+    Camera, Extract, ExtractStage, ExtractSystem, GlobalTransform, PreparedLights, Query,
+    RawAreaLight, RenderLayers, Renderer, RendererPlugin,
+};
+use lumi_util::{math::Vec3, HashMap};
+use shiv::{
+    schedule::IntoSystemDescriptor,
+    system::{Local, ResMut},
+    world::Component,
+};
+
+use crate::{ExtractMaterials, Primitive, Primitives, StandardMaterial};
+
+/// The emissive factor the pbr shader applies on top of the emissive of materials, see
+/// `default_pbr` in `pbr_types.wgsl`.
+pub const PBR_EMISSIVE_FACTOR: f32 = 8.0;
+
+/// Makes the emissive surfaces of an entity light their surroundings.
+///
+/// Every emissive [`StandardMaterial`] mesh of the entity is approximated by a one sided
+/// rectangular area light, fitted to the mesh in the plane of its average normal. This works
+/// best for flat emitters like signs and screens, the emissive map isn't taken into account.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct EmissiveLight {
+    /// Scales the intensity of the approximated lights.
+    pub intensity: f32,
+    /// The exposure value emissive materials are assumed to be viewed at, see [`Camera::ev100`].
+    pub ev100: f32,
+}
+
+impl Default for EmissiveLight {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            ev100: Camera::default().ev100(),
+        }
+    }
+}
+
+/// A rectangle fitted to a mesh in its local space, emitting along the front faces of the mesh.
+#[derive(Clone, Copy, Debug)]
+pub struct EmissiveRect {
+    pub position: Vec3,
+    /// Half the width of the rectangle.
+    pub right: Vec3,
+    /// Half the height of the rectangle.
+    pub up: Vec3,
+    /// The fraction of the rectangle covered by the mesh.
+    pub coverage: f32,
+}
+
+impl EmissiveRect {
+    /// Returns `None` if the mesh has no positions or no well defined front.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        let positions = mesh.attribute::<[[f32; 3]]>(Mesh::POSITION)?;
+
+        let triangle = |i: usize| -> [Vec3; 3] {
+            let index = |j: usize| match mesh.indices() {
+                Some(indices) => indices[i * 3 + j] as usize,
+                None => i * 3 + j,
+            };
+
+            [
+                Vec3::from(positions[index(0)]),
+                Vec3::from(positions[index(1)]),
+                Vec3::from(positions[index(2)]),
+            ]
+        };
+
+        let triangle_count = match mesh.indices() {
+            Some(indices) => indices.len() / 3,
+            None => positions.len() / 3,
+        };
+
+        // the area weighted normal, pointing in the direction light is emitted
+        let mut normal = Vec3::ZERO;
+        let mut area = 0.0;
+        for i in 0..triangle_count {
+            let [a, b, c] = triangle(i);
+            let cross = (b - a).cross(c - a);
+
+            normal += cross;
+            area += cross.length() / 2.0;
+        }
+
+        let normal = normal.try_normalize()?;
+
+        // align the rectangle with the local x axis where possible
+        let axis = if normal.x.abs() < 0.9 {
+            Vec3::X
+        } else {
+            Vec3::Y
+        };
+        let right = (axis - normal * axis.dot(normal)).normalize();
+        let up = right.cross(normal);
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        for &position in positions {
+            let position = Vec3::from(position);
+            let local = Vec3::new(position.dot(right), position.dot(up), position.dot(normal));
+
+            min = min.min(local);
+            max = max.max(local);
+        }
+
+        let center = (min + max) / 2.0;
+        let extent = (max - min) / 2.0;
+        let rect_area = 4.0 * extent.x * extent.y;
+
+        if rect_area <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            position: right * center.x + up * center.y + normal * center.z,
+            right: right * extent.x,
+            up: up * extent.y,
+            coverage: f32::min(area / rect_area, 1.0),
+        })
+    }
+
+    pub fn raw(
+        &self,
+        transform: &GlobalTransform,
+        material: &StandardMaterial,
+        light: &EmissiveLight,
+    ) -> Option<RawAreaLight> {
+        let luminance = f32::powf(
+            2.0,
+            light.ev100 + material.emissive_exposure_compensation - 3.0,
+        );
+        let emissive = material.emissive
+            * material.emissive_factor
+            * PBR_EMISSIVE_FACTOR
+            * material.base_color.w;
+        let scale = emissive.max_element();
+
+        if scale <= 0.0 {
+            return None;
+        }
+
+        Some(RawAreaLight {
+            position: transform.translation + transform.matrix.mul_vec3(self.position),
+            kind: RawAreaLight::RECT,
+            right: transform.matrix.mul_vec3(self.right),
+            radius: 0.0,
+            up: transform.matrix.mul_vec3(self.up),
+            intensity: scale * luminance * self.coverage * light.intensity,
+            color: emissive / scale,
+            render_layers: RenderLayers::default().bits(),
+        })
+    }
+}
+
+pub fn extract_emissive_light_system<T: ExtractMaterials<Material = StandardMaterial>>(
+    mut prepared_lights: ResMut<PreparedLights>,
+    mut rects: Local<HashMap<MeshId, Option<EmissiveRect>>>,
+    query: Extract<
+        Query<(
+            &EmissiveLight,
+            T::MeshQuery,
+            Option<&GlobalTransform>,
+            Option<&RenderLayers>,
+        )>,
+    >,
+) {
+    let area_light_cap = prepared_lights.area_lights.capacity();
+
+    let mut previous_rects = mem::take(&mut *rects);
+    for (light, item, transform, render_layers) in query.iter() {
+        let transform = transform.copied().unwrap_or_default();
+        let render_layers = render_layers.copied().unwrap_or_default();
+
+        for (material, mesh) in T::mesh_iter(&item) {
+            // fitting the rectangle walks every triangle, so it's only done when the mesh changes
+            let rect = match previous_rects.remove(&mesh.id()) {
+                Some(rect) => rect,
+                None => EmissiveRect::from_mesh(mesh),
+            };
+            rects.insert(mesh.id(), rect);
+
+            let mut raw_light = match rect.and_then(|rect| rect.raw(&transform, material, light)) {
+                Some(raw_light) => raw_light,
+                None => continue,
+            };
+            raw_light.render_layers = render_layers.bits();
+
+            prepared_lights.area_lights.push(raw_light);
+            prepared_lights.light_counts.area_count += 1;
+        }
+    }
+
+    if prepared_lights.area_lights.capacity() != area_light_cap {
+        prepared_lights.bindings_changed = true;
+    }
+}
+
+/// Adds [`EmissiveLight`] support for [`StandardMaterial`] meshes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmissiveLightPlugin;
+
+impl RendererPlugin for EmissiveLightPlugin {
+    fn build(&self, renderer: &mut Renderer) {
+        renderer
+            .extract
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_emissive_light_system::<StandardMaterial>.after(ExtractSystem::Light),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_emissive_light_system::<Primitive>.after(ExtractSystem::Light),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_emissive_light_system::<Primitives>.after(ExtractSystem::Light),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+    }
+
+    fn quad(z: f32) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.insert_positions(vec![
+            Vec3::new(-1.0, -0.5, z),
+            Vec3::new(1.0, -0.5, z),
+            Vec3::new(1.0, 0.5, z),
+            Vec3::new(-1.0, 0.5, z),
+        ]);
+        mesh.insert_indices(vec![0, 1, 2, 0, 2, 3]);
+        mesh
+    }
+
+    /// Rect lights emit along their local -z axis.
+    fn emit_direction(rect: &EmissiveRect) -> Vec3 {
+        -rect.right.cross(rect.up).normalize()
+    }
+
+    #[test]
+    fn test_from_mesh() {
+        let rect = EmissiveRect::from_mesh(&quad(2.0)).unwrap();
+
+        assert_approx(rect.position, Vec3::new(0.0, 0.0, 2.0));
+        assert_approx(rect.right.abs(), Vec3::X);
+        assert_approx(rect.up.abs(), Vec3::Y * 0.5);
+        assert_approx(emit_direction(&rect), Vec3::Z);
+        assert!((rect.coverage - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_from_mesh_winding() {
+        let mut mesh = quad(0.0);
+        mesh.insert_indices(vec![0, 2, 1, 0, 3, 2]);
+
+        let rect = EmissiveRect::from_mesh(&mesh).unwrap();
+        assert_approx(emit_direction(&rect), -Vec3::Z);
+    }
+
+    #[test]
+    fn test_from_mesh_coverage() {
+        // a single triangle without indices covers half of its bounding rectangle
+        let mut mesh = Mesh::new();
+        mesh.insert_positions(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+        ]);
+
+        let rect = EmissiveRect::from_mesh(&mesh).unwrap();
+        assert_approx(emit_direction(&rect), Vec3::X);
+        assert_approx(rect.position, Vec3::new(0.0, 1.0, 1.0));
+        assert!((rect.coverage - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_from_mesh_invalid() {
+        assert!(EmissiveRect::from_mesh(&Mesh::new()).is_none());
+
+        // the front faces of a double sided sheet cancel out
+        let mut mesh = quad(0.0);
+        mesh.insert_indices(vec![0, 1, 2, 0, 2, 3, 0, 2, 1, 0, 3, 2]);
+        assert!(EmissiveRect::from_mesh(&mesh).is_none());
+
+        // degenerate triangles have no area
+        let mut mesh = Mesh::new();
+        mesh.insert_positions(vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0]);
+        assert!(EmissiveRect::from_mesh(&mesh).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 mod draw;
+mod emissive;
 mod material;
 mod prepare;
 mod primitive;
@@ -6,6 +7,7 @@ mod standard;
 mod unlit;
 
 pub use draw::*;
+pub use emissive::*;
 use lumi_mesh::Mesh;
 pub use material::*;
 pub use prepare::*;
@@ -58,7 +60,8 @@ impl<T: ExtractMaterials> RendererPlugin for ExtractMaterialPlugin<T> {
                 prepare_material_system::<T>
                     .label(MaterialSystem::Prepare)
                     .after(ViewSystem::ScreenSpaceResize)
-                    .after(ViewSystem::PrepareCamera),
+                    .after(ViewSystem::PrepareCamera)
+                    .after(ViewSystem::PrepareClusters),
             )
             .add_system_to_stage(
                 ViewStage::Draw,
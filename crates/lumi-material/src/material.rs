@@ -1,11 +1,75 @@
 use std::borrow::Cow;
 
 use lumi_bind::Bind;
-use lumi_core::VertexFormat;
+use lumi_core::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, PrimitiveState, VertexFormat,
+};
 use lumi_mesh::Mesh;
 use lumi_shader::{DefaultShader, Shader, ShaderDefs, ShaderDefsHash, ShaderRef};
 use shiv::world::Component;
 
+/// How the alpha of a material is used when rendering it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    /// Alpha is ignored.
+    Opaque,
+    /// Fragments with alpha below the alpha cutoff are discarded, using alpha to coverage
+    /// when multisampling.
+    Mask,
+    /// Blended over the surfaces behind it.
+    Blend,
+    /// Like [`AlphaMode::Blend`], but the color is already premultiplied by alpha.
+    Premultiplied,
+    /// Added to the surfaces behind it, scaled by alpha.
+    Additive,
+    /// Fragments are discarded in a screen space dither pattern, where alpha is the fraction
+    /// of fragments kept.
+    Dithered,
+}
+
+impl Default for AlphaMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Opaque
+    }
+}
+
+impl AlphaMode {
+    /// Returns true if the material is rendered in the transparent queue.
+    #[inline]
+    pub const fn is_translucent(self) -> bool {
+        matches!(self, Self::Blend | Self::Premultiplied | Self::Additive)
+    }
+
+    /// Returns true if fragments can be discarded, which requires the depth prepass to run
+    /// the fragment shader.
+    #[inline]
+    pub const fn is_masked(self) -> bool {
+        matches!(self, Self::Mask | Self::Dithered)
+    }
+
+    #[inline]
+    pub const fn blend_state(self) -> BlendState {
+        match self {
+            Self::Opaque | Self::Mask | Self::Dithered => BlendState::REPLACE,
+            Self::Blend => BlendState::ALPHA_BLENDING,
+            Self::Premultiplied => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            Self::Additive => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MeshVertexLayout {
     pub attribute: Cow<'static, str>,
@@ -17,6 +81,8 @@ pub struct MeshVertexLayout {
 pub struct MaterialPipeline {
     pub vertex_shader: Shader,
     pub fragment_shader: Shader,
+    /// The shader defs the shaders were processed with.
+    pub shader_defs: ShaderDefs,
     pub vertices: Vec<MeshVertexLayout>,
 }
 
@@ -74,10 +140,84 @@ pub trait Material: Bind + Clone + Component {
                 location: 3,
             },
         ];
+
+        if pipeline.shader_defs.contains(&"LIGHTMAP".into()) {
+            pipeline.vertices.push(MeshVertexLayout {
+                attribute: Mesh::UV_1.into(),
+                format: VertexFormat::Float32x2,
+                location: 4,
+            });
+        }
     }
 
+    /// The entry point of the fragment shader in the depth prepass of masked materials, which
+    /// only has to discard fragments and has no outputs.
+    ///
+    /// The prepass runs without a fragment stage when this is `None`, masked materials should
+    /// provide one, otherwise discarded fragments still write depth.
+    #[inline(always)]
+    fn prepass_entry_point() -> Option<&'static str> {
+        None
+    }
+
+    #[inline(always)]
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Opaque
+    }
+
+    /// The primitive state of the material pipelines, which controls face culling and how
+    /// vertices are assembled.
+    #[inline(always)]
+    fn primitive_state(&self) -> PrimitiveState {
+        PrimitiveState::default()
+    }
+
+    /// Returns true if the material is rendered in the transparent queue, after the opaque
+    /// surfaces behind it.
     #[inline(always)]
     fn is_translucent(&self) -> bool {
-        false
+        self.alpha_mode().is_translucent()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_state() {
+        for mode in [AlphaMode::Opaque, AlphaMode::Mask, AlphaMode::Dithered] {
+            assert_eq!(mode.blend_state(), BlendState::REPLACE);
+            assert!(!mode.is_translucent());
+        }
+
+        assert_eq!(AlphaMode::Blend.blend_state(), BlendState::ALPHA_BLENDING);
+        assert_eq!(
+            AlphaMode::Premultiplied.blend_state(),
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING
+        );
+
+        // additive blending scales the color by alpha and leaves the destination alpha as is
+        let additive = AlphaMode::Additive.blend_state();
+        assert_eq!(additive.color.src_factor, BlendFactor::SrcAlpha);
+        assert_eq!(additive.color.dst_factor, BlendFactor::One);
+        assert_eq!(additive.alpha.src_factor, BlendFactor::Zero);
+        assert_eq!(additive.alpha.dst_factor, BlendFactor::One);
+
+        for mode in [
+            AlphaMode::Blend,
+            AlphaMode::Premultiplied,
+            AlphaMode::Additive,
+        ] {
+            assert!(mode.is_translucent());
+            assert!(!mode.is_masked());
+        }
+    }
+
+    #[test]
+    fn test_is_masked() {
+        assert!(AlphaMode::Mask.is_masked());
+        assert!(AlphaMode::Dithered.is_masked());
+        assert!(!AlphaMode::Opaque.is_masked());
     }
 }
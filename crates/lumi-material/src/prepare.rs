@@ -3,34 +3,61 @@ use std::any::TypeId;
 use deref_derive::{Deref, DerefMut};
 use lumi_bind::BindingLayout;
 use lumi_core::{
-    BlendState, ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState,
-    Device, FragmentState, MultisampleState, PipelineLayout, PrimitiveState,
-    RenderPipelineDescriptor, SharedDevice, SharedRenderPipeline, StencilState, TextureFormat,
-    VertexAttribute, VertexBufferLayout, VertexState, VertexStepMode,
+    ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, Device,
+    FragmentState, MultisampleState, PipelineLayout, PrimitiveState, RenderPipelineDescriptor,
+    SharedDevice, SharedRenderPipeline, StencilState, TextureFormat, VertexAttribute,
+    VertexBufferLayout, VertexState, VertexStepMode,
 };
 use lumi_id::{Id, IdMap};
 use lumi_renderer::{
-    IntegratedBrdf, PreparedCamera, PreparedEnvironment, PreparedLights, PreparedShadows,
-    PreparedTransform, ScreenSpaceBindings,
+    IntegratedBrdf, LtcTables, PreparedCamera, PreparedEnvironment, PreparedIrradianceVolumes,
+    PreparedLightTextures, PreparedLightmap, PreparedLights, PreparedProbes,
+    PreparedReflectionProbes, PreparedShadows, PreparedTransform, ScreenSpaceBindings,
 };
 use lumi_shader::{ShaderDefs, ShaderProcessor};
 
-use crate::{Material, MaterialPipeline};
+use crate::{AlphaMode, Material, MaterialPipeline};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct PreparedMaterialPipelineKey {
     pub material_type: TypeId,
     pub shader_defs: ShaderDefs,
     pub sample_count: u32,
+    pub alpha_mode: AlphaMode,
+    pub primitive: PrimitiveState,
 }
 
 impl PreparedMaterialPipelineKey {
+    /// Creates the key of `material`, `lightmap` is whether the mesh has a
+    /// [`PreparedLightmap`], which adds the `LIGHTMAP` shader def.
     #[inline]
-    pub fn new<T: Material>(material: &T, sample_count: u32) -> Self {
+    pub fn new<T: Material>(material: &T, sample_count: u32, lightmap: bool) -> Self {
+        let mut shader_defs = material.shader_defs();
+        let alpha_mode = material.alpha_mode();
+
+        if lightmap {
+            shader_defs.push("LIGHTMAP");
+        }
+
+        match alpha_mode {
+            AlphaMode::Opaque => shader_defs.push("ALPHA_OPAQUE"),
+            AlphaMode::Mask => {
+                shader_defs.push("ALPHA_MASK");
+
+                if sample_count > 1 {
+                    shader_defs.push("ALPHA_TO_COVERAGE");
+                }
+            }
+            AlphaMode::Dithered => shader_defs.push("ALPHA_DITHERED"),
+            _ => {}
+        }
+
         Self {
             material_type: TypeId::of::<T>(),
-            shader_defs: material.shader_defs(),
+            shader_defs,
             sample_count,
+            alpha_mode,
+            primitive: material.primitive_state(),
         }
     }
 
@@ -61,6 +88,8 @@ impl PreparedMaterialPipelines {
                 &key.shader_defs,
                 shader_processor,
                 key.sample_count,
+                key.alpha_mode,
+                key.primitive,
             );
 
             self.insert(id, pipeline);
@@ -86,6 +115,8 @@ impl PreparedMaterialPipeline {
         shader_defs: &ShaderDefs,
         shader_processor: &mut ShaderProcessor,
         sample_count: u32,
+        alpha_mode: AlphaMode,
+        primitive: PrimitiveState,
     ) -> Self {
         let vertex_shader = shader_processor
             .process(T::vertex_shader(), shader_defs)
@@ -97,6 +128,7 @@ impl PreparedMaterialPipeline {
         let mut material_pipeline = MaterialPipeline {
             vertex_shader,
             fragment_shader,
+            shader_defs: shader_defs.clone(),
             vertices: Vec::new(),
         };
 
@@ -110,8 +142,14 @@ impl PreparedMaterialPipeline {
             .bind::<PreparedCamera>()
             .bind::<PreparedTransform>()
             .bind::<IntegratedBrdf>()
+            .bind::<LtcTables>()
             .bind::<PreparedLights>()
+            .bind::<PreparedLightTextures>()
             .bind::<PreparedEnvironment>()
+            .bind::<PreparedProbes>()
+            .bind::<PreparedReflectionProbes>()
+            .bind::<PreparedIrradianceVolumes>()
+            .bind::<PreparedLightmap>()
             .bind::<PreparedShadows>()
             .bind::<ScreenSpaceBindings>()
             .bind::<T>();
@@ -122,7 +160,10 @@ impl PreparedMaterialPipeline {
             device,
             &pipeline_layout,
             &mut material_pipeline,
+            T::prepass_entry_point(),
             sample_count,
+            alpha_mode,
+            primitive,
         );
 
         let opaque_pipeline = Self::create_opaque_pipeline(
@@ -130,6 +171,8 @@ impl PreparedMaterialPipeline {
             &pipeline_layout,
             &mut material_pipeline,
             sample_count,
+            alpha_mode,
+            primitive,
         );
 
         let transparent_pipeline = Self::create_transparent_pipeline(
@@ -137,6 +180,8 @@ impl PreparedMaterialPipeline {
             &pipeline_layout,
             &mut material_pipeline,
             sample_count,
+            alpha_mode,
+            primitive,
         );
 
         Self {
@@ -153,7 +198,10 @@ impl PreparedMaterialPipeline {
         device: &Device,
         pipeline_layout: &PipelineLayout,
         material_pipeline: &mut MaterialPipeline,
+        entry_point: Option<&str>,
         sample_count: u32,
+        alpha_mode: AlphaMode,
+        primitive: PrimitiveState,
     ) -> SharedRenderPipeline {
         let vertex_attributes = material_pipeline
             .vertices
@@ -178,6 +226,15 @@ impl PreparedMaterialPipeline {
             })
             .collect::<Vec<_>>();
 
+        // masked materials run the fragment shader without color targets, so discarded
+        // fragments don't write depth
+        let entry_point = entry_point.filter(|_| alpha_mode.is_masked());
+        let fragment = entry_point.map(|entry_point| FragmentState {
+            module: material_pipeline.fragment_shader.shader_module(device),
+            entry_point,
+            targets: &[],
+        });
+
         device.create_shared_render_pipeline(&RenderPipelineDescriptor {
             label: Some("Lumi Material Depth Prepass RenderPipeline"),
             layout: Some(&pipeline_layout),
@@ -186,8 +243,8 @@ impl PreparedMaterialPipeline {
                 entry_point: "vertex",
                 buffers: &vertex_buffers,
             },
-            fragment: None,
-            primitive: PrimitiveState::default(),
+            fragment,
+            primitive,
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 depth_write_enabled: true,
@@ -208,6 +265,8 @@ impl PreparedMaterialPipeline {
         pipeline_layout: &PipelineLayout,
         material_pipeline: &mut MaterialPipeline,
         sample_count: u32,
+        alpha_mode: AlphaMode,
+        primitive: PrimitiveState,
     ) -> SharedRenderPipeline {
         let vertex_attributes = material_pipeline
             .vertices
@@ -245,11 +304,11 @@ impl PreparedMaterialPipeline {
                 entry_point: "fragment",
                 targets: &[Some(ColorTargetState {
                     format: TextureFormat::Rgba16Float,
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    blend: Some(alpha_mode.blend_state()),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
-            primitive: PrimitiveState::default(),
+            primitive,
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 depth_write_enabled: false,
@@ -259,6 +318,7 @@ impl PreparedMaterialPipeline {
             }),
             multisample: MultisampleState {
                 count: sample_count,
+                alpha_to_coverage_enabled: alpha_mode == AlphaMode::Mask && sample_count > 1,
                 ..Default::default()
             },
             multiview: None,
@@ -270,6 +330,8 @@ impl PreparedMaterialPipeline {
         pipeline_layout: &PipelineLayout,
         material_pipeline: &mut MaterialPipeline,
         sample_count: u32,
+        alpha_mode: AlphaMode,
+        primitive: PrimitiveState,
     ) -> SharedRenderPipeline {
         let vertex_attributes = material_pipeline
             .vertices
@@ -307,11 +369,11 @@ impl PreparedMaterialPipeline {
                 entry_point: "fragment",
                 targets: &[Some(ColorTargetState {
                     format: TextureFormat::Rgba16Float,
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    blend: Some(alpha_mode.blend_state()),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
-            primitive: PrimitiveState::default(),
+            primitive,
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 depth_write_enabled: true,
@@ -321,6 +383,7 @@ impl PreparedMaterialPipeline {
             }),
             multisample: MultisampleState {
                 count: sample_count,
+                alpha_to_coverage_enabled: alpha_mode == AlphaMode::Mask && sample_count > 1,
                 ..Default::default()
             },
             multiview: None,
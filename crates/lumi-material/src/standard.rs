@@ -1,15 +1,17 @@
 use lumi_bind::Bind;
-use lumi_core::Image;
+use lumi_core::{Face, Image, PrimitiveState};
 use lumi_macro::ShaderType;
 use lumi_shader::{ShaderDefs, ShaderRef};
 use lumi_util::math::{Vec3, Vec4};
 use shiv::{storage::DenseStorage, world::Component};
 
-use crate::Material;
+use crate::{AlphaMode, Material};
 
 #[derive(Clone, Debug, PartialEq, Bind)]
 #[uniform(RawStandardMaterial = "standard_material")]
 pub struct StandardMaterial<T = Image> {
+    /// The sampler of the base color texture is also used by the maps without a sampler of their
+    /// own, which are the maps below `emissive_map`.
     #[texture]
     #[sampler(name = "base_color_sampler")]
     pub base_color_texture: Option<T>,
@@ -25,8 +27,42 @@ pub struct StandardMaterial<T = Image> {
     #[texture]
     #[sampler(name = "emissive_map_sampler")]
     pub emissive_map: Option<T>,
+    #[texture]
+    pub occlusion_texture: Option<T>,
+    /// Scales `clearcoat` by the red channel.
+    #[texture]
+    pub clearcoat_texture: Option<T>,
+    /// Scales `clearcoat_roughness` by the green channel.
+    #[texture]
+    pub clearcoat_roughness_texture: Option<T>,
+    /// Scales `thickness` by the green channel.
+    #[texture]
+    pub thickness_texture: Option<T>,
+    /// Scales `transmission` by the red channel.
+    #[texture]
+    pub transmission_texture: Option<T>,
+    /// Scales the specular reflectance of dielectrics by the alpha channel.
+    #[texture]
+    pub specular_texture: Option<T>,
+    /// Scales `specular_color` by the rgb channels.
+    #[texture]
+    pub specular_color_texture: Option<T>,
+    /// Scales `sheen_color` by the rgb channels.
+    #[texture]
+    pub sheen_color_texture: Option<T>,
+    /// Scales `sheen_roughness` by the alpha channel.
+    #[texture]
+    pub sheen_roughness_texture: Option<T>,
+    /// The red and green channels hold the direction of the anisotropy in tangent space, relative
+    /// to `anisotropy_rotation`, the blue channel scales `anisotropy`.
+    #[texture]
+    pub anisotropy_texture: Option<T>,
     pub base_color: Vec4,
+    pub alpha_mode: AlphaMode,
+    /// Fragments with alpha at or below the cutoff are discarded with [`AlphaMode::Mask`].
     pub alpha_cutoff: f32,
+    /// Renders back faces with flipped normals instead of culling them.
+    pub double_sided: bool,
     pub metallic: f32,
     pub roughness: f32,
     pub clearcoat: f32,
@@ -42,6 +78,17 @@ pub struct StandardMaterial<T = Image> {
     pub transmission: f32,
     pub ior: f32,
     pub absorption: Vec3,
+    /// How strongly the `occlusion_texture` occludes indirect light.
+    pub occlusion_strength: f32,
+    /// Tints the specular reflectance of dielectrics.
+    pub specular_color: Vec3,
+    /// The color of the sheen layer, a black sheen disables it.
+    pub sheen_color: Vec3,
+    pub sheen_roughness: f32,
+    /// How much reflections are stretched along the direction of the anisotropy.
+    pub anisotropy: f32,
+    /// Rotates the direction of the anisotropy counter clockwise from the tangent, in radians.
+    pub anisotropy_rotation: f32,
 }
 
 impl Default for StandardMaterial {
@@ -53,8 +100,20 @@ impl Default for StandardMaterial {
             normal_map: None,
             clearcoat_normal_map: None,
             emissive_map: None,
+            occlusion_texture: None,
+            clearcoat_texture: None,
+            clearcoat_roughness_texture: None,
+            thickness_texture: None,
+            transmission_texture: None,
+            specular_texture: None,
+            specular_color_texture: None,
+            sheen_color_texture: None,
+            sheen_roughness_texture: None,
+            anisotropy_texture: None,
             base_color: Vec4::ONE,
+            alpha_mode: AlphaMode::Opaque,
             alpha_cutoff: 0.01,
+            double_sided: false,
             metallic: 0.01,
             roughness: 0.089,
             clearcoat: 0.0,
@@ -70,6 +129,12 @@ impl Default for StandardMaterial {
             transmission: 0.0,
             ior: 1.5,
             absorption: Vec3::ZERO,
+            occlusion_strength: 1.0,
+            specular_color: Vec3::ONE,
+            sheen_color: Vec3::ZERO,
+            sheen_roughness: 0.0,
+            anisotropy: 0.0,
+            anisotropy_rotation: 0.0,
         }
     }
 }
@@ -96,6 +161,12 @@ pub struct RawStandardMaterial {
     pub transmission: f32,
     pub ior: f32,
     pub absorption: Vec3,
+    pub occlusion_strength: f32,
+    pub specular_color: Vec3,
+    pub sheen_roughness: f32,
+    pub sheen_color: Vec3,
+    pub anisotropy: f32,
+    pub anisotropy_rotation: f32,
 }
 
 impl From<&StandardMaterial> for RawStandardMaterial {
@@ -118,6 +189,12 @@ impl From<&StandardMaterial> for RawStandardMaterial {
             transmission: material.transmission,
             ior: material.ior,
             absorption: material.absorption,
+            occlusion_strength: material.occlusion_strength,
+            specular_color: material.specular_color,
+            sheen_roughness: material.sheen_roughness,
+            sheen_color: material.sheen_color,
+            anisotropy: material.anisotropy,
+            anisotropy_rotation: material.anisotropy_rotation,
         }
     }
 }
@@ -144,6 +221,10 @@ impl Material for StandardMaterial {
             shader_defs.push("EMISSIVE_MAP");
         }
 
+        if self.occlusion_texture.is_some() {
+            shader_defs.push("OCCLUSION_MAP");
+        }
+
         if self.normal_map.is_some() {
             shader_defs.push("NORMAL_MAP");
         }
@@ -152,10 +233,58 @@ impl Material for StandardMaterial {
             shader_defs.push("CLEARCOAT_NORMAL_MAP");
         }
 
+        if self.clearcoat_texture.is_some() {
+            shader_defs.push("CLEARCOAT_TEXTURE");
+        }
+
+        if self.clearcoat_roughness_texture.is_some() {
+            shader_defs.push("CLEARCOAT_ROUGHNESS_TEXTURE");
+        }
+
+        if self.thickness_texture.is_some() {
+            shader_defs.push("THICKNESS_TEXTURE");
+        }
+
+        if self.transmission_texture.is_some() {
+            shader_defs.push("TRANSMISSION_TEXTURE");
+        }
+
+        if self.specular_texture.is_some() {
+            shader_defs.push("SPECULAR_TEXTURE");
+        }
+
+        if self.specular_color_texture.is_some() {
+            shader_defs.push("SPECULAR_COLOR_TEXTURE");
+        }
+
+        if self.sheen_color_texture.is_some() {
+            shader_defs.push("SHEEN_COLOR_TEXTURE");
+        }
+
+        if self.sheen_roughness_texture.is_some() {
+            shader_defs.push("SHEEN_ROUGHNESS_TEXTURE");
+        }
+
+        if self.anisotropy_texture.is_some() {
+            shader_defs.push("ANISOTROPY_TEXTURE");
+        }
+
+        if self.double_sided {
+            shader_defs.push("DOUBLE_SIDED");
+        }
+
         if self.clearcoat > 0.0 {
             shader_defs.push("CLEARCOAT");
         }
 
+        if self.sheen_color != Vec3::ZERO {
+            shader_defs.push("SHEEN");
+        }
+
+        if self.anisotropy != 0.0 {
+            shader_defs.push("ANISOTROPY");
+        }
+
         if self.subsurface {
             shader_defs.push("SUBSURFACE");
         }
@@ -171,8 +300,30 @@ impl Material for StandardMaterial {
         shader_defs
     }
 
+    #[inline]
+    fn prepass_entry_point() -> Option<&'static str> {
+        Some("prepass")
+    }
+
+    #[inline]
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    #[inline]
+    fn primitive_state(&self) -> PrimitiveState {
+        PrimitiveState {
+            cull_mode: if self.double_sided {
+                None
+            } else {
+                Some(Face::Back)
+            },
+            ..Default::default()
+        }
+    }
+
     #[inline]
     fn is_translucent(&self) -> bool {
-        self.base_color.w < 1.0 || self.transmission > 0.0
+        self.alpha_mode.is_translucent() || self.transmission > 0.0
     }
 }
@@ -35,6 +35,7 @@ impl Mesh {
     pub const NORMAL: &'static str = "normal";
     pub const TANGENT: &'static str = "tangent";
     pub const UV_0: &'static str = "uv_0";
+    pub const UV_1: &'static str = "uv_1";
 
     /// Creates a new mesh.
     pub fn new() -> Self {
@@ -283,3 +284,21 @@ impl Mesh {
         self.attribute_mut(Self::UV_0)
     }
 }
+
+impl Mesh {
+    pub fn insert_uv1(&mut self, uvs: impl Into<Vec<Vec2>>) {
+        self.insert_attribute(Self::UV_1, uvs.into());
+    }
+
+    pub fn remove_uv1(&mut self) -> Option<Vec<Vec2>> {
+        self.remove_attribute(Self::UV_1)
+    }
+
+    pub fn uv_1(&self) -> Option<&[Vec2]> {
+        self.attribute(Self::UV_1)
+    }
+
+    pub fn uv_1_mut(&mut self) -> Option<&mut [Vec2]> {
+        self.attribute_mut(Self::UV_1)
+    }
+}
@@ -0,0 +1,411 @@
+use std::ops::Deref;
+
+use lumi_bind::{Bind, BindingLayout, Bindings};
+use lumi_core::{
+    BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d,
+    FragmentState, LoadOp, Operations, Queue, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipelineDescriptor, SharedDevice, SharedRenderPipeline, SharedTexture, SharedTextureView,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, VertexState,
+};
+use lumi_macro::ShaderType;
+use lumi_shader::{Shader, ShaderDefs, ShaderProcessor, ShaderRef};
+use shiv::{
+    system::{Commands, Res},
+    world::{FromWorld, World},
+};
+
+use crate::{Extract, PreparedCamera, RenderDevice};
+
+#[derive(Clone, Debug)]
+pub struct AmbientOcclusionSettings {
+    pub enabled: bool,
+    /// The radius in meters around each pixel searched for occluders.
+    pub radius: f32,
+    /// The exponent applied to the visibility, higher values darken the occlusion.
+    pub intensity: f32,
+    /// The fraction of the radius over which occluders fade out.
+    pub falloff: f32,
+    /// The number of directions searched around each pixel.
+    pub slice_count: u32,
+    /// The number of samples taken along each direction.
+    pub step_count: u32,
+}
+
+impl Default for AmbientOcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 0.5,
+            intensity: 1.0,
+            falloff: 0.6,
+            slice_count: 2,
+            step_count: 4,
+        }
+    }
+}
+
+impl AmbientOcclusionSettings {
+    #[inline]
+    pub fn raw(&self) -> RawAmbientOcclusion {
+        RawAmbientOcclusion {
+            radius: self.radius,
+            intensity: self.intensity,
+            falloff: self.falloff,
+            slice_count: self.slice_count,
+            step_count: self.step_count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawAmbientOcclusion {
+    pub radius: f32,
+    pub intensity: f32,
+    pub falloff: f32,
+    pub slice_count: u32,
+    pub step_count: u32,
+}
+
+pub fn extract_ambient_occlusion_settings_system(
+    mut commands: Commands,
+    settings: Extract<Option<Res<AmbientOcclusionSettings>>>,
+) {
+    if let Some(settings) = settings.deref() {
+        if settings.is_changed() {
+            commands.insert_resource(settings.as_ref().clone());
+        }
+    }
+}
+
+/// The textures ambient occlusion is rendered to.
+///
+/// Both store the occlusion in the red channel and the linear view depth of the occluded surface
+/// in the green channel.
+pub struct AmbientOcclusionTarget {
+    pub noisy_texture: SharedTexture,
+    pub noisy_view: SharedTextureView,
+    pub texture: SharedTexture,
+    pub view: SharedTextureView,
+}
+
+impl AmbientOcclusionTarget {
+    pub const FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let noisy_texture = Self::create_texture(device, "Lumi Noisy AO Target", width, height);
+        let texture = Self::create_texture(device, "Lumi AO Target", width, height);
+
+        Self {
+            noisy_view: noisy_texture.create_view(&Default::default()),
+            noisy_texture,
+            view: texture.create_view(&Default::default()),
+            texture,
+        }
+    }
+
+    fn create_texture(device: &Device, label: &str, width: u32, height: u32) -> SharedTexture {
+        device.create_shared_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        })
+    }
+
+    pub fn size(&self) -> Extent3d {
+        self.texture.size()
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let size = self.size();
+
+        if size.width != width || size.height != height {
+            *self = Self::new(device, width, height);
+        }
+    }
+
+    /// Clears the occlusion to fully visible.
+    pub fn clear(&self, encoder: &mut CommandEncoder) {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi AO Clear Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+    }
+
+    /// Renders ambient occlusion from the depth bound in `bindings` and denoises it.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &AmbientOcclusionPipeline,
+        bindings: &AmbientOcclusionBindings,
+    ) {
+        let gtao_pipeline = if bindings.multisampled {
+            &pipeline.multisampled_gtao_pipeline
+        } else {
+            &pipeline.gtao_pipeline
+        };
+
+        let mut gtao_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi GTAO Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.noisy_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        gtao_pass.set_pipeline(gtao_pipeline);
+        bindings.gtao.apply(&mut gtao_pass);
+        gtao_pass.draw(0..3, 0..1);
+
+        drop(gtao_pass);
+
+        let mut denoise_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi GTAO Denoise Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        denoise_pass.set_pipeline(&pipeline.denoise_pipeline);
+        bindings.denoise.apply(&mut denoise_pass);
+        denoise_pass.draw(0..3, 0..1);
+    }
+}
+
+#[derive(Bind)]
+struct GtaoBindings {
+    #[uniform]
+    ambient_occlusion: RawAmbientOcclusion,
+}
+
+#[derive(Bind)]
+struct DepthBindings<'a> {
+    #[texture(sample_type = depth)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct MultisampledDepthBindings<'a> {
+    #[texture(sample_type = depth, multisampled = true)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct DenoiseBindings<'a> {
+    #[texture]
+    ao_noisy_texture: &'a SharedTextureView,
+}
+
+/// The bindings of the ambient occlusion passes of a single camera.
+pub struct AmbientOcclusionBindings {
+    pub gtao: Bindings,
+    pub denoise: Bindings,
+    pub multisampled: bool,
+}
+
+impl AmbientOcclusionBindings {
+    pub fn new(device: &Device, pipeline: &AmbientOcclusionPipeline, multisampled: bool) -> Self {
+        let gtao_layout = if multisampled {
+            &pipeline.multisampled_gtao_layout
+        } else {
+            &pipeline.gtao_layout
+        };
+
+        Self {
+            gtao: gtao_layout.create_bindings(device),
+            denoise: pipeline.denoise_layout.create_bindings(device),
+            multisampled,
+        }
+    }
+
+    pub fn bind(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        settings: &AmbientOcclusionSettings,
+        camera: &PreparedCamera,
+        depth: &SharedTextureView,
+        target: &AmbientOcclusionTarget,
+    ) {
+        let gtao_bindings = GtaoBindings {
+            ambient_occlusion: settings.raw(),
+        };
+
+        self.gtao.bind(device, queue, camera);
+        self.gtao.bind(device, queue, &gtao_bindings);
+
+        if self.multisampled {
+            let depth_bindings = MultisampledDepthBindings {
+                depth_texture: depth,
+            };
+
+            self.gtao.bind(device, queue, &depth_bindings);
+        } else {
+            let depth_bindings = DepthBindings {
+                depth_texture: depth,
+            };
+
+            self.gtao.bind(device, queue, &depth_bindings);
+        }
+
+        let denoise_bindings = DenoiseBindings {
+            ao_noisy_texture: &target.noisy_view,
+        };
+
+        self.denoise.bind(device, queue, &denoise_bindings);
+
+        self.gtao.update_bind_groups(device);
+        self.denoise.update_bind_groups(device);
+    }
+}
+
+pub struct AmbientOcclusionPipeline {
+    pub gtao_layout: BindingLayout,
+    pub gtao_pipeline: SharedRenderPipeline,
+    pub multisampled_gtao_layout: BindingLayout,
+    pub multisampled_gtao_pipeline: SharedRenderPipeline,
+    pub denoise_layout: BindingLayout,
+    pub denoise_pipeline: SharedRenderPipeline,
+}
+
+impl AmbientOcclusionPipeline {
+    fn create_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &BindingLayout,
+        vertex: &Shader,
+        fragment: &Shader,
+    ) -> SharedRenderPipeline {
+        let pipeline_layout = layout.create_pipeline_layout(device);
+
+        device.create_shared_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &vertex.shader_module(device),
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &fragment.shader_module(device),
+                entry_point: "fragment",
+                targets: &[Some(ColorTargetState {
+                    format: AmbientOcclusionTarget::FORMAT,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    }
+}
+
+impl FromWorld for AmbientOcclusionPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut shader_processor = world.resource_mut::<ShaderProcessor>();
+
+        let mut multisampled_defs = ShaderDefs::new();
+        multisampled_defs.push("MULTISAMPLED");
+
+        let mut vertex = shader_processor
+            .process(
+                ShaderRef::module("lumi/fullscreen_vert.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut gtao = shader_processor
+            .process(
+                ShaderRef::module("lumi/gtao_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut multisampled_gtao = shader_processor
+            .process(ShaderRef::module("lumi/gtao_frag.wgsl"), &multisampled_defs)
+            .unwrap();
+        let mut denoise = shader_processor
+            .process(
+                ShaderRef::module("lumi/gtao_denoise_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        vertex.rebind_with(&mut gtao).unwrap();
+        vertex.rebind_with(&mut multisampled_gtao).unwrap();
+        vertex.rebind_with(&mut denoise).unwrap();
+
+        let gtao_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&gtao)
+            .bind::<PreparedCamera>()
+            .bind::<GtaoBindings>()
+            .bind::<DepthBindings>();
+
+        let multisampled_gtao_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&multisampled_gtao)
+            .bind::<PreparedCamera>()
+            .bind::<GtaoBindings>()
+            .bind::<MultisampledDepthBindings>();
+
+        let denoise_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&denoise)
+            .bind::<DenoiseBindings>();
+
+        let device = world.resource::<RenderDevice>();
+
+        let gtao_pipeline =
+            Self::create_pipeline(device, "Lumi GTAO", &gtao_layout, &vertex, &gtao);
+        let multisampled_gtao_pipeline = Self::create_pipeline(
+            device,
+            "Lumi Multisampled GTAO",
+            &multisampled_gtao_layout,
+            &vertex,
+            &multisampled_gtao,
+        );
+        let denoise_pipeline = Self::create_pipeline(
+            device,
+            "Lumi GTAO Denoise",
+            &denoise_layout,
+            &vertex,
+            &denoise,
+        );
+
+        Self {
+            gtao_layout,
+            gtao_pipeline,
+            multisampled_gtao_layout,
+            multisampled_gtao_pipeline,
+            denoise_layout,
+            denoise_pipeline,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::f32::consts::PI;
+
+use lumi_macro::ShaderType;
+use lumi_util::math::Vec3;
+
+use shiv::{bundle::Bundle, world::Component};
+use shiv_transform::{GlobalTransform, Transform};
+
+use crate::RenderLayers;
+
+/// A rect, disk or tube light, all area lights share a single buffer.
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawAreaLight {
+    pub position: Vec3,
+    /// The shape of the light, one of [`Self::RECT`], [`Self::DISK`] or [`Self::TUBE`].
+    pub kind: u32,
+    /// Half the extent of the light along its local x axis.
+    pub right: Vec3,
+    /// The radius of a tube light.
+    pub radius: f32,
+    /// Half the extent of the light along its local y axis, zero for tube lights.
+    pub up: Vec3,
+    pub intensity: f32,
+    pub color: Vec3,
+    /// The [`RenderLayers`] of meshes affected by the light.
+    pub render_layers: u32,
+}
+
+impl RawAreaLight {
+    pub const RECT: u32 = 0;
+    pub const DISK: u32 = 1;
+    pub const TUBE: u32 = 2;
+}
+
+/// A rectangular area light, lying in the local xy plane and emitting along the local -z axis.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct RectLight {
+    /// The color of the light.
+    pub color: Vec3,
+    /// The intensity of the light in lumens.
+    pub intensity: f32,
+    /// The width of the light in meters.
+    pub width: f32,
+    /// The height of the light in meters.
+    pub height: f32,
+}
+
+impl Default for RectLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 800.0,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+}
+
+impl RectLight {
+    pub fn raw(&self, transform: &GlobalTransform) -> RawAreaLight {
+        let right = transform.matrix.mul_vec3(Vec3::X) * self.width / 2.0;
+        let up = transform.matrix.mul_vec3(Vec3::Y) * self.height / 2.0;
+
+        // convert luminous power to luminance of a one sided lambertian emitter
+        let area = 4.0 * right.length() * up.length();
+        let intensity = self.intensity / (PI * f32::max(area, 0.0001));
+
+        RawAreaLight {
+            position: transform.translation,
+            kind: RawAreaLight::RECT,
+            right,
+            radius: 0.0,
+            up,
+            intensity,
+            color: self.color,
+            render_layers: RenderLayers::default().bits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct RectLightBundle {
+    pub light: RectLight,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// A disk shaped area light, lying in the local xy plane and emitting along the local -z axis.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DiskLight {
+    /// The color of the light.
+    pub color: Vec3,
+    /// The intensity of the light in lumens.
+    pub intensity: f32,
+    /// The radius of the light in meters.
+    pub radius: f32,
+}
+
+impl Default for DiskLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 800.0,
+            radius: 0.5,
+        }
+    }
+}
+
+impl DiskLight {
+    pub fn raw(&self, transform: &GlobalTransform) -> RawAreaLight {
+        let right = transform.matrix.mul_vec3(Vec3::X) * self.radius;
+        let up = transform.matrix.mul_vec3(Vec3::Y) * self.radius;
+
+        let area = PI * right.length() * up.length();
+        let intensity = self.intensity / (PI * f32::max(area, 0.0001));
+
+        RawAreaLight {
+            position: transform.translation,
+            kind: RawAreaLight::DISK,
+            right,
+            radius: 0.0,
+            up,
+            intensity,
+            color: self.color,
+            render_layers: RenderLayers::default().bits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct DiskLightBundle {
+    pub light: DiskLight,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+/// A capsule shaped area light along the local x axis, emitting in all directions.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TubeLight {
+    /// The color of the light.
+    pub color: Vec3,
+    /// The intensity of the light in lumens.
+    pub intensity: f32,
+    /// The length of the light in meters, excluding the rounded ends.
+    pub length: f32,
+    /// The radius of the light in meters.
+    pub radius: f32,
+}
+
+impl Default for TubeLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 800.0,
+            length: 1.0,
+            radius: 0.05,
+        }
+    }
+}
+
+impl TubeLight {
+    pub fn raw(&self, transform: &GlobalTransform) -> RawAreaLight {
+        let axis = transform.matrix.mul_vec3(Vec3::X) * self.length / 2.0;
+        let radius = f32::max(self.radius, 0.001);
+
+        // the surface area of the capsule
+        let area = 4.0 * PI * radius * axis.length() + 4.0 * PI * radius * radius;
+        let intensity = self.intensity / (PI * area);
+
+        RawAreaLight {
+            position: transform.translation,
+            kind: RawAreaLight::TUBE,
+            right: axis,
+            radius,
+            up: Vec3::ZERO,
+            intensity,
+            color: self.color,
+            render_layers: RenderLayers::default().bits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct TubeLightBundle {
+    pub light: TubeLight,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
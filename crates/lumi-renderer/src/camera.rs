@@ -2,10 +2,13 @@ use lumi_bounds::{CameraFrustum, Frustum};
 use lumi_core::{RenderTarget, SharedTextureView, TextureView};
 use lumi_macro::ShaderType;
 use lumi_util::math::{Mat4, Vec3};
-use shiv::{prelude::Bundle, world::Component};
+use shiv::{
+    prelude::Bundle,
+    world::{Component, Entity},
+};
 use shiv_transform::{GlobalTransform, Transform};
 
-#[derive(Clone, Copy, Debug, ShaderType)]
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
 pub struct RawCamera {
     pub position: Vec3,
     pub aspect_ratio: f32,
@@ -147,6 +150,13 @@ impl Projection {
         }
     }
 
+    pub fn near(&self) -> f32 {
+        match self {
+            Projection::Perspective(perspective) => perspective.near,
+            Projection::Orthographic(orthographic) => orthographic.near,
+        }
+    }
+
     pub fn far(&self) -> Option<f32> {
         match self {
             Projection::Perspective(_) => None,
@@ -194,6 +204,16 @@ impl CameraTarget {
     }
 }
 
+/// The camera that most recently rendered to the main target.
+///
+/// Shadow cascades of [`DirectionalLight`](crate::DirectionalLight)s are fitted to this camera.
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveCamera {
+    pub camera: Entity,
+    /// The aspect ratio the camera rendered with.
+    pub aspect: f32,
+}
+
 #[derive(Component, Clone, Debug)]
 pub struct Camera {
     pub projection: Projection,
@@ -257,6 +277,10 @@ impl Camera {
         self.projection.has_far_plane()
     }
 
+    pub fn near(&self) -> f32 {
+        self.projection.near()
+    }
+
     pub fn far(&self) -> Option<f32> {
         self.projection.far()
     }
@@ -294,6 +318,31 @@ impl Camera {
         }
     }
 
+    /// Returns the world space corners of the frustum slice between the view space depths `near`
+    /// and `far`.
+    ///
+    /// The first four corners lie on the `near` plane, the last four on the `far` plane.
+    pub fn frustum_corners(&self, view: Mat4, aspect: f32, near: f32, far: f32) -> [Vec3; 8] {
+        let inverse_proj = self.projection.projection_with_aspect(aspect).inverse();
+
+        let mut corners = [Vec3::ZERO; 8];
+        for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            // two points along the edge of the frustum, which is then extended to `near` and `far`
+            let a = inverse_proj.project_point3(Vec3::new(x, y, 0.0));
+            let b = inverse_proj.project_point3(Vec3::new(x, y, 0.5));
+
+            let along = |depth: f32| a + (b - a) * (depth + a.z) / (a.z - b.z);
+
+            corners[i] = view.transform_point3(along(near));
+            corners[i + 4] = view.transform_point3(along(far));
+        }
+
+        corners
+    }
+
     pub fn ev100(&self) -> f32 {
         let sensitivity = self.sensitivity / 100.0;
         let ev100 = f32::log2(self.aperture * self.aperture / self.shutter_speed * sensitivity);
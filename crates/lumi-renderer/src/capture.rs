@@ -0,0 +1,152 @@
+use lumi_bake::BakedEnvironment;
+use lumi_core::{
+    CommandEncoder, Device, Extent3d, ImageCopyTexture, Origin3d, Queue, RenderTarget,
+    SharedDevice, Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages,
+};
+use lumi_util::math::{Mat4, Vec3};
+use shiv::world::Entity;
+
+use crate::{Camera, CameraTarget, Perspective, PreparedTransform, Projection, Renderer};
+
+/// The six faces of a cube rendered by [`Renderer::capture_cube`].
+pub struct CubeCapture {
+    /// The faces in the layers of an `Rgba16Float` texture, in the directions given by
+    /// [`BakedEnvironment::CUBE_FACES`].
+    pub faces: Texture,
+    pub size: u32,
+    /// The exposure of the camera the faces were rendered with.
+    pub exposure: f32,
+}
+
+impl CubeCapture {
+    /// Returns the world transform of the camera rendering `face` from `position`.
+    pub fn face_view(position: Vec3, face: usize) -> Mat4 {
+        let (forward, up) = BakedEnvironment::CUBE_FACES[face];
+        let right = forward.cross(up);
+
+        Mat4::from_cols(
+            right.extend(0.0),
+            up.extend(0.0),
+            (-forward).extend(0.0),
+            position.extend(1.0),
+        )
+    }
+}
+
+impl Renderer {
+    /// Renders the scene in every direction from `position`, using `entity` as a temporary
+    /// camera.
+    ///
+    /// `entity` must have a [`PreparedTransform`], which is restored after rendering.
+    pub fn capture_cube(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        entity: Entity,
+        position: Vec3,
+        size: u32,
+        near: f32,
+    ) -> CubeCapture {
+        let extent = Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+
+        let target = device.create_shared_texture(&TextureDescriptor {
+            label: Some("Lumi Cube Capture Target"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+        });
+        let target_view = target.create_view(&Default::default());
+
+        let faces = device.create_texture(&TextureDescriptor {
+            label: Some("Lumi Cube Capture Faces"),
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba16Float,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::COPY_SRC,
+        });
+
+        let camera = Camera {
+            projection: Projection::Perspective(Perspective::new(90.0, 1.0, near)),
+            target: CameraTarget::Texture(target_view.clone()),
+            msaa: false,
+            ..Default::default()
+        };
+        let exposure = camera.exposure();
+
+        let transform = self
+            .world
+            .get::<PreparedTransform>(entity)
+            .unwrap()
+            .transform;
+        self.world.entity_mut(entity).insert(camera);
+
+        for face in 0..6 {
+            let mut prepared = self.world.get_mut::<PreparedTransform>(entity).unwrap();
+            prepared.transform = CubeCapture::face_view(position, face);
+
+            // each render submits and removes the command encoder
+            if !self.world.contains_resource::<CommandEncoder>() {
+                let encoder = device.create_command_encoder(&Default::default());
+                self.world.insert_resource(encoder);
+            }
+
+            let render_target = RenderTarget {
+                view: &target_view,
+                width: size,
+                height: size,
+            };
+
+            self.render(device, queue, entity, render_target);
+
+            let frame_buffer = &self.frame_buffers[&entity];
+            let mut encoder = device.create_command_encoder(&Default::default());
+            encoder.copy_texture_to_texture(
+                frame_buffer.hdr.texture().as_image_copy(),
+                ImageCopyTexture {
+                    texture: &faces,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: face as u32,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                extent,
+            );
+            queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        let mut prepared = self.world.get_mut::<PreparedTransform>(entity).unwrap();
+        prepared.transform = transform;
+
+        self.world.entity_mut(entity).remove::<Camera>();
+
+        if !self.world.contains_resource::<CommandEncoder>() {
+            let encoder = device.create_command_encoder(&Default::default());
+            self.world.insert_resource(encoder);
+        }
+
+        CubeCapture {
+            faces,
+            size,
+            exposure,
+        }
+    }
+}
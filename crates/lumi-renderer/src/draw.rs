@@ -12,14 +12,21 @@ use lumi_core::{
 use lumi_util::{
     math::{Mat4, Vec3A, Vec4Swizzles},
     smallvec::SmallVec,
+    HashMap,
 };
 
 use shiv::{
     query::Query,
-    system::{Res, ResMut},
+    system::{Local, Res, ResInit, ResMut},
+    world::Entity,
 };
 
-use crate::{Camera, PreparedTransform, View};
+use crate::{
+    AmbientOcclusionBindings, AmbientOcclusionPipeline, AmbientOcclusionSettings, Camera,
+    GlobalIlluminationBindings, GlobalIlluminationPipeline, GlobalIlluminationSettings,
+    HiZBindings, HiZPipeline, PreparedCamera, PreparedLights, PreparedTransform, RenderDevice,
+    RenderQueue, ScreenSpaceReflectionSettings, ScreenSpaceTarget, View,
+};
 
 #[derive(Clone, Debug)]
 pub struct Draw {
@@ -233,10 +240,28 @@ pub fn draw_system(
 
 pub fn render_opaque_system(
     mut encoder: ResMut<CommandEncoder>,
+    mut ao_bindings: Local<HashMap<Entity, AmbientOcclusionBindings>>,
+    mut gi_bindings: Local<HashMap<Entity, GlobalIlluminationBindings>>,
+    mut hi_z_bindings: Local<HashMap<Entity, HiZBindings>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
     view: Res<View>,
     opaque_draws: Res<OpaqueDraws>,
     draw_keys: Res<DrawKeys>,
+    ao_pipeline: ResInit<AmbientOcclusionPipeline>,
+    ao_settings: Option<Res<AmbientOcclusionSettings>>,
+    gi_pipeline: ResInit<GlobalIlluminationPipeline>,
+    gi_settings: Option<Res<GlobalIlluminationSettings>>,
+    hi_z_pipeline: ResInit<HiZPipeline>,
+    ssr_settings: Option<Res<ScreenSpaceReflectionSettings>>,
+    prepared_lights: Res<PreparedLights>,
+    camera_query: Query<(&PreparedCamera, &ScreenSpaceTarget)>,
 ) {
+    // remove the bindings of cameras that no longer exist
+    ao_bindings.retain(|&camera, _| camera_query.contains(camera));
+    gi_bindings.retain(|&camera, _| camera_query.contains(camera));
+    hi_z_bindings.retain(|&camera, _| camera_query.contains(camera));
+
     let mut depth_prepass = view.frame_buffer.begin_depth_prepass(&mut encoder);
 
     for draw_key in draw_keys.iter() {
@@ -250,6 +275,88 @@ pub fn render_opaque_system(
 
     drop(depth_prepass);
 
+    // ambient occlusion, global illumination and the hi-z are computed from the depth prepass and
+    // sampled by the opaque pass
+    if let Some((camera, target)) = camera_query.get(view.camera) {
+        let multisampled = view.frame_buffer.sample_count() > 1;
+
+        let ao_settings = ao_settings.as_deref().cloned().unwrap_or_default();
+        let ao_target = &target.ambient_occlusion;
+
+        if ao_settings.enabled {
+            let bindings = ao_bindings.entry(view.camera).or_insert_with(|| {
+                AmbientOcclusionBindings::new(&device, &ao_pipeline, multisampled)
+            });
+
+            if bindings.multisampled != multisampled {
+                *bindings = AmbientOcclusionBindings::new(&device, &ao_pipeline, multisampled);
+            }
+
+            bindings.bind(
+                &device,
+                &queue,
+                &ao_settings,
+                camera,
+                &view.frame_buffer.depth_view,
+                ao_target,
+            );
+
+            ao_target.render(&mut encoder, &ao_pipeline, bindings);
+        } else {
+            ao_target.clear(&mut encoder);
+        }
+
+        let gi_settings = gi_settings.as_deref().cloned().unwrap_or_default();
+        let gi_target = &target.global_illumination;
+
+        if gi_settings.enabled {
+            let bindings = gi_bindings.entry(view.camera).or_insert_with(|| {
+                GlobalIlluminationBindings::new(&device, &gi_pipeline, multisampled)
+            });
+
+            if bindings.multisampled != multisampled {
+                *bindings = GlobalIlluminationBindings::new(&device, &gi_pipeline, multisampled);
+            }
+
+            bindings.bind(
+                &device,
+                &queue,
+                &gi_settings,
+                camera,
+                &view.frame_buffer.depth_view,
+                &target.mip_chain.view,
+                gi_target,
+            );
+
+            gi_target.render(&mut encoder, &gi_pipeline, bindings);
+        } else if gi_bindings.remove(&view.camera).is_some() {
+            gi_target.clear(&mut encoder);
+        }
+
+        // the hi-z is only used by reflections and contact shadows
+        let ssr_settings = ssr_settings.as_deref().cloned().unwrap_or_default();
+
+        if ssr_settings.enabled || prepared_lights.has_contact_shadows() {
+            let hi_z = hi_z_bindings
+                .entry(view.camera)
+                .or_insert_with(|| HiZBindings::new(&device, &hi_z_pipeline, multisampled));
+
+            if hi_z.multisampled != multisampled {
+                *hi_z = HiZBindings::new(&device, &hi_z_pipeline, multisampled);
+            }
+
+            hi_z.bind(
+                &device,
+                &queue,
+                &hi_z_pipeline,
+                &view.frame_buffer.depth_view,
+                &target.hi_z,
+            );
+
+            target.hi_z.render(&mut encoder, &hi_z_pipeline, hi_z);
+        }
+    }
+
     let mut opaque_pass = (view.frame_buffer).begin_hdr_opaque_resolve_pass(&mut encoder);
 
     if let Some(first_transparent) = draw_keys.first_transparent {
@@ -1,12 +1,86 @@
-use std::path::Path;
+use std::{f32::consts::PI, path::Path};
 
 use lumi_bake::{BakedEnvironment, EnvironmentData};
 use lumi_core::{Device, ImageData, ImageError, Queue};
 use lumi_id::Id;
+use lumi_macro::ShaderType;
+use lumi_util::math::Vec3;
+use shiv::world::Entity;
+
+pub use lumi_bake::Atmosphere;
+
+/// The sun lighting an [`EnvironmentSource::Atmosphere`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtmosphereSun {
+    /// The direction towards the sun.
+    Direction(Vec3),
+    /// Follows a [`DirectionalLight`](crate::DirectionalLight), the environment is baked again
+    /// when the light is rotated by more than [`AtmosphereSun::REBAKE_ANGLE`].
+    Light(Entity),
+}
+
+impl AtmosphereSun {
+    /// The angle in radians the sun has to move before the environment is baked again.
+    ///
+    /// Baking blocks until the gpu is done, so small movements are ignored.
+    pub const REBAKE_ANGLE: f32 = 0.5 * PI / 180.0;
+
+    /// Returns true if the sun moved far enough from `baked` to bake the environment again.
+    #[inline]
+    pub fn needs_rebake(baked: Vec3, direction: Vec3) -> bool {
+        baked != direction && baked.dot(direction) < f32::cos(Self::REBAKE_ANGLE)
+    }
+}
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawEnvironmentSettings {
+    pub color: Vec3,
+    pub rotation: f32,
+}
+
+impl Default for RawEnvironmentSettings {
+    fn default() -> Self {
+        EnvironmentSettings::default().raw()
+    }
+}
+
+/// Controls how the [`Environment`] is applied, without baking it again.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvironmentSettings {
+    /// The luminance of a value of `1` in the environment, in nits.
+    pub intensity: f32,
+    /// The rotation of the environment around the y axis, in radians.
+    pub rotation: f32,
+    pub tint: Vec3,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 15000.0,
+            rotation: 0.0,
+            tint: Vec3::ONE,
+        }
+    }
+}
+
+impl EnvironmentSettings {
+    pub fn raw(&self) -> RawEnvironmentSettings {
+        RawEnvironmentSettings {
+            color: self.tint * self.intensity,
+            rotation: self.rotation,
+        }
+    }
+}
 
 pub enum EnvironmentSource {
     Baked(EnvironmentData),
     RealTime(ImageData),
+    /// A sky rendered from the scattering of `atmosphere`.
+    Atmosphere {
+        atmosphere: Atmosphere,
+        sun: AtmosphereSun,
+    },
 }
 
 pub type EnvironmentId = Id<Environment>;
@@ -44,7 +118,26 @@ impl Environment {
     }
 
     #[inline]
-    pub fn bake(&self, device: &Device, queue: &Queue) -> BakedEnvironment {
+    pub fn atmosphere(atmosphere: Atmosphere, sun: AtmosphereSun) -> Self {
+        Self {
+            kind: EnvironmentSource::Atmosphere { atmosphere, sun },
+            id: EnvironmentId::new(),
+        }
+    }
+
+    /// Returns the sun of an [`EnvironmentSource::Atmosphere`].
+    #[inline]
+    pub fn sun(&self) -> Option<AtmosphereSun> {
+        match self.kind {
+            EnvironmentSource::Atmosphere { sun, .. } => Some(sun),
+            _ => None,
+        }
+    }
+
+    /// Bakes the environment, `sun_direction` is the direction towards the sun used by
+    /// [`EnvironmentSource::Atmosphere`].
+    #[inline]
+    pub fn bake(&self, device: &Device, queue: &Queue, sun_direction: Vec3) -> BakedEnvironment {
         match &self.kind {
             EnvironmentSource::Baked(data) => BakedEnvironment::from_data(device, queue, data),
             EnvironmentSource::RealTime(image) => BakedEnvironment::from_eq_bytes(
@@ -57,6 +150,15 @@ impl Environment {
                 128,
                 2048,
             ),
+            EnvironmentSource::Atmosphere { atmosphere, .. } => BakedEnvironment::from_atmosphere(
+                device,
+                queue,
+                atmosphere,
+                sun_direction,
+                128,
+                32,
+                512,
+            ),
         }
     }
 
@@ -0,0 +1,612 @@
+use std::ops::Deref;
+
+use lumi_bind::{Bind, BindingLayout, Bindings};
+use lumi_core::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrites,
+    CommandEncoder, ComputePipeline, ComputePipelineDescriptor, Device, Extent3d, FragmentState,
+    LoadOp, Operations, RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
+    ShaderStages, SharedDevice, SharedRenderPipeline, SharedTexture, SharedTextureView,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, VertexState,
+};
+use lumi_macro::ShaderType;
+use lumi_shader::{Shader, ShaderDefs, ShaderProcessor, ShaderRef};
+use lumi_util::{
+    math::{Mat4, Vec3},
+    HashMap,
+};
+use shiv::{
+    bundle::Bundle,
+    query::Query,
+    system::{Commands, Local, Res, ResInit, ResMut},
+    world::{Component, Entity, FromWorld, World},
+};
+use shiv_transform::{GlobalTransform, Transform};
+
+use crate::{
+    Extract, PreparedCamera, PreparedEnvironment, PreparedFogVolumes, PreparedLights,
+    PreparedShadows, RenderDevice, RenderQueue, View,
+};
+
+/// Global settings for volumetric fog.
+///
+/// Fog is only rendered when this resource is present and enabled.
+#[derive(Clone, Debug)]
+pub struct FogSettings {
+    pub enabled: bool,
+    /// The extinction of the fog per meter at [`FogSettings::height`] and below.
+    pub density: f32,
+    /// The height in meters above which the fog starts thinning out.
+    pub height: f32,
+    /// How quickly the fog thins out above [`FogSettings::height`], per meter.
+    pub height_falloff: f32,
+    /// The anisotropy of the scattering, positive values scatter light forward, creating light
+    /// shafts when looking towards a light.
+    pub anisotropy: f32,
+    /// The fraction of the extinction that is scattered, per color channel.
+    pub albedo: Vec3,
+    /// The distance in meters from the camera that fog is rendered to.
+    pub distance: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            density: 0.01,
+            height: 0.0,
+            height_falloff: 0.1,
+            anisotropy: 0.6,
+            albedo: Vec3::ONE,
+            distance: 100.0,
+        }
+    }
+}
+
+impl FogSettings {
+    #[inline]
+    pub fn raw(&self) -> RawFog {
+        RawFog {
+            albedo: self.albedo,
+            density: self.density,
+            height: self.height,
+            height_falloff: self.height_falloff,
+            anisotropy: self.anisotropy.clamp(-0.99, 0.99),
+            near: 0.1,
+            far: f32::max(self.distance, 0.2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawFog {
+    pub albedo: Vec3,
+    pub density: f32,
+    pub height: f32,
+    pub height_falloff: f32,
+    pub anisotropy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawFogVolume {
+    pub volume_from_world: Mat4,
+    pub half_extents: Vec3,
+    pub density: f32,
+    pub albedo: Vec3,
+    pub blend_distance: f32,
+}
+
+/// A box of additional fog, added on top of the fog of [`FogSettings`].
+#[derive(Component, Clone, Debug)]
+pub struct FogVolume {
+    /// The half extents of the box along each local axis.
+    pub half_extents: Vec3,
+    /// The extinction of the fog per meter.
+    pub density: f32,
+    pub albedo: Vec3,
+    /// The distance in meters from the edge of the volume, over which the volume fades in.
+    pub blend_distance: f32,
+}
+
+impl Default for FogVolume {
+    fn default() -> Self {
+        Self {
+            half_extents: Vec3::splat(5.0),
+            density: 0.1,
+            albedo: Vec3::ONE,
+            blend_distance: 0.5,
+        }
+    }
+}
+
+impl FogVolume {
+    #[inline]
+    pub fn raw(&self, transform: &GlobalTransform) -> RawFogVolume {
+        RawFogVolume {
+            volume_from_world: transform.compute_matrix().inverse(),
+            half_extents: self.half_extents,
+            density: self.density,
+            albedo: self.albedo,
+            blend_distance: f32::max(self.blend_distance, 0.0001),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct FogVolumeBundle {
+    pub volume: FogVolume,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+pub fn extract_fog_settings_system(
+    mut commands: Commands,
+    settings: Extract<Option<Res<FogSettings>>>,
+) {
+    if let Some(settings) = settings.deref() {
+        if settings.is_changed() {
+            commands.insert_resource(settings.as_ref().clone());
+        }
+    }
+}
+
+#[derive(Bind)]
+struct FogBindings {
+    #[uniform]
+    fog: RawFog,
+}
+
+#[derive(Bind)]
+struct ScatterBindings<'a> {
+    #[storage_texture(texel_format = rgba16float, access = write, dimension = d3)]
+    fog_scatter_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct IntegrateBindings<'a> {
+    #[texture(dimension = d3)]
+    fog_scatter_texture: &'a SharedTextureView,
+    #[storage_texture(texel_format = rgba16float, access = write, dimension = d3)]
+    fog_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct CompositeBindings<'a> {
+    #[texture(dimension = d3)]
+    #[sampler(name = "fog_sampler")]
+    fog_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct DepthBindings<'a> {
+    #[texture(sample_type = depth)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct MultisampledDepthBindings<'a> {
+    #[texture(sample_type = depth, multisampled = true)]
+    depth_texture: &'a SharedTextureView,
+}
+
+/// The froxel grid of a single camera.
+///
+/// Each froxel covers [`FogState::TILE_SIZE`] pixels squared of the screen, and the view depth
+/// up to [`FogSettings::distance`] is divided into [`FogState::SLICES`] exponentially
+/// distributed slices.
+pub struct FogState {
+    pub scatter_texture: SharedTexture,
+    pub scatter_view: SharedTextureView,
+    pub texture: SharedTexture,
+    pub view: SharedTextureView,
+    pub scatter_bindings: Bindings,
+    pub integrate_bindings: Bindings,
+    pub composite_bindings: Bindings,
+    pub multisampled: bool,
+}
+
+impl FogState {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+    pub const TILE_SIZE: u32 = 8;
+    pub const SLICES: u32 = 64;
+    pub const WORKGROUP_SIZE: u32 = 8;
+
+    pub fn new(
+        device: &Device,
+        pipeline: &FogPipeline,
+        width: u32,
+        height: u32,
+        multisampled: bool,
+    ) -> Self {
+        let size = Extent3d {
+            width: (width + Self::TILE_SIZE - 1) / Self::TILE_SIZE,
+            height: (height + Self::TILE_SIZE - 1) / Self::TILE_SIZE,
+            depth_or_array_layers: Self::SLICES,
+        };
+
+        let scatter_texture = Self::create_texture(device, "Lumi Fog Scatter Texture", size);
+        let texture = Self::create_texture(device, "Lumi Fog Texture", size);
+
+        let composite_layout = if multisampled {
+            &pipeline.multisampled_composite_layout
+        } else {
+            &pipeline.composite_layout
+        };
+
+        Self {
+            scatter_view: scatter_texture.create_view(&Default::default()),
+            scatter_texture,
+            view: texture.create_view(&Default::default()),
+            texture,
+            scatter_bindings: pipeline.scatter_layout.create_bindings(device),
+            integrate_bindings: pipeline.integrate_layout.create_bindings(device),
+            composite_bindings: composite_layout.create_bindings(device),
+            multisampled,
+        }
+    }
+
+    fn create_texture(device: &Device, label: &str, size: Extent3d) -> SharedTexture {
+        device.create_shared_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: Self::FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+        })
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        pipeline: &FogPipeline,
+        width: u32,
+        height: u32,
+        multisampled: bool,
+    ) {
+        let size = self.texture.size();
+        let width_changed = size.width != (width + Self::TILE_SIZE - 1) / Self::TILE_SIZE;
+        let height_changed = size.height != (height + Self::TILE_SIZE - 1) / Self::TILE_SIZE;
+
+        if width_changed || height_changed || self.multisampled != multisampled {
+            *self = Self::new(device, pipeline, width, height, multisampled);
+        }
+    }
+}
+
+pub struct FogPipeline {
+    pub scatter_layout: BindingLayout,
+    pub scatter_pipeline: ComputePipeline,
+    pub integrate_layout: BindingLayout,
+    pub integrate_pipeline: ComputePipeline,
+    pub composite_layout: BindingLayout,
+    pub composite_pipeline: SharedRenderPipeline,
+    pub multisampled_composite_layout: BindingLayout,
+    pub multisampled_composite_pipeline: SharedRenderPipeline,
+}
+
+impl FogPipeline {
+    fn create_compute_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &BindingLayout,
+        shader: &Shader,
+        entry_point: &str,
+    ) -> ComputePipeline {
+        let pipeline_layout = layout.create_pipeline_layout(device);
+
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            module: &shader.shader_module(device),
+            entry_point,
+        })
+    }
+
+    fn create_composite_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &BindingLayout,
+        vertex: &Shader,
+        fragment: &Shader,
+    ) -> SharedRenderPipeline {
+        let pipeline_layout = layout.create_pipeline_layout(device);
+
+        device.create_shared_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &vertex.shader_module(device),
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &fragment.shader_module(device),
+                entry_point: "fragment",
+                targets: &[Some(ColorTargetState {
+                    format: TextureFormat::Rgba16Float,
+                    // the scene is attenuated by the transmittance in alpha, and the in-scattered
+                    // light is added on top
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::SrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    }
+}
+
+impl FromWorld for FogPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut shader_processor = world.resource_mut::<ShaderProcessor>();
+
+        let mut multisampled_defs = ShaderDefs::new();
+        multisampled_defs.push("MULTISAMPLED");
+
+        let mut scatter = shader_processor
+            .process(
+                ShaderRef::module("lumi/fog_scatter.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut integrate = shader_processor
+            .process(
+                ShaderRef::module("lumi/fog_integrate.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut vertex = shader_processor
+            .process(
+                ShaderRef::module("lumi/fullscreen_vert.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut composite = shader_processor
+            .process(ShaderRef::module("lumi/fog_frag.wgsl"), &Default::default())
+            .unwrap();
+        let mut multisampled_composite = shader_processor
+            .process(ShaderRef::module("lumi/fog_frag.wgsl"), &multisampled_defs)
+            .unwrap();
+        scatter.rebind().unwrap();
+        integrate.rebind().unwrap();
+        vertex.rebind_with(&mut composite).unwrap();
+        vertex.rebind_with(&mut multisampled_composite).unwrap();
+
+        let scatter_layout = BindingLayout::new()
+            .with_visibility(ShaderStages::COMPUTE)
+            .with_shader(&scatter)
+            .bind::<PreparedCamera>()
+            .bind::<FogBindings>()
+            .bind::<PreparedFogVolumes>()
+            .bind::<PreparedLights>()
+            .bind::<PreparedShadows>()
+            .bind::<PreparedEnvironment>()
+            .bind::<ScatterBindings>();
+
+        let integrate_layout = BindingLayout::new()
+            .with_visibility(ShaderStages::COMPUTE)
+            .with_shader(&integrate)
+            .bind::<PreparedCamera>()
+            .bind::<FogBindings>()
+            .bind::<IntegrateBindings>();
+
+        let composite_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&composite)
+            .bind::<PreparedCamera>()
+            .bind::<FogBindings>()
+            .bind::<CompositeBindings>()
+            .bind::<DepthBindings>();
+
+        let multisampled_composite_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&multisampled_composite)
+            .bind::<PreparedCamera>()
+            .bind::<FogBindings>()
+            .bind::<CompositeBindings>()
+            .bind::<MultisampledDepthBindings>();
+
+        let device = world.resource::<RenderDevice>();
+
+        let scatter_pipeline = Self::create_compute_pipeline(
+            device,
+            "Lumi Fog Scatter",
+            &scatter_layout,
+            &scatter,
+            "scatter",
+        );
+        let integrate_pipeline = Self::create_compute_pipeline(
+            device,
+            "Lumi Fog Integrate",
+            &integrate_layout,
+            &integrate,
+            "integrate",
+        );
+        let composite_pipeline = Self::create_composite_pipeline(
+            device,
+            "Lumi Fog Composite",
+            &composite_layout,
+            &vertex,
+            &composite,
+        );
+        let multisampled_composite_pipeline = Self::create_composite_pipeline(
+            device,
+            "Lumi Multisampled Fog Composite",
+            &multisampled_composite_layout,
+            &vertex,
+            &multisampled_composite,
+        );
+
+        Self {
+            scatter_layout,
+            scatter_pipeline,
+            integrate_layout,
+            integrate_pipeline,
+            composite_layout,
+            composite_pipeline,
+            multisampled_composite_layout,
+            multisampled_composite_pipeline,
+        }
+    }
+}
+
+pub fn render_fog_system(
+    mut state: Local<HashMap<Entity, FogState>>,
+    mut encoder: ResMut<CommandEncoder>,
+    view: Res<View>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    pipeline: ResInit<FogPipeline>,
+    settings: Option<Res<FogSettings>>,
+    lights: Res<PreparedLights>,
+    shadows: Res<PreparedShadows>,
+    environment: Res<PreparedEnvironment>,
+    fog_volumes: Res<PreparedFogVolumes>,
+    camera_query: Query<&PreparedCamera>,
+) {
+    let settings = match settings {
+        Some(ref settings) if settings.enabled => settings,
+        _ => return,
+    };
+
+    let width = view.frame_buffer.width();
+    let height = view.frame_buffer.height();
+    let multisampled = view.frame_buffer.sample_count() > 1;
+
+    let state = state
+        .entry(view.camera)
+        .or_insert_with(|| FogState::new(&device, &pipeline, width, height, multisampled));
+
+    state.resize(&device, &pipeline, width, height, multisampled);
+
+    let camera = camera_query.get(view.camera).unwrap();
+    let fog_bindings = FogBindings {
+        fog: settings.raw(),
+    };
+
+    state.scatter_bindings.bind(&device, &queue, camera);
+    state.scatter_bindings.bind(&device, &queue, &fog_bindings);
+    state
+        .scatter_bindings
+        .bind(&device, &queue, fog_volumes.deref());
+    state.scatter_bindings.bind(&device, &queue, lights.deref());
+    state
+        .scatter_bindings
+        .bind(&device, &queue, shadows.deref());
+    state
+        .scatter_bindings
+        .bind(&device, &queue, environment.deref());
+    state.scatter_bindings.bind(
+        &device,
+        &queue,
+        &ScatterBindings {
+            fog_scatter_texture: &state.scatter_view,
+        },
+    );
+
+    state.integrate_bindings.bind(&device, &queue, camera);
+    state
+        .integrate_bindings
+        .bind(&device, &queue, &fog_bindings);
+    state.integrate_bindings.bind(
+        &device,
+        &queue,
+        &IntegrateBindings {
+            fog_scatter_texture: &state.scatter_view,
+            fog_texture: &state.view,
+        },
+    );
+
+    state.composite_bindings.bind(&device, &queue, camera);
+    state
+        .composite_bindings
+        .bind(&device, &queue, &fog_bindings);
+    state.composite_bindings.bind(
+        &device,
+        &queue,
+        &CompositeBindings {
+            fog_texture: &state.view,
+        },
+    );
+
+    if multisampled {
+        let depth_bindings = MultisampledDepthBindings {
+            depth_texture: &view.frame_buffer.depth_view,
+        };
+
+        state
+            .composite_bindings
+            .bind(&device, &queue, &depth_bindings);
+    } else {
+        let depth_bindings = DepthBindings {
+            depth_texture: &view.frame_buffer.depth_view,
+        };
+
+        state
+            .composite_bindings
+            .bind(&device, &queue, &depth_bindings);
+    }
+
+    state.scatter_bindings.update_bind_groups(&device);
+    state.integrate_bindings.update_bind_groups(&device);
+    state.composite_bindings.update_bind_groups(&device);
+
+    let size = state.texture.size();
+    let groups_x = (size.width + FogState::WORKGROUP_SIZE - 1) / FogState::WORKGROUP_SIZE;
+    let groups_y = (size.height + FogState::WORKGROUP_SIZE - 1) / FogState::WORKGROUP_SIZE;
+
+    let mut scatter_pass = encoder.begin_compute_pass(&Default::default());
+
+    scatter_pass.set_pipeline(&pipeline.scatter_pipeline);
+    state.scatter_bindings.apply_compute(&mut scatter_pass);
+    scatter_pass.dispatch_workgroups(groups_x, groups_y, size.depth_or_array_layers);
+
+    drop(scatter_pass);
+
+    let mut integrate_pass = encoder.begin_compute_pass(&Default::default());
+
+    integrate_pass.set_pipeline(&pipeline.integrate_pipeline);
+    state.integrate_bindings.apply_compute(&mut integrate_pass);
+    integrate_pass.dispatch_workgroups(groups_x, groups_y, 1);
+
+    drop(integrate_pass);
+
+    let composite_pipeline = if multisampled {
+        &pipeline.multisampled_composite_pipeline
+    } else {
+        &pipeline.composite_pipeline
+    };
+
+    let mut composite_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Lumi Fog Composite Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: &view.frame_buffer.hdr_view,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Load,
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    composite_pass.set_pipeline(composite_pipeline);
+    state.composite_bindings.apply(&mut composite_pass);
+    composite_pass.draw(0..3, 0..1);
+}
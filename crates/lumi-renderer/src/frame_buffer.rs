@@ -87,7 +87,7 @@ impl FrameBuffer {
             sample_count,
             dimension: TextureDimension::D2,
             format: TextureFormat::Depth32Float,
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
         });
 
         let hdr_view = hdr.create_view(&Default::default());
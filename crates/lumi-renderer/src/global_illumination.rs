@@ -0,0 +1,535 @@
+use std::ops::Deref;
+
+use lumi_bind::{Bind, BindingLayout, Bindings};
+use lumi_core::{
+    BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d,
+    FragmentState, ImageCopyTexture, LoadOp, Operations, Origin3d, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, SharedDevice,
+    SharedRenderPipeline, SharedTexture, SharedTextureView, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, VertexState,
+};
+use lumi_macro::ShaderType;
+use lumi_shader::{Shader, ShaderDefs, ShaderProcessor, ShaderRef};
+use shiv::{
+    system::{Commands, Res},
+    world::{FromWorld, World},
+};
+
+use crate::{Extract, PreparedCamera, RawCamera, RenderDevice};
+
+/// Screen space diffuse global illumination.
+///
+/// Rays are traced against the depth buffer and gather light from the previous frame, which
+/// is accumulated over time, so light keeps bouncing between surfaces over several frames.
+#[derive(Clone, Debug)]
+pub struct GlobalIlluminationSettings {
+    pub enabled: bool,
+    /// The maximum distance in meters a ray is traced.
+    pub radius: f32,
+    /// The assumed thickness in meters of surfaces in the depth buffer.
+    pub thickness: f32,
+    /// The multiplier applied to the gathered light.
+    pub intensity: f32,
+    /// The weight of the previous frames when accumulating, higher values reduce noise but
+    /// make the lighting respond slower to changes.
+    pub history_weight: f32,
+    /// The number of rays traced per pixel.
+    pub ray_count: u32,
+    /// The number of samples taken along each ray.
+    pub step_count: u32,
+}
+
+impl Default for GlobalIlluminationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 2.0,
+            thickness: 0.25,
+            intensity: 1.0,
+            history_weight: 0.9,
+            ray_count: 2,
+            step_count: 12,
+        }
+    }
+}
+
+impl GlobalIlluminationSettings {
+    #[inline]
+    pub fn raw(&self, frame: u32) -> RawGlobalIllumination {
+        RawGlobalIllumination {
+            radius: self.radius,
+            thickness: self.thickness,
+            intensity: self.intensity,
+            history_weight: self.history_weight,
+            ray_count: self.ray_count,
+            step_count: self.step_count,
+            frame,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawGlobalIllumination {
+    pub radius: f32,
+    pub thickness: f32,
+    pub intensity: f32,
+    pub history_weight: f32,
+    pub ray_count: u32,
+    pub step_count: u32,
+    pub frame: u32,
+}
+
+pub fn extract_global_illumination_settings_system(
+    mut commands: Commands,
+    settings: Extract<Option<Res<GlobalIlluminationSettings>>>,
+) {
+    if let Some(settings) = settings.deref() {
+        if settings.is_changed() {
+            commands.insert_resource(settings.as_ref().clone());
+        }
+    }
+}
+
+/// The textures global illumination is rendered to.
+///
+/// All store the indirect light in the rgb channels and the linear view depth of the lit
+/// surface in the alpha channel.
+pub struct GlobalIlluminationTarget {
+    pub noisy_texture: SharedTexture,
+    pub noisy_view: SharedTextureView,
+    pub texture: SharedTexture,
+    pub view: SharedTextureView,
+    pub history_texture: SharedTexture,
+    pub history_view: SharedTextureView,
+}
+
+impl GlobalIlluminationTarget {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let noisy_texture = Self::create_texture(
+            device,
+            "Lumi Noisy GI Target",
+            width,
+            height,
+            TextureUsages::empty(),
+        );
+        let texture = Self::create_texture(
+            device,
+            "Lumi GI Target",
+            width,
+            height,
+            TextureUsages::COPY_SRC,
+        );
+        let history_texture = Self::create_texture(
+            device,
+            "Lumi GI History",
+            width,
+            height,
+            TextureUsages::COPY_DST,
+        );
+
+        Self {
+            noisy_view: noisy_texture.create_view(&Default::default()),
+            noisy_texture,
+            view: texture.create_view(&Default::default()),
+            texture,
+            history_view: history_texture.create_view(&Default::default()),
+            history_texture,
+        }
+    }
+
+    fn create_texture(
+        device: &Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        usage: TextureUsages,
+    ) -> SharedTexture {
+        device.create_shared_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING | usage,
+        })
+    }
+
+    pub fn size(&self) -> Extent3d {
+        self.texture.size()
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let size = self.size();
+
+        if size.width != width || size.height != height {
+            *self = Self::new(device, width, height);
+        }
+    }
+
+    /// Clears the indirect light and the history.
+    pub fn clear(&self, encoder: &mut CommandEncoder) {
+        for view in [&self.view, &self.history_view] {
+            encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Lumi GI Clear Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+        }
+    }
+
+    /// Traces global illumination from the depth and color bound in `bindings` and accumulates
+    /// it with the previous frames.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &GlobalIlluminationPipeline,
+        bindings: &GlobalIlluminationBindings,
+    ) {
+        let (trace_pipeline, accumulate_pipeline) = if bindings.multisampled {
+            (
+                &pipeline.multisampled_trace_pipeline,
+                &pipeline.multisampled_accumulate_pipeline,
+            )
+        } else {
+            (&pipeline.trace_pipeline, &pipeline.accumulate_pipeline)
+        };
+
+        let mut trace_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi GI Trace Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.noisy_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        trace_pass.set_pipeline(trace_pipeline);
+        bindings.trace.apply(&mut trace_pass);
+        trace_pass.draw(0..3, 0..1);
+
+        drop(trace_pass);
+
+        let mut accumulate_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi GI Accumulate Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        accumulate_pass.set_pipeline(accumulate_pipeline);
+        bindings.accumulate.apply(&mut accumulate_pass);
+        accumulate_pass.draw(0..3, 0..1);
+
+        drop(accumulate_pass);
+
+        encoder.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: self.texture.texture(),
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyTexture {
+                texture: self.history_texture.texture(),
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            self.size(),
+        );
+    }
+}
+
+#[derive(Bind)]
+struct SsgiBindings {
+    #[uniform]
+    global_illumination: RawGlobalIllumination,
+    #[uniform]
+    previous_camera: RawCamera,
+}
+
+#[derive(Bind)]
+struct TraceBindings<'a> {
+    #[texture]
+    #[sampler(name = "color_sampler")]
+    color_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct AccumulateBindings<'a> {
+    #[texture]
+    gi_noisy_texture: &'a SharedTextureView,
+    #[texture]
+    #[sampler(name = "gi_history_sampler")]
+    gi_history_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct DepthBindings<'a> {
+    #[texture(sample_type = depth)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct MultisampledDepthBindings<'a> {
+    #[texture(sample_type = depth, multisampled = true)]
+    depth_texture: &'a SharedTextureView,
+}
+
+/// The bindings of the global illumination passes of a single camera.
+pub struct GlobalIlluminationBindings {
+    pub trace: Bindings,
+    pub accumulate: Bindings,
+    pub multisampled: bool,
+    /// The camera of the previous frame, which the history and color were rendered with.
+    pub previous_camera: Option<RawCamera>,
+    pub frame: u32,
+}
+
+impl GlobalIlluminationBindings {
+    pub fn new(device: &Device, pipeline: &GlobalIlluminationPipeline, multisampled: bool) -> Self {
+        let (trace_layout, accumulate_layout) = if multisampled {
+            (
+                &pipeline.multisampled_trace_layout,
+                &pipeline.multisampled_accumulate_layout,
+            )
+        } else {
+            (&pipeline.trace_layout, &pipeline.accumulate_layout)
+        };
+
+        Self {
+            trace: trace_layout.create_bindings(device),
+            accumulate: accumulate_layout.create_bindings(device),
+            multisampled,
+            previous_camera: None,
+            frame: 0,
+        }
+    }
+
+    /// Binds the resources of this frame, `color` is the mip chain of the previous frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bind(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        settings: &GlobalIlluminationSettings,
+        camera: &PreparedCamera,
+        depth: &SharedTextureView,
+        color: &SharedTextureView,
+        target: &GlobalIlluminationTarget,
+    ) {
+        let raw_camera = *camera.camera;
+
+        let ssgi_bindings = SsgiBindings {
+            global_illumination: settings.raw(self.frame),
+            previous_camera: self.previous_camera.unwrap_or(raw_camera),
+        };
+
+        self.trace.bind(device, queue, camera);
+        self.trace.bind(device, queue, &ssgi_bindings);
+        self.accumulate.bind(device, queue, camera);
+        self.accumulate.bind(device, queue, &ssgi_bindings);
+
+        if self.multisampled {
+            let depth_bindings = MultisampledDepthBindings {
+                depth_texture: depth,
+            };
+
+            self.trace.bind(device, queue, &depth_bindings);
+            self.accumulate.bind(device, queue, &depth_bindings);
+        } else {
+            let depth_bindings = DepthBindings {
+                depth_texture: depth,
+            };
+
+            self.trace.bind(device, queue, &depth_bindings);
+            self.accumulate.bind(device, queue, &depth_bindings);
+        }
+
+        let trace_bindings = TraceBindings {
+            color_texture: color,
+        };
+
+        let accumulate_bindings = AccumulateBindings {
+            gi_noisy_texture: &target.noisy_view,
+            gi_history_texture: &target.history_view,
+        };
+
+        self.trace.bind(device, queue, &trace_bindings);
+        self.accumulate.bind(device, queue, &accumulate_bindings);
+
+        self.trace.update_bind_groups(device);
+        self.accumulate.update_bind_groups(device);
+
+        self.previous_camera = Some(raw_camera);
+        self.frame = self.frame.wrapping_add(1);
+    }
+}
+
+pub struct GlobalIlluminationPipeline {
+    pub trace_layout: BindingLayout,
+    pub trace_pipeline: SharedRenderPipeline,
+    pub multisampled_trace_layout: BindingLayout,
+    pub multisampled_trace_pipeline: SharedRenderPipeline,
+    pub accumulate_layout: BindingLayout,
+    pub accumulate_pipeline: SharedRenderPipeline,
+    pub multisampled_accumulate_layout: BindingLayout,
+    pub multisampled_accumulate_pipeline: SharedRenderPipeline,
+}
+
+impl GlobalIlluminationPipeline {
+    fn create_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &BindingLayout,
+        vertex: &Shader,
+        fragment: &Shader,
+    ) -> SharedRenderPipeline {
+        let pipeline_layout = layout.create_pipeline_layout(device);
+
+        device.create_shared_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &vertex.shader_module(device),
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &fragment.shader_module(device),
+                entry_point: "fragment",
+                targets: &[Some(ColorTargetState {
+                    format: GlobalIlluminationTarget::FORMAT,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_layout<T: Bind>(vertex: &Shader, fragment: &Shader) -> BindingLayout {
+        BindingLayout::new()
+            .with_shader(vertex)
+            .with_shader(fragment)
+            .bind::<PreparedCamera>()
+            .bind::<SsgiBindings>()
+            .bind::<T>()
+    }
+}
+
+impl FromWorld for GlobalIlluminationPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut shader_processor = world.resource_mut::<ShaderProcessor>();
+
+        let mut multisampled_defs = ShaderDefs::new();
+        multisampled_defs.push("MULTISAMPLED");
+
+        let mut vertex = shader_processor
+            .process(
+                ShaderRef::module("lumi/fullscreen_vert.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut trace = shader_processor
+            .process(
+                ShaderRef::module("lumi/ssgi_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut multisampled_trace = shader_processor
+            .process(ShaderRef::module("lumi/ssgi_frag.wgsl"), &multisampled_defs)
+            .unwrap();
+        let mut accumulate = shader_processor
+            .process(
+                ShaderRef::module("lumi/ssgi_accumulate_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut multisampled_accumulate = shader_processor
+            .process(
+                ShaderRef::module("lumi/ssgi_accumulate_frag.wgsl"),
+                &multisampled_defs,
+            )
+            .unwrap();
+        vertex.rebind_with(&mut trace).unwrap();
+        vertex.rebind_with(&mut multisampled_trace).unwrap();
+        vertex.rebind_with(&mut accumulate).unwrap();
+        vertex.rebind_with(&mut multisampled_accumulate).unwrap();
+
+        let trace_layout =
+            Self::create_layout::<DepthBindings>(&vertex, &trace).bind::<TraceBindings>();
+        let multisampled_trace_layout =
+            Self::create_layout::<MultisampledDepthBindings>(&vertex, &multisampled_trace)
+                .bind::<TraceBindings>();
+        let accumulate_layout =
+            Self::create_layout::<DepthBindings>(&vertex, &accumulate).bind::<AccumulateBindings>();
+        let multisampled_accumulate_layout =
+            Self::create_layout::<MultisampledDepthBindings>(&vertex, &multisampled_accumulate)
+                .bind::<AccumulateBindings>();
+
+        let device = world.resource::<RenderDevice>();
+
+        let trace_pipeline =
+            Self::create_pipeline(device, "Lumi GI Trace", &trace_layout, &vertex, &trace);
+        let multisampled_trace_pipeline = Self::create_pipeline(
+            device,
+            "Lumi Multisampled GI Trace",
+            &multisampled_trace_layout,
+            &vertex,
+            &multisampled_trace,
+        );
+        let accumulate_pipeline = Self::create_pipeline(
+            device,
+            "Lumi GI Accumulate",
+            &accumulate_layout,
+            &vertex,
+            &accumulate,
+        );
+        let multisampled_accumulate_pipeline = Self::create_pipeline(
+            device,
+            "Lumi Multisampled GI Accumulate",
+            &multisampled_accumulate_layout,
+            &vertex,
+            &multisampled_accumulate,
+        );
+
+        Self {
+            trace_layout,
+            trace_pipeline,
+            multisampled_trace_layout,
+            multisampled_trace_pipeline,
+            accumulate_layout,
+            accumulate_pipeline,
+            multisampled_accumulate_layout,
+            multisampled_accumulate_pipeline,
+        }
+    }
+}
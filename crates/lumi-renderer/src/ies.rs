@@ -0,0 +1,389 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+use lumi_core::{Image, ImageData, TextureFormat};
+use lumi_util::thiserror;
+
+#[derive(thiserror::Error, Debug)]
+pub enum IesError {
+    #[error("missing TILT line")]
+    MissingTilt,
+    #[error("unexpected end of file")]
+    UnexpectedEof,
+    #[error("invalid number {0:?}")]
+    InvalidNumber(String),
+    #[error("unsupported photometric type {0}")]
+    UnsupportedPhotometricType(u32),
+    #[error("angles must be sorted in increasing order")]
+    UnsortedAngles,
+    #[error("invalid count {0}, expected between 1 and {max}", max = IesData::MAX_COUNT)]
+    InvalidCount(f32),
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+/// Photometric data of a luminaire, parsed from an IESNA LM-63 file.
+///
+/// Only type C photometry is supported, where vertical angles are measured from the nadir of the
+/// luminaire and horizontal angles around it.
+#[derive(Clone, Debug, Default)]
+pub struct IesData {
+    /// The vertical angles in degrees, `0` points straight down.
+    pub vertical_angles: Vec<f32>,
+    /// The horizontal angles in degrees.
+    pub horizontal_angles: Vec<f32>,
+    /// The candela values, one row of vertical angles for each horizontal angle.
+    pub candela: Vec<Vec<f32>>,
+}
+
+impl IesData {
+    /// The largest number of angles or tilt pairs accepted by [`IesData::parse`].
+    pub const MAX_COUNT: usize = 4096;
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IesError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, IesError> {
+        let mut lines = source.lines();
+
+        // skip the header and keywords
+        let tilt = loop {
+            let line = lines.next().ok_or(IesError::MissingTilt)?.trim();
+
+            if let Some(tilt) = line.strip_prefix("TILT=") {
+                break tilt.trim();
+            }
+        };
+
+        let mut tokens = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty());
+        let mut next = move || -> Result<f32, IesError> {
+            let token = tokens.next().ok_or(IesError::UnexpectedEof)?;
+            f32::from_str(token).map_err(|_| IesError::InvalidNumber(String::from(token)))
+        };
+
+        // counts are bounded before anything is allocated for them
+        let count = |value: f32| -> Result<usize, IesError> {
+            let valid = value >= 1.0 && value <= Self::MAX_COUNT as f32 && value.fract() == 0.0;
+            valid
+                .then(|| value as usize)
+                .ok_or(IesError::InvalidCount(value))
+        };
+
+        // tilt data only applies to luminaires mounted at an angle, skip it
+        if tilt == "INCLUDE" {
+            let _geometry = next()?;
+            let pair_count = count(next()?)?;
+
+            for _ in 0..pair_count * 2 {
+                next()?;
+            }
+        }
+
+        let _lamp_count = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = count(next()?)?;
+        let horizontal_count = count(next()?)?;
+        let photometric_type = next()? as u32;
+        let _units = next()?;
+        let _width = next()?;
+        let _length = next()?;
+        let _height = next()?;
+        let _ballast_factor = next()?;
+        let _future_use = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1 {
+            return Err(IesError::UnsupportedPhotometricType(photometric_type));
+        }
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let is_sorted = |angles: &[f32]| angles.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !is_sorted(&vertical_angles) || !is_sorted(&horizontal_angles) {
+            return Err(IesError::UnsortedAngles);
+        }
+
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            let row = (0..vertical_count)
+                .map(|_| Ok(next()? * multiplier))
+                .collect::<Result<Vec<_>, IesError>>()?;
+
+            candela.push(row);
+        }
+
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+        })
+    }
+
+    #[inline]
+    pub fn max_candela(&self) -> f32 {
+        self.candela.iter().flatten().copied().fold(0.0, f32::max)
+    }
+
+    /// Maps `horizontal` in degrees into the range covered by the horizontal angles, using the
+    /// symmetry implied by the last angle.
+    fn fold_horizontal(&self, horizontal: f32) -> f32 {
+        let last = self.horizontal_angles.last().copied().unwrap_or_default();
+        let horizontal = horizontal.rem_euclid(360.0);
+
+        if last <= 0.0 {
+            // rotationally symmetric
+            0.0
+        } else if last <= 90.0 {
+            // symmetric in each quadrant
+            let horizontal = horizontal % 180.0;
+            f32::min(horizontal, 180.0 - horizontal)
+        } else if last <= 180.0 {
+            // symmetric about the 0-180 degree plane
+            f32::min(horizontal, 360.0 - horizontal)
+        } else {
+            horizontal
+        }
+    }
+
+    /// Returns the candela in the direction given by `vertical` and `horizontal` angles in
+    /// degrees, linearly interpolated between the measured angles.
+    pub fn sample(&self, vertical: f32, horizontal: f32) -> f32 {
+        let (first, last) = match (self.vertical_angles.first(), self.vertical_angles.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        // no light is emitted outside the measured vertical range
+        if vertical < first || vertical > last {
+            return 0.0;
+        }
+
+        let (v0, v1, vt) = interpolation(&self.vertical_angles, vertical);
+        let horizontal = self.fold_horizontal(horizontal);
+        let (h0, h1, ht) = interpolation(&self.horizontal_angles, horizontal);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let row = |h: usize| lerp(self.candela[h][v0], self.candela[h][v1], vt);
+
+        lerp(row(h0), row(h1), ht)
+    }
+}
+
+/// Finds the two angles surrounding `angle` and the interpolation factor between them.
+fn interpolation(angles: &[f32], angle: f32) -> (usize, usize, f32) {
+    let upper = angles.partition_point(|&a| a < angle);
+
+    if upper == 0 {
+        return (0, 0, 0.0);
+    }
+
+    if upper == angles.len() {
+        return (upper - 1, upper - 1, 0.0);
+    }
+
+    let lower = upper - 1;
+    let range = angles[upper] - angles[lower];
+    let t = if range > 0.0 {
+        (angle - angles[lower]) / range
+    } else {
+        0.0
+    };
+
+    (lower, upper, t)
+}
+
+/// Converts a non-negative `value` to the bits of a half precision float.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent >= 31 {
+        return 0x7c00;
+    }
+
+    if exponent <= 0 {
+        if exponent < -10 {
+            return 0;
+        }
+
+        // subnormal
+        let mantissa = (mantissa | 0x0080_0000) >> (1 - exponent);
+        return ((mantissa + 0x1000) >> 13) as u16;
+    }
+
+    (((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1)) as u16
+}
+
+/// An IES light profile, modulating the intensity of a [`PointLight`](crate::PointLight) or
+/// [`SpotLight`](crate::SpotLight) by direction.
+///
+/// The profile is stored as a lookup texture, indexed by the horizontal angle along `u` and the
+/// vertical angle along `v`, and normalized to the brightest direction. The intensity of the light
+/// is still given by the light itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IesProfile {
+    image: Image,
+}
+
+impl IesProfile {
+    pub const WIDTH: u32 = 256;
+    pub const HEIGHT: u32 = 128;
+    pub const FORMAT: TextureFormat = TextureFormat::R16Float;
+
+    pub fn new(data: &IesData) -> Self {
+        let max_candela = f32::max(data.max_candela(), f32::EPSILON);
+        let mut bytes = Vec::with_capacity((Self::WIDTH * Self::HEIGHT * 2) as usize);
+
+        for y in 0..Self::HEIGHT {
+            let vertical = (y as f32 + 0.5) / Self::HEIGHT as f32 * 180.0;
+
+            for x in 0..Self::WIDTH {
+                let horizontal = (x as f32 + 0.5) / Self::WIDTH as f32 * 360.0;
+
+                let value = data.sample(vertical, horizontal) / max_candela;
+                bytes.extend_from_slice(&f16_bits(value.clamp(0.0, 1.0)).to_le_bytes());
+            }
+        }
+
+        let image = ImageData::with_format(Self::WIDTH, Self::HEIGHT, bytes, Self::FORMAT);
+
+        Self {
+            image: Image::new(image),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IesError> {
+        Ok(Self::new(&IesData::open(path)?))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, IesError> {
+        Ok(Self::new(&IesData::parse(source)?))
+    }
+
+    /// Returns the lookup texture of the profile.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "IESNA:LM-63-2002
+[TEST] sample
+[MANUFAC] lumi
+TILT=NONE
+1 1000 2 3 2 1 2 0.5 0.5 0
+1 1 100
+0 45 90
+0 90
+50 25 0
+40 20 0
+";
+
+    fn sample_with(tilt: &str, counts: &str) -> String {
+        SAMPLE
+            .replace("TILT=NONE", tilt)
+            .replace("1 1000 2 3 2 1", counts)
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = IesData::parse(SAMPLE).unwrap();
+
+        assert_eq!(data.vertical_angles, [0.0, 45.0, 90.0]);
+        assert_eq!(data.horizontal_angles, [0.0, 90.0]);
+        // candela are scaled by the multiplier
+        assert_eq!(data.candela, [[100.0, 50.0, 0.0], [80.0, 40.0, 0.0]]);
+        assert_eq!(data.max_candela(), 100.0);
+    }
+
+    #[test]
+    fn test_parse_tilt_include() {
+        let source = sample_with("TILT=INCLUDE\n1\n3\n0 45 90\n1.0 0.9 0.8", "1 1000 2 3 2 1");
+        let data = IesData::parse(&source).unwrap();
+
+        assert_eq!(data.vertical_angles, [0.0, 45.0, 90.0]);
+        assert_eq!(data.candela[1], [80.0, 40.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let data = IesData::parse(SAMPLE).unwrap();
+
+        assert_eq!(data.sample(0.0, 0.0), 100.0);
+        assert_eq!(data.sample(22.5, 0.0), 75.0);
+        assert_eq!(data.sample(45.0, 45.0), 45.0);
+        // no light is emitted outside the vertical range
+        assert_eq!(data.sample(-1.0, 0.0), 0.0);
+        assert_eq!(data.sample(120.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_sample_symmetry() {
+        // a last horizontal angle of 90 is symmetric in each quadrant
+        let data = IesData::parse(SAMPLE).unwrap();
+
+        assert_eq!(data.sample(0.0, 270.0), data.sample(0.0, 90.0));
+        assert_eq!(data.sample(0.0, 180.0), data.sample(0.0, 0.0));
+        assert_eq!(data.sample(45.0, 135.0), data.sample(45.0, 45.0));
+        assert_eq!(data.sample(45.0, -45.0), data.sample(45.0, 45.0));
+
+        // a single horizontal angle is rotationally symmetric
+        let source = sample_with("TILT=NONE", "1 1000 2 3 1 1").replace("0 90\n", "0\n");
+        let data = IesData::parse(&source).unwrap();
+
+        assert_eq!(data.sample(45.0, 0.0), 50.0);
+        assert_eq!(data.sample(45.0, 200.0), 50.0);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |source: &str| IesData::parse(source).unwrap_err();
+
+        assert!(matches!(error("IESNA:LM-63-2002\n"), IesError::MissingTilt));
+        assert!(matches!(
+            error(&sample_with("TILT=NONE", "1 1000 2 3 0 1")),
+            IesError::InvalidCount(count) if count == 0.0
+        ));
+        assert!(matches!(
+            error(&sample_with("TILT=NONE", "1 1000 2 0 2 1")),
+            IesError::InvalidCount(_)
+        ));
+        assert!(matches!(
+            error(&sample_with("TILT=NONE", "1 1000 2 3 1e9 1")),
+            IesError::InvalidCount(_)
+        ));
+        assert!(matches!(
+            error(&sample_with("TILT=INCLUDE\n1\n-1", "1 1000 2 3 2 1")),
+            IesError::InvalidCount(_)
+        ));
+        assert!(matches!(
+            error(&sample_with("TILT=NONE", "1 1000 2 3 2 2")),
+            IesError::UnsupportedPhotometricType(2)
+        ));
+        assert!(matches!(
+            error(&SAMPLE.replace("0 45 90", "0 90 45")),
+            IesError::UnsortedAngles
+        ));
+        assert!(matches!(
+            error(&SAMPLE.replace("40 20 0\n", "")),
+            IesError::UnexpectedEof
+        ));
+        assert!(matches!(
+            error(&SAMPLE.replace("40 20 0", "40 twenty 0")),
+            IesError::InvalidNumber(number) if number == "twenty"
+        ));
+    }
+}
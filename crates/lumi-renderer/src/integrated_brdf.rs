@@ -6,6 +6,10 @@ pub struct IntegratedBrdf {
     #[texture(name = "integrated_brdf")]
     #[sampler(name = "integrated_brdf_sampler")]
     pub image: Image,
+    /// The DFG term of the Charlie sheen distribution with Ashikhmin visibility, indexed like
+    /// `image`, generated by the `sheen_brdf` binary of lumi-bake.
+    #[texture(name = "integrated_sheen_brdf")]
+    pub sheen: Image,
 }
 
 impl Default for IntegratedBrdf {
@@ -18,8 +22,16 @@ impl Default for IntegratedBrdf {
             TextureFormat::Rgba8Unorm,
         );
 
+        let sheen = ImageData::with_format(
+            64,
+            64,
+            include_bytes!("integrated_sheen_brdf").to_vec(),
+            TextureFormat::R16Float,
+        );
+
         Self {
             image: Image::new(data),
+            sheen: Image::new(sheen),
         }
     }
 }
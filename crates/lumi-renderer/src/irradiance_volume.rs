@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use lumi_bake::{IrradianceVolumeData, SphericalHarmonics};
+use lumi_core::{Device, Queue};
+use lumi_util::math::{UVec3, Vec3};
+
+use shiv::{bundle::Bundle, world::Component};
+use shiv_transform::{GlobalTransform, Transform};
+
+use crate::{PreparedIrradianceVolumes, PreparedTransform, RawProbe, Renderer};
+
+/// A grid of light probes providing diffuse indirect light inside a box.
+///
+/// The scene is rendered at each probe and stored as [`SphericalHarmonics`], when
+/// [`Renderer::capture_irradiance_volumes`] is called after the volume was added or changed.
+/// The captured probes can be read from [`PreparedIrradianceVolumes::data`] and saved, to be
+/// loaded into [`IrradianceVolume::data`] later.
+#[derive(Component, Clone, Debug)]
+pub struct IrradianceVolume {
+    /// The half extents of the box along each local axis.
+    pub half_extents: Vec3,
+    /// The number of probes along each axis.
+    pub resolution: UVec3,
+    /// The distance in meters from the edge of the volume, over which the volume fades in.
+    pub blend_distance: f32,
+    /// The resolution of each cube face rendered when capturing.
+    pub capture_resolution: u32,
+    /// The near plane used when capturing.
+    pub near: f32,
+    pub intensity: f32,
+    /// Baked probes, the volume isn't captured when set.
+    pub data: Option<Arc<IrradianceVolumeData>>,
+}
+
+impl Default for IrradianceVolume {
+    fn default() -> Self {
+        Self {
+            half_extents: Vec3::splat(5.0),
+            resolution: UVec3::splat(4),
+            blend_distance: 0.5,
+            capture_resolution: 32,
+            near: 0.05,
+            intensity: 1.0,
+            data: None,
+        }
+    }
+}
+
+impl IrradianceVolume {
+    /// Returns the local position of the probe at `index` in the grid.
+    pub fn probe_position(&self, index: UVec3) -> Vec3 {
+        let steps = (self.resolution.as_vec3() - 1.0).max(Vec3::ONE);
+        let position = -self.half_extents + self.half_extents * 2.0 * index.as_vec3() / steps;
+
+        // a single probe along an axis is placed in the center
+        Vec3::select(self.resolution.cmpgt(UVec3::ONE), position, Vec3::ZERO)
+    }
+
+    pub fn raw(
+        &self,
+        transform: &GlobalTransform,
+        data: &IrradianceVolumeData,
+        layer: u32,
+    ) -> RawProbe {
+        RawProbe {
+            probe_from_world: transform.compute_matrix().inverse(),
+            position: transform.translation,
+            shape: 0,
+            half_extents: self.half_extents,
+            radius: 0.0,
+            resolution: data.resolution.as_vec3(),
+            blend_distance: f32::max(self.blend_distance, 0.0001),
+            intensity: self.intensity / data.exposure,
+            layer: layer as i32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct IrradianceVolumeBundle {
+    pub volume: IrradianceVolume,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl Renderer {
+    /// Captures the [`IrradianceVolume`]s without [`IrradianceVolume::data`] that were added or
+    /// changed since they were last captured.
+    ///
+    /// This should be called after [`Renderer::extract`] and before rendering any cameras, the
+    /// captures are used from the next extract.
+    pub fn capture_irradiance_volumes(&mut self, device: &Device, queue: &Queue) {
+        let pending = match self.world.get_resource_mut::<PreparedIrradianceVolumes>() {
+            Some(mut prepared) => std::mem::take(&mut prepared.pending),
+            None => return,
+        };
+
+        for entity in pending {
+            let volume = match self.world.get::<IrradianceVolume>(entity) {
+                Some(volume) => volume.clone(),
+                None => continue,
+            };
+
+            let transform = match self.world.get::<PreparedTransform>(entity) {
+                Some(prepared) => prepared.transform,
+                None => continue,
+            };
+
+            let mut data = IrradianceVolumeData {
+                resolution: volume.resolution,
+                ..Default::default()
+            };
+
+            for z in 0..volume.resolution.z {
+                for y in 0..volume.resolution.y {
+                    for x in 0..volume.resolution.x {
+                        let local = volume.probe_position(UVec3::new(x, y, z));
+                        let position = transform.transform_point3(local);
+
+                        let capture = self.capture_cube(
+                            device,
+                            queue,
+                            entity,
+                            position,
+                            volume.capture_resolution,
+                            volume.near,
+                        );
+
+                        let probe = SphericalHarmonics::from_faces(
+                            device,
+                            queue,
+                            &capture.faces,
+                            capture.size,
+                        );
+
+                        data.exposure = capture.exposure;
+                        data.probes.push(probe);
+                    }
+                }
+            }
+
+            let mut prepared = self.world.resource_mut::<PreparedIrradianceVolumes>();
+            prepared.insert_data(entity, Arc::new(data));
+        }
+    }
+}
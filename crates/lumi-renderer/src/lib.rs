@@ -1,34 +1,58 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
+mod ambient_occlusion;
+mod area_light;
 mod bloom;
 mod camera;
+mod capture;
 mod draw;
 mod environment;
 mod extract;
+mod fog;
 mod frame_buffer;
+mod global_illumination;
+mod ies;
 mod integrated_brdf;
+mod irradiance_volume;
 mod light;
+mod lightmap;
+mod ltc;
 mod mip_chain;
 mod plugin;
 mod prepare;
+mod reflection_probe;
+mod render_layers;
 mod resource;
 mod screen_space;
+mod screen_space_reflection;
 mod sky;
 mod tone_mapping;
 
+pub use ambient_occlusion::*;
+pub use area_light::*;
 pub use bloom::*;
 pub use camera::*;
+pub use capture::*;
 pub use draw::*;
 pub use environment::*;
 pub use extract::*;
+pub use fog::*;
 pub use frame_buffer::*;
+pub use global_illumination::*;
+pub use ies::*;
 pub use integrated_brdf::*;
+pub use irradiance_volume::*;
 pub use light::*;
+pub use lightmap::*;
+pub use ltc::*;
 pub use mip_chain::*;
 pub use plugin::*;
 pub use prepare::*;
+pub use reflection_probe::*;
+pub use render_layers::*;
 pub use resource::*;
 pub use screen_space::*;
+pub use screen_space_reflection::*;
 pub use sky::*;
 pub use tone_mapping::*;
 
@@ -39,7 +63,7 @@ pub use shiv::{
 };
 pub use shiv_transform::*;
 
-use lumi_core::{CommandEncoder, Device, Queue, RenderTarget, TextureView};
+use lumi_core::{CommandEncoder, Device, Limits, Queue, RenderTarget, TextureView};
 use lumi_util::HashMap;
 
 use shiv::schedule::Schedule;
@@ -79,6 +103,19 @@ impl Renderer {
         }
     }
 
+    /// The [`Limits`] the device must be created with.
+    ///
+    /// A `StandardMaterial` using every map and lobe binds 30 sampled textures in the fragment
+    /// stage, above the default of 16. 31 is the lowest limit of Metal, desktop Vulkan and
+    /// DirectX 12 adapters support more, while GL is limited to 16.
+    #[inline]
+    pub fn limits() -> Limits {
+        Limits {
+            max_sampled_textures_per_shader_stage: 31,
+            ..Default::default()
+        }
+    }
+
     pub fn add_plugin(&mut self, plugin: impl RendererPlugin) -> &mut Self {
         plugin.build(self);
 
@@ -137,12 +174,20 @@ impl Renderer {
 
         let camera = self.world.entity(camera_entity);
         let camera = camera.get::<Camera>().expect("camera not found");
+        let is_main = camera.target == CameraTarget::Main;
 
         let target = camera.target.get_view(&target);
         guard!(target);
 
         let frame_buffer = self.frame_buffers[&camera_entity].clone();
 
+        if is_main {
+            self.world.insert_resource(ActiveCamera {
+                camera: camera_entity,
+                aspect: frame_buffer.aspect_ratio(),
+            });
+        }
+
         let view = View {
             camera: camera_entity,
             frame_buffer,
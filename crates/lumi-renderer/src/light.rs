@@ -1,19 +1,71 @@
-use lumi_bounds::{CascadeFrustum, Frustum};
+use lumi_bounds::Frustum;
+use lumi_core::Image;
 use lumi_macro::ShaderType;
-use lumi_util::math::{Mat4, Vec3};
+use lumi_util::math::{Mat3, Mat4, Vec3};
 
 use shiv::{bundle::Bundle, world::Component};
 use shiv_transform::{GlobalTransform, Transform};
 
+use crate::{Camera, EnvironmentSettings, IesProfile, RenderLayers};
+
+/// Biases applied when rendering and sampling shadow maps, used to counter shadow acne.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowBias {
+    /// Constant depth bias added when rendering the shadow map, in units of the depth format.
+    pub constant: i32,
+    /// Depth bias added when rendering the shadow map, scaled by the slope of the geometry.
+    pub slope: f32,
+    /// Offset of the shadow receiver along its normal, in shadow map texels.
+    pub normal: f32,
+}
+
+impl Default for ShadowBias {
+    fn default() -> Self {
+        Self {
+            constant: 0,
+            slope: 0.0,
+            normal: 2.0,
+        }
+    }
+}
+
+/// Returns the rotation from world space into the local space of a light emitting along `axis`.
+///
+/// The local z axis is `axis` and the local x axis is `tangent` made perpendicular to it, this is
+/// the space light cookies and IES profiles are sampled in.
+pub fn light_orientation(axis: Vec3, tangent: Vec3) -> Mat3 {
+    let z = axis.normalize_or_zero();
+    let mut x = (tangent - z * tangent.dot(z)).normalize_or_zero();
+
+    if x == Vec3::ZERO {
+        x = z.any_orthonormal_vector();
+    }
+
+    Mat3::from_cols(x, z.cross(x), z).transpose()
+}
+
 #[derive(Clone, Copy, Debug, ShaderType)]
 pub struct RawPointLight {
     pub position: Vec3,
     pub color: Vec3,
     pub intensity: f32,
     pub range: f32,
+    pub shadow_index: i32,
+    pub softness: f32,
+    pub normal_bias: f32,
+    /// The rotation from world space into light space.
+    pub orientation: Mat3,
+    /// The layer of the cookie in the light cookie array, `-1` if the light has no cookie.
+    pub cookie_index: i32,
+    /// The layer of the IES profile in the light profile array, `-1` if the light has no profile.
+    pub profile_index: i32,
+    /// The [`RenderLayers`] of meshes affected by the light.
+    pub render_layers: u32,
+    /// The length of the contact shadow rays, `0.0` if contact shadows are disabled.
+    pub contact_shadow_length: f32,
 }
 
-#[derive(Component, Clone, Copy, Debug)]
+#[derive(Component, Clone, Debug)]
 pub struct PointLight {
     /// The color of the light.
     pub color: Vec3,
@@ -21,6 +73,33 @@ pub struct PointLight {
     pub intensity: f32,
     /// The range of the light in meters.
     pub range: f32,
+    /// Enables shadows.
+    ///
+    /// Point light shadows render the scene six times, once for each face of a cube map.
+    pub shadows: bool,
+    /// The resolution of each face of the shadow map.
+    ///
+    /// This is the largest resolution used, it is lowered when the light is far from the camera
+    /// or the shadow atlas is full.
+    pub shadow_resolution: u32,
+    /// The biases used to counter shadow acne.
+    pub shadow_bias: ShadowBias,
+    /// The near plane of the shadow projection in meters.
+    pub shadow_near: f32,
+    /// The softness of the shadows cast by this light.
+    pub shadow_softness: f32,
+    /// A texture tinting the light, wrapped around the local y axis with an equirectangular
+    /// projection, with the top of the texture along the local -y axis.
+    ///
+    /// Cookies are sampled as 8 bit sRGB color.
+    pub cookie: Option<Image>,
+    /// An IES profile shaping the light, with the nadir along the local -y axis.
+    pub ies_profile: Option<IesProfile>,
+    /// Enables contact shadows, which catch small scale occlusion missed by the shadow map by
+    /// marching through the depth buffer towards the light.
+    pub contact_shadows: bool,
+    /// The distance in meters marched by contact shadows.
+    pub contact_shadow_length: f32,
 }
 
 impl Default for PointLight {
@@ -29,19 +108,78 @@ impl Default for PointLight {
             color: Vec3::ONE,
             intensity: 800.0,
             range: 20.0,
+            shadows: false,
+            shadow_resolution: 512,
+            shadow_bias: ShadowBias::default(),
+            shadow_near: 0.1,
+            shadow_softness: 1.0,
+            cookie: None,
+            ies_profile: None,
+            contact_shadows: false,
+            contact_shadow_length: 0.2,
         }
     }
 }
 
 impl PointLight {
-    pub fn raw(&self, position: Vec3) -> RawPointLight {
+    pub const CUBE_FACES: u32 = 6;
+
+    /// The direction and up vector of each cube map face, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub const CUBE_FACE_DIRECTIONS: [(Vec3, Vec3); 6] = [
+        (Vec3::X, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Y),
+        (Vec3::Y, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::Z),
+        (Vec3::Z, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y),
+    ];
+
+    pub fn view(&self, position: Vec3, face: u32) -> Mat4 {
+        let (direction, up) = Self::CUBE_FACE_DIRECTIONS[face as usize];
+        Mat4::look_at_rh(position, position + direction, up)
+    }
+
+    pub fn proj(&self) -> Mat4 {
+        Mat4::perspective_rh(
+            std::f32::consts::FRAC_PI_2,
+            1.0,
+            self.shadow_near,
+            self.range,
+        )
+    }
+
+    pub fn view_proj(&self, position: Vec3, face: u32) -> Mat4 {
+        self.proj() * self.view(position, face)
+    }
+
+    pub fn frustum(&self, position: Vec3, face: u32) -> Frustum {
+        let view = self.view(position, face);
+        Frustum::from_view_proj(view.inverse(), self.proj(), self.range)
+    }
+
+    pub fn raw(&self, transform: &GlobalTransform, shadow_index: Option<u32>) -> RawPointLight {
         let intensity = self.intensity / (4.0 * std::f32::consts::PI);
 
+        let axis = transform.matrix.mul_vec3(Vec3::NEG_Y);
+        let tangent = transform.matrix.mul_vec3(Vec3::X);
+
         RawPointLight {
-            position,
+            position: transform.translation,
             color: self.color,
             intensity,
             range: self.range,
+            shadow_index: shadow_index.map_or(-1, |index| index as i32),
+            softness: self.shadow_softness,
+            normal_bias: self.shadow_bias.normal,
+            orientation: light_orientation(axis, tangent),
+            cookie_index: -1,
+            profile_index: -1,
+            render_layers: RenderLayers::default().bits(),
+            contact_shadow_length: if self.contact_shadows {
+                self.contact_shadow_length
+            } else {
+                0.0
+            },
         }
     }
 }
@@ -53,25 +191,219 @@ pub struct PointLightBundle {
     pub global_transform: GlobalTransform,
 }
 
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawSpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    pub angle_scale: f32,
+    pub angle_offset: f32,
+    pub shadow_index: i32,
+    pub softness: f32,
+    pub normal_bias: f32,
+    /// The rotation from world space into light space.
+    pub orientation: Mat3,
+    /// Scales the tangent of the angle from the light direction to cookie uv space.
+    pub cookie_scale: f32,
+    /// The layer of the cookie in the light cookie array, `-1` if the light has no cookie.
+    pub cookie_index: i32,
+    /// The layer of the IES profile in the light profile array, `-1` if the light has no profile.
+    pub profile_index: i32,
+    /// The [`RenderLayers`] of meshes affected by the light.
+    pub render_layers: u32,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct SpotLight {
+    /// Direction of the light.
+    pub direction: Vec3,
+    /// The color of the light.
+    pub color: Vec3,
+    /// The intensity of the light in lumens.
+    pub intensity: f32,
+    /// The range of the light in meters.
+    pub range: f32,
+    /// The angle in radians from the center of the cone, where the light starts to fall off.
+    pub inner_angle: f32,
+    /// The angle in radians from the center of the cone, where the light is fully attenuated.
+    pub outer_angle: f32,
+    /// Enables shadows.
+    pub shadows: bool,
+    /// The resolution of the shadow map.
+    ///
+    /// This is the largest resolution used, it is lowered when the light is far from the camera
+    /// or the shadow atlas is full.
+    pub shadow_resolution: u32,
+    /// The biases used to counter shadow acne.
+    pub shadow_bias: ShadowBias,
+    /// The near plane of the shadow projection in meters.
+    pub shadow_near: f32,
+    /// The softness of the shadows cast by this light.
+    pub shadow_softness: f32,
+    /// A texture projected by the light, covering the outer cone, with the top of the texture
+    /// towards the local y axis.
+    ///
+    /// Cookies are sampled as 8 bit sRGB color.
+    pub cookie: Option<Image>,
+    /// An IES profile shaping the light, with the nadir along the direction of the light.
+    pub ies_profile: Option<IesProfile>,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            color: Vec3::ONE,
+            intensity: 800.0,
+            range: 20.0,
+            inner_angle: std::f32::consts::FRAC_PI_8,
+            outer_angle: std::f32::consts::FRAC_PI_4,
+            shadows: true,
+            shadow_resolution: 1024,
+            shadow_bias: ShadowBias::default(),
+            shadow_near: 0.1,
+            shadow_softness: 1.0,
+            cookie: None,
+            ies_profile: None,
+        }
+    }
+}
+
+impl SpotLight {
+    pub fn view(&self, position: Vec3) -> Mat4 {
+        let direction = self.direction.normalize_or_zero();
+
+        if direction.y.abs() > 0.999 {
+            Mat4::look_at_rh(position, position + direction, Vec3::X)
+        } else {
+            Mat4::look_at_rh(position, position + direction, Vec3::Y)
+        }
+    }
+
+    pub fn proj(&self) -> Mat4 {
+        let fov = f32::min(self.outer_angle * 2.0, std::f32::consts::PI - 0.01);
+        Mat4::perspective_rh(fov, 1.0, self.shadow_near, self.range)
+    }
+
+    pub fn view_proj(&self, position: Vec3) -> Mat4 {
+        self.proj() * self.view(position)
+    }
+
+    pub fn frustum(&self, position: Vec3) -> Frustum {
+        Frustum::from_view_proj(self.view(position).inverse(), self.proj(), self.range)
+    }
+
+    /// Returns the scale and offset used to compute the angular attenuation of the light.
+    pub fn angle_scale_offset(&self) -> (f32, f32) {
+        let outer = self.outer_angle.clamp(0.0, std::f32::consts::FRAC_PI_2);
+        let inner = self.inner_angle.clamp(0.0, outer);
+
+        let cos_outer = outer.cos();
+        let scale = 1.0 / f32::max(inner.cos() - cos_outer, 0.0001);
+        let offset = -cos_outer * scale;
+
+        (scale, offset)
+    }
+
+    /// Returns the scale from the tangent of the angle from the light direction to cookie uv
+    /// space, such that the cookie covers the outer cone.
+    pub fn cookie_scale(&self) -> f32 {
+        let outer = self
+            .outer_angle
+            .clamp(0.001, std::f32::consts::FRAC_PI_2 - 0.001);
+        1.0 / outer.tan()
+    }
+
+    /// Returns the raw light, with the direction of the light transformed by `transform`.
+    pub fn raw(&self, transform: &GlobalTransform, shadow_index: Option<u32>) -> RawSpotLight {
+        let intensity = self.intensity / std::f32::consts::PI;
+        let (angle_scale, angle_offset) = self.angle_scale_offset();
+
+        let direction = transform
+            .matrix
+            .mul_vec3(self.direction)
+            .normalize_or_zero();
+        let tangent = transform.matrix.mul_vec3(Vec3::X);
+
+        RawSpotLight {
+            position: transform.translation,
+            direction,
+            color: self.color,
+            intensity,
+            range: self.range,
+            angle_scale,
+            angle_offset,
+            shadow_index: shadow_index.map_or(-1, |index| index as i32),
+            softness: self.shadow_softness,
+            normal_bias: self.shadow_bias.normal,
+            orientation: light_orientation(direction, tangent),
+            cookie_scale: self.cookie_scale(),
+            cookie_index: -1,
+            profile_index: -1,
+            render_layers: RenderLayers::default().bits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct SpotLightBundle {
+    pub light: SpotLight,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
 #[derive(Clone, Copy, Debug, Default, ShaderType)]
 pub struct RawDirectionalLight {
     pub direction: Vec3,
     pub color: Vec3,
     pub intensity: f32,
-    pub size: f32,
     pub depth: f32,
     pub softness: f32,
     pub falloff: f32,
+    pub normal_bias: f32,
     pub cascade: u32,
-    pub view_proj: Mat4,
+    pub cascade_count: u32,
+    /// The [`RenderLayers`] of meshes affected by the light.
+    pub render_layers: u32,
+    /// The length of the contact shadow rays, `0.0` if contact shadows are disabled.
+    pub contact_shadow_length: f32,
+}
+
+/// A single cascade of a [`DirectionalLight`] shadow map, fitted to a slice of the camera frustum.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DirectionalCascade {
+    pub view: Mat4,
+    pub proj: Mat4,
+    /// The width of the cascade projection in meters.
+    pub size: f32,
+    pub far: f32,
+}
+
+impl DirectionalCascade {
+    pub fn view_proj(&self) -> Mat4 {
+        self.proj * self.view
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.view.inverse(), self.proj, self.far)
+    }
+
+    /// Snaps the cascade to the texel grid of a shadow map with `resolution`, so that it stays
+    /// stable while the camera moves and rotates.
+    pub fn snap(&mut self, resolution: u32) {
+        let texel_size = self.size / resolution as f32;
+
+        // the view translates the center of the cascade in light space to the origin
+        let center = -self.view.w_axis;
+        self.view.w_axis.x = -(center.x / texel_size).round() * texel_size;
+        self.view.w_axis.y = -(center.y / texel_size).round() * texel_size;
+    }
 }
 
 #[derive(Component, Clone, Copy, Debug)]
 pub struct DirectionalLight {
-    /// Translation of the shadow map projection.
-    ///
-    /// This should usually be the position of the main camera.
-    pub translation: Vec3,
     /// Direction of the light.
     pub direction: Vec3,
     /// Color of the light.
@@ -80,97 +412,155 @@ pub struct DirectionalLight {
     pub illuminance: f32,
     /// Enables shadows.
     pub shadows: bool,
-    /// Size of the shadow projection in meters.
-    pub size: f32,
+    /// The resolution of each shadow cascade.
+    pub shadow_resolution: u32,
+    /// The biases used to counter shadow acne.
+    pub shadow_bias: ShadowBias,
+    /// The number of shadow cascades.
+    pub cascades: u32,
+    /// The distance from the camera in meters covered by the shadow cascades.
+    pub shadow_distance: f32,
+    /// Blends the cascade split distances between uniform at `0.0` and logarithmic at `1.0`.
+    pub cascade_split_lambda: f32,
     /// The depth of the light frustum in meters.
     pub depth: f32,
     /// The softness of the shadows cast by this light.
     pub shadow_softness: f32,
     /// The falloff of the shadows cast by this light.
     pub shadow_falloff: f32,
+    /// Enables contact shadows, which catch small scale occlusion missed by the shadow cascades
+    /// by marching through the depth buffer towards the light.
+    pub contact_shadows: bool,
+    /// The distance in meters marched by contact shadows.
+    pub contact_shadow_length: f32,
 }
 
 impl Default for DirectionalLight {
     fn default() -> Self {
         Self {
-            translation: Vec3::ZERO,
             direction: Vec3::new(0.0, -1.0, 0.0),
             color: Vec3::ONE,
             illuminance: 100_000.0,
             shadows: true,
-            size: 200.0,
+            shadow_resolution: 2048,
+            shadow_bias: ShadowBias::default(),
+            cascades: 4,
+            shadow_distance: 100.0,
+            cascade_split_lambda: 0.8,
             depth: 1000.0,
             shadow_softness: 2.0,
             shadow_falloff: 2.0,
+            contact_shadows: false,
+            contact_shadow_length: 0.2,
         }
     }
 }
 
 impl DirectionalLight {
-    pub const CASCADES: u32 = 4;
-
-    pub fn view(&self) -> Mat4 {
-        let translation = Mat4::from_translation(self.translation);
-        let view = if self.direction.y.abs() > 0.999 {
-            Mat4::look_at_rh(-self.direction, Vec3::ZERO, Vec3::X)
+    /// Returns the rotation from world space into light space.
+    pub fn rotation(&self) -> Mat4 {
+        if self.direction.y.abs() > 0.999 {
+            Mat4::look_at_rh(Vec3::ZERO, self.direction, Vec3::X)
         } else {
-            Mat4::look_at_rh(-self.direction, Vec3::ZERO, Vec3::Y)
-        };
-
-        translation * view
+            Mat4::look_at_rh(Vec3::ZERO, self.direction, Vec3::Y)
+        }
     }
 
-    pub fn cascade_size(&self, cascade: u32) -> f32 {
-        let size = 2.0_f32.powi(cascade as i32);
-        let max = 2.0_f32.powi(4);
-        self.size * size / max
+    /// Returns the `cascades + 1` view space depths separating the cascades, between `near` and
+    /// `far`.
+    pub fn cascade_splits(&self, near: f32, far: f32) -> Vec<f32> {
+        let count = u32::max(self.cascades, 1);
+
+        (0..=count)
+            .map(|i| {
+                let p = i as f32 / count as f32;
+                let uniform = near + (far - near) * p;
+                let log = near * (far / near).powf(p);
+
+                uniform + (log - uniform) * self.cascade_split_lambda
+            })
+            .collect()
     }
 
-    pub fn proj(&self, cascade: u32) -> Mat4 {
-        let size = self.cascade_size(cascade);
+    /// Fits a cascade around `corners` of a camera frustum slice.
+    ///
+    /// The cascade is fitted to a bounding sphere and snapped to the texel grid of a shadow map
+    /// with `resolution`, so that it stays stable while the camera moves and rotates.
+    pub fn fit_cascade(&self, corners: &[Vec3; 8], resolution: u32) -> DirectionalCascade {
+        let center = corners.iter().fold(Vec3::ZERO, |sum, &corner| sum + corner) / 8.0;
 
-        let min = -size / 2.0;
-        let max = size / 2.0;
+        let radius = corners
+            .iter()
+            .map(|corner| corner.distance(center))
+            .fold(0.0, f32::max);
 
-        let near = -self.depth / 2.0;
-        let far = self.depth / 2.0;
+        // round the radius up, so that the size only changes in discrete steps
+        let radius = (radius * 16.0).ceil() / 16.0;
+        let size = radius * 2.0;
 
-        Mat4::orthographic_rh(min, max, min, max, near, far)
-    }
+        let rotation = self.rotation();
+        let light_center = rotation.transform_point3(center);
+        let view = Mat4::from_translation(-light_center) * rotation;
 
-    pub fn cascade_frustum(&self, cascade: u32) -> CascadeFrustum {
-        let view = self.view();
+        let near = -self.depth / 2.0;
         let far = self.depth / 2.0;
+        let proj = Mat4::orthographic_rh(-radius, radius, -radius, radius, near, far);
 
-        let outer = Frustum::from_view_proj(view.inverse(), self.proj(cascade), far);
-        let inner = if cascade > 0 {
-            Some(Frustum::from_view_proj(
-                view.inverse(),
-                self.proj(cascade - 1),
-                far,
-            ))
-        } else {
-            None
+        let mut cascade = DirectionalCascade {
+            view,
+            proj,
+            size,
+            far,
         };
 
-        CascadeFrustum { outer, inner }
+        cascade.snap(resolution);
+        cascade
     }
 
-    pub fn view_proj(&self, cascade: u32) -> Mat4 {
-        self.proj(cascade) * self.view()
+    /// Computes the cascades covering the frustum of `camera`.
+    ///
+    /// `view` is the camera transform and `aspect` the aspect ratio it renders with.
+    pub fn cascades(
+        &self,
+        camera: &Camera,
+        view: Mat4,
+        aspect: f32,
+        resolution: u32,
+    ) -> Vec<DirectionalCascade> {
+        let near = f32::max(camera.near(), 0.01);
+        let far = match camera.far() {
+            Some(far) => f32::min(far, self.shadow_distance),
+            None => self.shadow_distance,
+        };
+
+        let splits = self.cascade_splits(near, f32::max(far, near));
+
+        splits
+            .windows(2)
+            .map(|split| {
+                let corners = camera.frustum_corners(view, aspect, split[0], split[1]);
+                self.fit_cascade(&corners, resolution)
+            })
+            .collect()
     }
 
-    pub fn raw(&self, cascade: u32) -> RawDirectionalLight {
+    pub fn raw(&self, cascade: u32, cascade_count: u32) -> RawDirectionalLight {
         RawDirectionalLight {
             direction: self.direction.normalize(),
             color: self.color,
             intensity: self.illuminance,
-            size: self.size,
             depth: self.depth,
             softness: self.shadow_softness,
             falloff: self.shadow_falloff,
+            normal_bias: self.shadow_bias.normal,
             cascade,
-            view_proj: self.view_proj(0),
+            cascade_count,
+            render_layers: RenderLayers::default().bits(),
+            contact_shadow_length: if self.contact_shadows {
+                self.contact_shadow_length
+            } else {
+                0.0
+            },
         }
     }
 }
@@ -187,18 +577,25 @@ pub struct RawAmbientLight {
     pub color: Vec3,
 }
 
+#[allow(deprecated)]
 impl Default for RawAmbientLight {
     fn default() -> Self {
         AmbientLight::default().raw()
     }
 }
 
+/// Scales the [`Environment`](crate::Environment).
+///
+/// When no [`EnvironmentSettings`] resource is present, `color` and `intensity` are used as its
+/// `tint` and `intensity`.
+#[deprecated(note = "use `EnvironmentSettings` instead")]
 #[derive(Clone, Copy, Debug)]
 pub struct AmbientLight {
     pub color: Vec3,
     pub intensity: f32,
 }
 
+#[allow(deprecated)]
 impl Default for AmbientLight {
     fn default() -> Self {
         Self {
@@ -208,6 +605,7 @@ impl Default for AmbientLight {
     }
 }
 
+#[allow(deprecated)]
 impl AmbientLight {
     pub fn raw(&self) -> RawAmbientLight {
         RawAmbientLight {
@@ -215,3 +613,132 @@ impl AmbientLight {
         }
     }
 }
+
+#[allow(deprecated)]
+impl From<AmbientLight> for EnvironmentSettings {
+    fn from(ambient_light: AmbientLight) -> Self {
+        Self {
+            intensity: ambient_light.intensity,
+            tint: ambient_light.color,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_cascade_splits() {
+        let mut light = DirectionalLight {
+            cascades: 4,
+            cascade_split_lambda: 0.0,
+            ..Default::default()
+        };
+
+        // uniform splits
+        let splits = light.cascade_splits(1.0, 101.0);
+        assert_eq!(splits.len(), 5);
+        for (split, expected) in splits.iter().zip([1.0, 26.0, 51.0, 76.0, 101.0]) {
+            assert_approx(*split, expected);
+        }
+
+        // logarithmic splits
+        light.cascade_split_lambda = 1.0;
+        let splits = light.cascade_splits(1.0, 10_000.0);
+        for (split, expected) in splits.iter().zip([1.0, 10.0, 100.0, 1000.0, 10_000.0]) {
+            assert_approx(*split, expected);
+        }
+
+        light.cascade_split_lambda = 0.5;
+        let splits = light.cascade_splits(0.1, 100.0);
+        assert_approx(splits[0], 0.1);
+        assert_approx(splits[4], 100.0);
+        assert!(splits.windows(2).all(|split| split[0] < split[1]));
+
+        // at least one cascade
+        light.cascades = 0;
+        assert_eq!(light.cascade_splits(0.1, 100.0).len(), 2);
+    }
+
+    #[test]
+    fn test_fit_cascade() {
+        let light = DirectionalLight {
+            direction: Vec3::new(1.0, -2.0, 0.5).normalize(),
+            ..Default::default()
+        };
+
+        let camera = Camera::default();
+        let view = Mat4::from_rotation_y(0.3) * Mat4::from_translation(Vec3::new(3.0, 2.0, 1.0));
+        let corners = camera.frustum_corners(view, 16.0 / 9.0, 1.0, 20.0);
+
+        let cascade = light.fit_cascade(&corners, 1024);
+
+        // the slice of the camera frustum is covered by the cascade
+        for corner in corners {
+            let ndc = cascade.view_proj().project_point3(corner);
+
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{:?}", ndc);
+            assert!(ndc.z >= 0.0 && ndc.z <= 1.0, "{:?}", ndc);
+        }
+
+        // the cascade is snapped to texels
+        let texel_size = cascade.size / 1024.0;
+        for offset in [cascade.view.w_axis.x, cascade.view.w_axis.y] {
+            let texels = offset / texel_size;
+            assert_approx(texels, texels.round());
+        }
+
+        // moving the camera doesn't change the size
+        let offset = Vec3::new(0.37, 1.1, -4.2);
+        let moved = light.fit_cascade(&corners.map(|corner| corner + offset), 1024);
+        assert_eq!(moved.size, cascade.size);
+    }
+
+    #[test]
+    fn test_snap_cascade() {
+        let light = DirectionalLight::default();
+
+        let camera = Camera::default();
+        let corners = camera.frustum_corners(Mat4::IDENTITY, 1.0, 0.7, 13.0);
+
+        // a cascade allocated a smaller tile than requested is snapped to the smaller texels
+        let mut cascade = light.fit_cascade(&corners, 2048);
+        cascade.snap(512);
+
+        let texel_size = cascade.size / 512.0;
+        for offset in [cascade.view.w_axis.x, cascade.view.w_axis.y] {
+            let texels = offset / texel_size;
+            assert_approx(texels, texels.round());
+        }
+
+        // snapping is stable
+        let snapped = cascade;
+        cascade.snap(512);
+        assert_eq!(cascade.view, snapped.view);
+    }
+
+    #[test]
+    fn test_cascades() {
+        let light = DirectionalLight {
+            shadow_distance: 50.0,
+            ..Default::default()
+        };
+
+        let camera = Camera::default();
+        let cascades = light.cascades(&camera, Mat4::IDENTITY, 1.0, 1024);
+        assert_eq!(cascades.len(), light.cascades as usize);
+
+        // the cascades grow with the distance from the camera
+        assert!(cascades.windows(2).all(|c| c[0].size < c[1].size));
+
+        let corners = camera.frustum_corners(Mat4::IDENTITY, 1.0, 1.0, 50.0);
+        assert_approx(corners[0].z, -1.0);
+        assert_approx(corners[4].z, -50.0);
+    }
+}
@@ -0,0 +1,52 @@
+use lumi_bake::LightmapData;
+use lumi_core::Image;
+use lumi_macro::ShaderType;
+use lumi_util::math::Vec2;
+use shiv::world::Component;
+
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct RawLightmap {
+    pub scale: Vec2,
+    pub offset: Vec2,
+    pub intensity: f32,
+}
+
+/// A baked lightmap replacing the diffuse indirect light of a mesh, usually baked with
+/// [`LightmapBaker`](lumi_bake::LightmapBaker).
+///
+/// The lightmap is sampled with the [`Mesh::UV_1`](lumi_mesh::Mesh::UV_1) attribute, which the
+/// mesh must have.
+#[derive(Component, Clone, Debug)]
+pub struct Lightmap {
+    /// The irradiance divided by pi, in the units of the lights.
+    pub image: Image,
+    /// The scale applied to the uvs, for meshes sharing a lightmap atlas.
+    pub scale: Vec2,
+    /// The offset added to the uvs after [`Lightmap::scale`].
+    pub offset: Vec2,
+    pub intensity: f32,
+}
+
+impl Lightmap {
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            scale: Vec2::ONE,
+            offset: Vec2::ZERO,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn from_data(data: &LightmapData) -> Self {
+        Self::new(Image::new(data.image_data()))
+    }
+
+    #[inline]
+    pub fn raw(&self) -> RawLightmap {
+        RawLightmap {
+            scale: self.scale,
+            offset: self.offset,
+            intensity: self.intensity,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use lumi_bind::Bind;
+use lumi_core::{Image, ImageData, TextureFormat};
+
+/// Lookup tables for shading area lights with linearly transformed cosines, fitted to the GGX
+/// distribution and indexed by perceptual roughness and `sqrt(1 - n.v)`.
+///
+/// The tables are generated by the `ltc` binary of lumi-bake.
+#[derive(Clone, Debug, Bind)]
+pub struct LtcTables {
+    /// The four non-trivial elements of the inverse transformation matrix in the top half, and
+    /// the magnitude and fresnel of the fitted distribution in the bottom half.
+    #[texture(name = "ltc_table")]
+    #[sampler(name = "ltc_sampler")]
+    pub table: Image,
+}
+
+impl LtcTables {
+    pub const SIZE: u32 = 64;
+}
+
+impl Default for LtcTables {
+    #[inline]
+    fn default() -> Self {
+        // stacked into one texture to save a texture binding in the material shaders
+        let data = [include_bytes!("ltc_1").as_slice(), include_bytes!("ltc_2")].concat();
+        let table =
+            ImageData::with_format(Self::SIZE, Self::SIZE * 2, data, TextureFormat::Rgba16Float);
+
+        Self {
+            table: Image::new(table),
+        }
+    }
+}
@@ -4,10 +4,13 @@ use lumi_shader::{FileShaderIo, ShaderProcessor};
 use shiv::schedule::{DefaultStage, IntoSystemDescriptor, StageLabel, SystemLabel, SystemStage};
 
 use crate::{
-    clear_draws_system, draw_system, extract_bloom_settings_system, prepare_camera_system,
-    render_bloom_system, render_opaque_system, render_transparent_system,
-    screen_space_render_system, screen_space_resize_system, sky_render_system, tone_mapping_system,
-    DrawKeys, Extracted, IntegratedBrdf, OpaqueDraws, Renderer, TransparentDraws,
+    clear_draws_system, draw_system, extract_ambient_occlusion_settings_system,
+    extract_bloom_settings_system, extract_fog_settings_system,
+    extract_global_illumination_settings_system, extract_screen_space_reflection_settings_system,
+    prepare_camera_system, prepare_clusters_system, render_bloom_system, render_fog_system,
+    render_opaque_system, render_transparent_system, screen_space_render_system,
+    screen_space_resize_system, sky_render_system, tone_mapping_system, DrawKeys, Extracted,
+    IntegratedBrdf, LtcTables, OpaqueDraws, Renderer, TransparentDraws,
 };
 
 pub trait RendererPlugin {
@@ -62,11 +65,13 @@ pub enum ViewSystem {
     ClearDraw,
     Draw,
     PrepareCamera,
+    PrepareClusters,
     ScreenSpaceRender,
     ScreenSpaceResize,
     RenderSky,
     RenderOpaque,
     RenderTransparent,
+    RenderFog,
     RenderBloom,
     ToneMapping,
 }
@@ -85,6 +90,7 @@ impl RendererPlugin for CorePlugin {
         renderer.world.init_resource::<TransparentDraws>();
         renderer.world.init_resource::<DrawKeys>();
         renderer.world.init_resource::<IntegratedBrdf>();
+        renderer.world.init_resource::<LtcTables>();
 
         renderer
             .extract
@@ -111,7 +117,20 @@ impl RendererPlugin for CorePlugin {
         renderer
             .extract
             .add_system_to_stage(DefaultStage::First, Extracted::spawn_system)
-            .add_system_to_stage(ExtractStage::Extract, extract_bloom_settings_system);
+            .add_system_to_stage(ExtractStage::Extract, extract_bloom_settings_system)
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_ambient_occlusion_settings_system,
+            )
+            .add_system_to_stage(ExtractStage::Extract, extract_fog_settings_system)
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_global_illumination_settings_system,
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_screen_space_reflection_settings_system,
+            );
 
         renderer
             .view
@@ -123,6 +142,10 @@ impl RendererPlugin for CorePlugin {
                 ViewStage::PrePrepare,
                 prepare_camera_system.label(ViewSystem::PrepareCamera),
             )
+            .add_system_to_stage(
+                ViewStage::PrePrepare,
+                prepare_clusters_system.label(ViewSystem::PrepareClusters),
+            )
             .add_system_to_stage(
                 ViewStage::PrePrepare,
                 screen_space_resize_system.label(ViewSystem::ScreenSpaceResize),
@@ -144,6 +167,12 @@ impl RendererPlugin for CorePlugin {
                 ViewStage::RenderTransparent,
                 render_transparent_system.label(ViewSystem::RenderTransparent),
             )
+            .add_system_to_stage(
+                ViewStage::PostRender,
+                render_fog_system
+                    .label(ViewSystem::RenderFog)
+                    .before(ViewSystem::RenderBloom),
+            )
             .add_system_to_stage(
                 ViewStage::PostRender,
                 render_bloom_system.label(ViewSystem::RenderBloom),
@@ -0,0 +1,249 @@
+use lumi_util::math::Vec4;
+
+/// A square region of a [`ShadowAtlas`] in texels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShadowTile {
+    pub x: u32,
+    pub y: u32,
+    pub size: u32,
+}
+
+impl ShadowTile {
+    /// Returns the offset and size of the tile in uv space of an atlas with `atlas_size`.
+    #[inline]
+    pub fn uv_rect(&self, atlas_size: u32) -> Vec4 {
+        Vec4::new(
+            self.x as f32,
+            self.y as f32,
+            self.size as f32,
+            self.size as f32,
+        ) / atlas_size as f32
+    }
+}
+
+/// Packs square shadow map tiles into a single texture.
+///
+/// Tiles are power of two sized and allocated by recursively splitting free tiles into four.
+/// Allocating the largest tiles first packs the atlas without gaps.
+#[derive(Clone, Debug, Default)]
+pub struct ShadowAtlas {
+    size: u32,
+    free: Vec<ShadowTile>,
+}
+
+impl ShadowAtlas {
+    pub const MIN_TILE_SIZE: u32 = 32;
+
+    pub fn new(size: u32) -> Self {
+        let size = u32::max(size, Self::MIN_TILE_SIZE);
+
+        let mut atlas = Self {
+            size: size.next_power_of_two(),
+            free: Vec::new(),
+        };
+
+        atlas.clear();
+        atlas
+    }
+
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Frees all tiles.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.free.clear();
+        self.free.push(ShadowTile {
+            x: 0,
+            y: 0,
+            size: self.size,
+        });
+    }
+
+    /// Returns the size of the tile [`Self::allocate`] tries first for a request of `size`.
+    #[inline]
+    pub fn tile_size(&self, size: u32) -> u32 {
+        let size = u32::clamp(size, Self::MIN_TILE_SIZE, self.size);
+        1 << (u32::BITS - 1 - size.leading_zeros())
+    }
+
+    /// Halves all `sizes` together until their tiles fit into the atlas, or all of them are at
+    /// [`Self::MIN_TILE_SIZE`].
+    ///
+    /// Scaling every request by the same factor keeps the relative resolution of all shadows,
+    /// instead of starving the requests allocated last.
+    pub fn fit(&self, sizes: &mut [u32]) {
+        let atlas_area = self.size as u64 * self.size as u64;
+
+        loop {
+            let area = sizes
+                .iter()
+                .map(|&size| self.tile_size(size) as u64)
+                .map(|size| size * size)
+                .sum::<u64>();
+
+            if area <= atlas_area || sizes.iter().all(|&size| size <= Self::MIN_TILE_SIZE) {
+                break;
+            }
+
+            for size in sizes.iter_mut() {
+                *size = u32::max(*size / 2, Self::MIN_TILE_SIZE);
+            }
+        }
+    }
+
+    /// Allocates a tile of `size` rounded down to a power of two.
+    ///
+    /// If no such tile is free, the size is halved until one is found, returns `None` if no tile
+    /// of at least [`Self::MIN_TILE_SIZE`] is free.
+    pub fn allocate(&mut self, size: u32) -> Option<ShadowTile> {
+        let mut size = self.tile_size(size);
+
+        while size >= Self::MIN_TILE_SIZE {
+            if let Some(tile) = self.allocate_exact(size) {
+                return Some(tile);
+            }
+
+            size /= 2;
+        }
+
+        None
+    }
+
+    fn allocate_exact(&mut self, size: u32) -> Option<ShadowTile> {
+        let (index, _) = self
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.size >= size)
+            .min_by_key(|(_, tile)| tile.size)?;
+
+        let mut tile = self.free.swap_remove(index);
+
+        while tile.size > size {
+            let half = tile.size / 2;
+
+            self.free.push(ShadowTile {
+                x: tile.x + half,
+                y: tile.y,
+                size: half,
+            });
+            self.free.push(ShadowTile {
+                x: tile.x,
+                y: tile.y + half,
+                size: half,
+            });
+            self.free.push(ShadowTile {
+                x: tile.x + half,
+                y: tile.y + half,
+                size: half,
+            });
+
+            tile.size = half;
+        }
+
+        Some(tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &ShadowTile, b: &ShadowTile) -> bool {
+        a.x < b.x + b.size && b.x < a.x + a.size && a.y < b.y + b.size && b.y < a.y + a.size
+    }
+
+    #[test]
+    fn test_allocate_size() {
+        let mut atlas = ShadowAtlas::new(1000);
+        assert_eq!(atlas.size(), 1024);
+
+        assert_eq!(atlas.allocate(300).unwrap().size, 256);
+        assert_eq!(atlas.allocate(1).unwrap().size, ShadowAtlas::MIN_TILE_SIZE);
+        assert_eq!(atlas.allocate(u32::MAX).unwrap().size, 512);
+    }
+
+    #[test]
+    fn test_allocate_packs() {
+        let mut atlas = ShadowAtlas::new(1024);
+
+        let mut tiles = Vec::new();
+        for size in [512, 512, 256, 256, 256, 256] {
+            let tile = atlas.allocate(size).unwrap();
+            assert_eq!(tile.size, size);
+            tiles.push(tile);
+        }
+
+        // the request is lowered to the last free tile
+        let tile = atlas.allocate(1024).unwrap();
+        assert_eq!(tile.size, 512);
+        tiles.push(tile);
+
+        assert_eq!(atlas.allocate(32), None);
+
+        for (i, a) in tiles.iter().enumerate() {
+            assert!(a.x + a.size <= atlas.size() && a.y + a.size <= atlas.size());
+
+            for b in &tiles[i + 1..] {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_atlas() {
+        let mut atlas = ShadowAtlas::new(0);
+        assert_eq!(atlas.size(), ShadowAtlas::MIN_TILE_SIZE);
+
+        assert_eq!(
+            atlas.allocate(1024).unwrap().size,
+            ShadowAtlas::MIN_TILE_SIZE
+        );
+        assert_eq!(atlas.allocate(1024), None);
+    }
+
+    #[test]
+    fn test_fit() {
+        let atlas = ShadowAtlas::new(4096);
+
+        // four 2048 cascades fill the atlas, so everything is halved to make room for the spot
+        let mut sizes = [2048, 2048, 2048, 2048, 1024];
+        atlas.fit(&mut sizes);
+        assert_eq!(sizes, [1024, 1024, 1024, 1024, 512]);
+
+        // requests that already fit are left alone
+        let mut sizes = [2048, 1024, 1000];
+        atlas.fit(&mut sizes);
+        assert_eq!(sizes, [2048, 1024, 1000]);
+
+        // sizes stop at the minimum tile size
+        let mut sizes = [64; 20_000];
+        atlas.fit(&mut sizes);
+        assert!(sizes.iter().all(|&size| size == ShadowAtlas::MIN_TILE_SIZE));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut atlas = ShadowAtlas::new(1024);
+
+        assert_eq!(atlas.allocate(1024).unwrap().size, 1024);
+        assert_eq!(atlas.allocate(1024), None);
+
+        atlas.clear();
+        assert_eq!(atlas.allocate(1024).unwrap().size, 1024);
+    }
+
+    #[test]
+    fn test_uv_rect() {
+        let tile = ShadowTile {
+            x: 512,
+            y: 256,
+            size: 256,
+        };
+
+        assert_eq!(tile.uv_rect(1024), Vec4::new(0.5, 0.25, 0.25, 0.25));
+    }
+}
@@ -0,0 +1,436 @@
+use lumi_bind::Bind;
+use lumi_core::{StorageBuffer, UniformBuffer};
+use lumi_macro::ShaderType;
+use lumi_util::math::{Mat4, UVec3, Vec2, Vec3, Vec4Swizzles};
+use shiv::{
+    query::Query,
+    system::{Commands, Res},
+    world::{Component, Entity},
+};
+
+use crate::{
+    Camera, PreparedLights, PreparedTransform, RawCamera, RenderDevice, RenderQueue, View,
+};
+
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct RawClusters {
+    pub dimensions: UVec3,
+    pub z_scale: f32,
+    pub z_bias: f32,
+}
+
+/// The lights affecting a single cluster, stored as `point_count` point light indices followed by
+/// `spot_count` spot light indices, starting at `offset` in the cluster light indices.
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct RawCluster {
+    pub offset: u32,
+    pub point_count: u32,
+    pub spot_count: u32,
+}
+
+/// Point and spot lights assigned to the froxels of a camera.
+///
+/// The view frustum is split into [`PreparedClusters::DIMENSIONS`] clusters, tiled in screen space
+/// and sliced exponentially in depth, so fragments only have to iterate over nearby lights.
+#[derive(Component, Debug, Bind)]
+pub struct PreparedClusters {
+    #[uniform]
+    pub clusters: UniformBuffer<RawClusters>,
+    #[storage_buffer]
+    pub cluster_lights: StorageBuffer<Vec<RawCluster>>,
+    #[storage_buffer]
+    pub cluster_light_indices: StorageBuffer<Vec<u32>>,
+    /// The length of the index buffer, grown in powers of two.
+    index_capacity: usize,
+    pub bindings_changed: bool,
+}
+
+impl Default for PreparedClusters {
+    fn default() -> Self {
+        let cluster_count = Self::cluster_count();
+
+        Self {
+            clusters: UniformBuffer::default(),
+            cluster_lights: StorageBuffer::new(vec![RawCluster::default(); cluster_count]),
+            cluster_light_indices: StorageBuffer::new(vec![0; Self::MIN_INDEX_CAPACITY]),
+            index_capacity: Self::MIN_INDEX_CAPACITY,
+            bindings_changed: true,
+        }
+    }
+}
+
+/// The view space bounding sphere of a light.
+#[derive(Clone, Copy, Debug)]
+struct ClusterLight {
+    index: u32,
+    center: Vec3,
+    range: f32,
+}
+
+impl PreparedClusters {
+    /// The number of clusters along the x, y and z axes.
+    pub const DIMENSIONS: UVec3 = UVec3::new(16, 9, 24);
+    pub const MIN_INDEX_CAPACITY: usize = 1024;
+
+    #[inline]
+    pub const fn cluster_count() -> usize {
+        (Self::DIMENSIONS.x * Self::DIMENSIONS.y * Self::DIMENSIONS.z) as usize
+    }
+
+    /// Returns the z slice containing the view space `depth`.
+    #[inline]
+    fn slice(raw: &RawClusters, depth: f32) -> u32 {
+        let slice = f32::ln(depth) * raw.z_scale + raw.z_bias;
+        f32::clamp(slice, 0.0, (Self::DIMENSIONS.z - 1) as f32) as u32
+    }
+
+    /// Returns the range of screen space tiles covered by `light`.
+    fn tiles(light: &ClusterLight, proj: Mat4, near: f32) -> Option<(UVec3, UVec3)> {
+        let min_depth = f32::max(-light.center.z - light.range, near);
+        let max_depth = -light.center.z + light.range;
+
+        // project the corners of the view space bounding box of the light, clipped to `near`
+        let mut min = Vec2::splat(f32::INFINITY);
+        let mut max = Vec2::splat(f32::NEG_INFINITY);
+
+        for i in 0..8 {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let depth = if i & 4 == 0 { min_depth } else { max_depth };
+
+            let corner = Vec3::new(
+                light.center.x + x * light.range,
+                light.center.y + y * light.range,
+                -depth,
+            );
+
+            let clip = proj * corner.extend(1.0);
+            let ndc = clip.xy() / clip.w;
+
+            min = min.min(ndc);
+            max = max.max(ndc);
+        }
+
+        if min.x > 1.0 || min.y > 1.0 || max.x < -1.0 || max.y < -1.0 {
+            return None;
+        }
+
+        let dimensions = Self::DIMENSIONS.truncate().as_vec2();
+        let to_tile = |ndc: Vec2| {
+            let tile = (ndc * 0.5 + 0.5) * dimensions;
+            tile.clamp(Vec2::ZERO, dimensions - 1.0).as_uvec2()
+        };
+
+        Some((to_tile(min).extend(0), to_tile(max).extend(0)))
+    }
+
+    /// Assigns point and spot lights to clusters of `camera`.
+    pub fn assign_lights(&mut self, camera: &RawCamera, near: f32, lights: &PreparedLights) {
+        let world_to_view = camera.inverse_view;
+        let proj = camera.view_proj * camera.view;
+
+        let view_space = |index: usize, position: Vec3, range: f32| ClusterLight {
+            index: index as u32,
+            center: world_to_view.transform_point3(position),
+            range,
+        };
+
+        // lights entirely behind the near plane can't affect any cluster
+        let in_front = |light: &ClusterLight| -light.center.z + light.range > near;
+
+        let point_lights = lights
+            .point_lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| view_space(i, light.position, light.range))
+            .filter(in_front)
+            .collect::<Vec<_>>();
+
+        let spot_lights = lights
+            .spot_lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| view_space(i, light.position, light.range))
+            .filter(in_front)
+            .collect::<Vec<_>>();
+
+        // fit the depth slices to the furthest light
+        let far = point_lights
+            .iter()
+            .chain(spot_lights.iter())
+            .map(|light| -light.center.z + light.range)
+            .fold(near * 2.0, f32::max);
+
+        let z_scale = Self::DIMENSIONS.z as f32 / f32::ln(far / near);
+        let raw = RawClusters {
+            dimensions: Self::DIMENSIONS,
+            z_scale,
+            z_bias: -f32::ln(near) * z_scale,
+        };
+
+        let cluster_count = Self::cluster_count();
+        let mut point_counts = vec![0u32; cluster_count];
+        let mut spot_counts = vec![0u32; cluster_count];
+
+        let mut point_ranges = Vec::with_capacity(point_lights.len());
+        let mut spot_ranges = Vec::with_capacity(spot_lights.len());
+
+        for (lights, counts, ranges) in [
+            (&point_lights, &mut point_counts, &mut point_ranges),
+            (&spot_lights, &mut spot_counts, &mut spot_ranges),
+        ] {
+            for light in lights.iter() {
+                let (mut min, mut max) = match Self::tiles(light, proj, near) {
+                    Some(tiles) => tiles,
+                    None => continue,
+                };
+
+                min.z = Self::slice(&raw, f32::max(-light.center.z - light.range, near));
+                max.z = Self::slice(&raw, -light.center.z + light.range);
+
+                for_each_cluster(min, max, |index| counts[index] += 1);
+                ranges.push((light.index, min, max));
+            }
+        }
+
+        // compute the offset of each cluster into the index list
+        let mut offset = 0;
+        for (i, cluster) in self.cluster_lights.iter_mut().enumerate() {
+            cluster.offset = offset;
+            cluster.point_count = 0;
+            cluster.spot_count = 0;
+
+            offset += point_counts[i] + spot_counts[i];
+        }
+
+        // keep the size of the index buffer stable, to avoid reallocating it every frame
+        let index_count = offset as usize;
+        if index_count > self.index_capacity {
+            self.index_capacity = index_count.next_power_of_two();
+            self.cluster_light_indices = StorageBuffer::new(Vec::new());
+            self.bindings_changed = true;
+        }
+
+        let index_capacity = self.index_capacity;
+        let indices = &mut *self.cluster_light_indices;
+        indices.resize(index_capacity, 0);
+
+        let clusters = &mut *self.cluster_lights;
+
+        for &(index, min, max) in point_ranges.iter() {
+            for_each_cluster(min, max, |i| {
+                let cluster = &mut clusters[i];
+                indices[(cluster.offset + cluster.point_count) as usize] = index;
+                cluster.point_count += 1;
+            });
+        }
+
+        for &(index, min, max) in spot_ranges.iter() {
+            for_each_cluster(min, max, |i| {
+                let cluster = &mut clusters[i];
+                let offset = cluster.offset + point_counts[i] + cluster.spot_count;
+                indices[offset as usize] = index;
+                cluster.spot_count += 1;
+            });
+        }
+
+        self.clusters.set(raw);
+    }
+}
+
+fn for_each_cluster(min: UVec3, max: UVec3, mut f: impl FnMut(usize)) {
+    let dimensions = PreparedClusters::DIMENSIONS;
+
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                f((x + y * dimensions.x + z * dimensions.x * dimensions.y) as usize);
+            }
+        }
+    }
+}
+
+pub fn prepare_clusters_system(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    view: Res<View>,
+    prepared_lights: Res<PreparedLights>,
+    mut query: Query<(
+        Entity,
+        &Camera,
+        &PreparedTransform,
+        Option<&mut PreparedClusters>,
+    )>,
+) {
+    if let Some((entity, camera, transform, prepared)) = query.get_mut(view.camera) {
+        let aspect = view.frame_buffer.aspect_ratio();
+        let raw_camera = camera.raw_with_aspect(transform.transform, aspect);
+        let near = f32::max(camera.near(), 0.01);
+
+        let prepare = |clusters: &mut PreparedClusters| {
+            clusters.assign_lights(&raw_camera, near, &prepared_lights);
+
+            // bindings share the buffers, so writing them doesn't require rebinding
+            clusters.clusters.buffer(&device, &queue);
+            clusters.cluster_lights.buffer(&device, &queue);
+            clusters.cluster_light_indices.buffer(&device, &queue);
+        };
+
+        if let Some(mut prepared) = prepared {
+            prepared.bindings_changed = false;
+            prepare(&mut prepared);
+        } else {
+            let mut clusters = PreparedClusters::default();
+            prepare(&mut clusters);
+
+            commands.entity(entity).insert(clusters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lumi_util::math::Mat3;
+
+    use super::*;
+    use crate::{RawPointLight, RawSpotLight};
+
+    fn point_light(position: Vec3, range: f32) -> RawPointLight {
+        RawPointLight {
+            position,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            range,
+            shadow_index: -1,
+            softness: 0.0,
+            normal_bias: 0.0,
+            orientation: Mat3::IDENTITY,
+            cookie_index: -1,
+            profile_index: -1,
+            render_layers: 1,
+            contact_shadow_length: 0.0,
+        }
+    }
+
+    fn spot_light(position: Vec3, range: f32) -> RawSpotLight {
+        RawSpotLight {
+            position,
+            direction: -Vec3::Z,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            range,
+            angle_scale: 1.0,
+            angle_offset: 0.0,
+            shadow_index: -1,
+            softness: 0.0,
+            normal_bias: 0.0,
+            orientation: Mat3::IDENTITY,
+            cookie_scale: 1.0,
+            cookie_index: -1,
+            profile_index: -1,
+            render_layers: 1,
+        }
+    }
+
+    fn assign(lights: &PreparedLights) -> PreparedClusters {
+        let camera = Camera::default().raw_with_aspect(Mat4::IDENTITY, 16.0 / 9.0);
+
+        let mut clusters = PreparedClusters::default();
+        clusters.assign_lights(&camera, 0.1, lights);
+        clusters
+    }
+
+    fn cluster(clusters: &PreparedClusters, x: u32, y: u32, depth: f32) -> RawCluster {
+        let dimensions = PreparedClusters::DIMENSIONS;
+        let z = PreparedClusters::slice(&clusters.clusters, depth);
+        clusters.cluster_lights[(x + y * dimensions.x + z * dimensions.x * dimensions.y) as usize]
+    }
+
+    #[test]
+    fn test_slices() {
+        let mut lights = PreparedLights::default();
+        lights
+            .point_lights
+            .push(point_light(Vec3::new(0.0, 0.0, -10.0), 1.0));
+
+        let clusters = assign(&lights);
+        let last = PreparedClusters::DIMENSIONS.z - 1;
+
+        // slices span from the near plane to the furthest light
+        assert_eq!(PreparedClusters::slice(&clusters.clusters, 0.01), 0);
+        assert_eq!(PreparedClusters::slice(&clusters.clusters, 0.1), 0);
+        assert_eq!(PreparedClusters::slice(&clusters.clusters, 11.0), last);
+        assert_eq!(PreparedClusters::slice(&clusters.clusters, 1000.0), last);
+
+        let mut previous = 0;
+        for depth in 1..100 {
+            let slice = PreparedClusters::slice(&clusters.clusters, depth as f32 * 0.11);
+            assert!(slice >= previous);
+            previous = slice;
+        }
+    }
+
+    #[test]
+    fn test_assign_lights() {
+        let mut lights = PreparedLights::default();
+        lights
+            .point_lights
+            .push(point_light(Vec3::new(0.0, 0.0, -10.0), 1.0));
+
+        let clusters = assign(&lights);
+
+        // the light is centered on screen and only covers a few tiles around the center
+        for (x, y) in [(7, 3), (8, 4), (8, 5)] {
+            let cluster = cluster(&clusters, x, y, 10.0);
+            assert_eq!((cluster.point_count, cluster.spot_count), (1, 0));
+            assert_eq!(clusters.cluster_light_indices[cluster.offset as usize], 0);
+        }
+
+        for (x, y) in [(0, 0), (6, 4), (9, 4), (8, 2), (8, 6), (15, 8)] {
+            assert_eq!(cluster(&clusters, x, y, 10.0).point_count, 0);
+        }
+
+        assert_eq!(cluster(&clusters, 8, 4, 5.0).point_count, 0);
+    }
+
+    #[test]
+    fn test_assign_lights_order() {
+        let mut lights = PreparedLights::default();
+        let position = Vec3::new(0.0, 0.0, -10.0);
+        lights.point_lights.push(point_light(position, 1.0));
+        lights.point_lights.push(point_light(position, 2.0));
+        lights.spot_lights.push(spot_light(position, 1.0));
+
+        let clusters = assign(&lights);
+
+        // point light indices are followed by spot light indices
+        let cluster = cluster(&clusters, 8, 4, 10.0);
+        assert_eq!((cluster.point_count, cluster.spot_count), (2, 1));
+
+        let offset = cluster.offset as usize;
+        assert_eq!(
+            clusters.cluster_light_indices[offset..offset + 3],
+            [0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_assign_lights_culled() {
+        let mut lights = PreparedLights::default();
+        // behind the camera
+        lights
+            .point_lights
+            .push(point_light(Vec3::new(0.0, 0.0, 10.0), 1.0));
+        // outside the view frustum
+        lights
+            .spot_lights
+            .push(spot_light(Vec3::new(100.0, 0.0, -10.0), 1.0));
+
+        let clusters = assign(&lights);
+
+        for cluster in clusters.cluster_lights.iter() {
+            assert_eq!((cluster.point_count, cluster.spot_count), (0, 0));
+        }
+    }
+}
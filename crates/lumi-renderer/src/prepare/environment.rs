@@ -1,11 +1,21 @@
 use lumi_bind::Bind;
-use lumi_core::{Device, Queue, SharedTextureView};
+use lumi_core::{Device, Queue, SharedTextureView, UniformBuffer};
 use lumi_id::Id;
-use shiv::system::{Commands, Res};
+use lumi_util::math::Vec3;
+use shiv::{
+    query::Query,
+    system::{Commands, Res, ResMut},
+};
+use shiv_transform::GlobalTransform;
 
-use crate::{Environment, EnvironmentId, Extract, RenderDevice, RenderQueue};
+#[allow(deprecated)]
+use crate::AmbientLight;
+use crate::{
+    AtmosphereSun, DirectionalLight, Environment, EnvironmentId, EnvironmentSettings, Extract,
+    RawEnvironmentSettings, RenderDevice, RenderQueue,
+};
 
-#[derive(Clone, Debug, Bind)]
+#[derive(Debug, Bind)]
 pub struct PreparedEnvironment {
     pub sky: SharedTextureView,
     #[texture(name = "environment_diffuse", dimension = cube)]
@@ -13,38 +23,108 @@ pub struct PreparedEnvironment {
     pub irradiance: SharedTextureView,
     #[texture(name = "environment_specular", dimension = cube)]
     pub indirect: SharedTextureView,
+    #[uniform]
+    pub environment_settings: UniformBuffer<RawEnvironmentSettings>,
     pub id: EnvironmentId,
+    /// The direction towards the sun the environment was baked with.
+    pub sun_direction: Vec3,
 }
 
 impl PreparedEnvironment {
-    pub fn new(device: &Device, queue: &Queue, environment: &Environment) -> Self {
-        let baked = environment.bake(device, queue);
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        environment: &Environment,
+        sun_direction: Vec3,
+        settings: &EnvironmentSettings,
+    ) -> Self {
+        let baked = environment.bake(device, queue, sun_direction);
 
         Self {
             sky: baked.sky_view,
             irradiance: baked.irradiance_view,
             indirect: baked.indirect_view,
+            environment_settings: UniformBuffer::new(settings.raw()),
             id: environment.id(),
+            sun_direction,
         }
     }
 }
 
+#[allow(deprecated)]
 pub fn extract_environment_system(
     mut commands: Commands,
     device: Res<RenderDevice>,
     queue: Res<RenderQueue>,
     environment: Extract<Option<Res<Environment>>>,
-    prepared_environment: Option<Res<PreparedEnvironment>>,
+    settings: Extract<Option<Res<EnvironmentSettings>>>,
+    ambient_light: Extract<Option<Res<AmbientLight>>>,
+    light_query: Extract<Query<(&DirectionalLight, Option<&GlobalTransform>)>>,
+    prepared_environment: Option<ResMut<PreparedEnvironment>>,
 ) {
-    let prepared_id = prepared_environment.map(|env| env.id);
+    let settings = match (settings.as_deref(), ambient_light.as_deref()) {
+        (Some(settings), _) => *settings,
+        (None, Some(&ambient_light)) => EnvironmentSettings::from(ambient_light),
+        (None, None) => EnvironmentSettings::default(),
+    };
+    let prepared = (prepared_environment.as_ref()).map(|env| (env.id, env.sun_direction));
+
+    // settings are applied without baking the environment again
+    if let Some(mut prepared_environment) = prepared_environment {
+        let raw = settings.raw();
+        let current = *prepared_environment.environment_settings;
+
+        if raw.color != current.color || raw.rotation != current.rotation {
+            *prepared_environment.environment_settings = raw;
+        }
+    }
 
     if let Some(environment) = environment.as_deref() {
-        if prepared_id != Some(environment.id()) {
-            commands.insert_resource(PreparedEnvironment::new(&device, &queue, &environment));
+        // the direction the current environment was baked with
+        let baked_direction = match prepared {
+            Some((id, direction)) if id == environment.id() => Some(direction),
+            _ => None,
+        };
+
+        let sun_direction = match environment.sun() {
+            Some(AtmosphereSun::Direction(direction)) => direction.normalize_or_zero(),
+            Some(AtmosphereSun::Light(entity)) => match light_query.get(entity) {
+                Some((light, transform)) => {
+                    let transform = transform.copied().unwrap_or_default();
+                    -transform
+                        .matrix
+                        .mul_vec3(light.direction)
+                        .normalize_or_zero()
+                }
+                // keep the last direction while the light is missing
+                None => baked_direction.unwrap_or(Vec3::Y),
+            },
+            None => Vec3::ZERO,
+        };
+
+        let needs_bake = match baked_direction {
+            Some(baked) => AtmosphereSun::needs_rebake(baked, sun_direction),
+            None => true,
+        };
+
+        if needs_bake {
+            commands.insert_resource(PreparedEnvironment::new(
+                &device,
+                &queue,
+                &environment,
+                sun_direction,
+                &settings,
+            ));
         }
-    } else if prepared_id != Some(Id::NULL) {
+    } else if prepared.map(|(id, _)| id) != Some(Id::NULL) {
         let default = Environment::default();
 
-        commands.insert_resource(PreparedEnvironment::new(&device, &queue, &default));
+        commands.insert_resource(PreparedEnvironment::new(
+            &device,
+            &queue,
+            &default,
+            Vec3::ZERO,
+            &settings,
+        ));
     }
 }
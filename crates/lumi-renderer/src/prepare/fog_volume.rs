@@ -0,0 +1,47 @@
+use lumi_bind::Bind;
+use lumi_core::{StorageBuffer, UniformBuffer};
+use shiv::{
+    query::Query,
+    system::ResMutInit,
+    world::{FromWorld, World},
+};
+use shiv_transform::GlobalTransform;
+
+use crate::{Extract, FogVolume, RawFogVolume};
+
+#[derive(Bind)]
+pub struct PreparedFogVolumes {
+    #[uniform]
+    pub fog_volume_count: UniformBuffer<u32>,
+    #[storage_buffer]
+    pub fog_volumes: StorageBuffer<Vec<RawFogVolume>>,
+}
+
+impl FromWorld for PreparedFogVolumes {
+    fn from_world(_: &mut World) -> Self {
+        Self::new()
+    }
+}
+
+impl PreparedFogVolumes {
+    pub fn new() -> Self {
+        Self {
+            fog_volume_count: UniformBuffer::new(0),
+            fog_volumes: StorageBuffer::new(Vec::new()),
+        }
+    }
+}
+
+pub fn extract_fog_volume_system(
+    mut prepared: ResMutInit<PreparedFogVolumes>,
+    volume_query: Extract<Query<(&FogVolume, Option<&GlobalTransform>)>>,
+) {
+    prepared.fog_volumes.clear();
+
+    for (volume, transform) in volume_query.iter() {
+        let transform = transform.copied().unwrap_or_default();
+        prepared.fog_volumes.push(volume.raw(&transform));
+    }
+
+    *prepared.fog_volume_count = prepared.fog_volumes.len() as u32;
+}
@@ -0,0 +1,232 @@
+use std::{num::NonZeroU32, sync::Arc};
+
+use lumi_bake::{IrradianceVolumeData, SphericalHarmonics};
+use lumi_bind::Bind;
+use lumi_core::{
+    Device, Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, Queue, SharedDevice,
+    SharedTexture, SharedTextureView, TextureAspect, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages,
+};
+use lumi_util::HashMap;
+use shiv::{
+    query::{Changed, Or, Query, With},
+    system::{Commands, Res, ResMutInit},
+    world::{Entity, FromWorld, World},
+};
+use shiv_transform::GlobalTransform;
+
+use crate::{Extract, IrradianceVolume, RawProbe, RenderDevice, RenderQueue};
+
+#[derive(Bind)]
+pub struct PreparedIrradianceVolumes {
+    /// The volumes, they're packed into [`PreparedProbes`](crate::PreparedProbes).
+    pub irradiance_volumes: Vec<RawProbe>,
+    #[texture(name = "irradiance_volume_texture", dimension = d3)]
+    #[sampler(name = "irradiance_volume_sampler")]
+    pub view: SharedTextureView,
+    pub texture: SharedTexture,
+    data: HashMap<Entity, Arc<IrradianceVolumeData>>,
+    /// The volumes written to [`PreparedIrradianceVolumes::texture`], in order.
+    written: Vec<(Entity, Arc<IrradianceVolumeData>)>,
+    /// Volumes that need to be captured, by
+    /// [`Renderer::capture_irradiance_volumes`](crate::Renderer::capture_irradiance_volumes).
+    pub pending: Vec<Entity>,
+    pub bindings_changed: bool,
+}
+
+impl FromWorld for PreparedIrradianceVolumes {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        Self::new(device)
+    }
+}
+
+impl PreparedIrradianceVolumes {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    pub fn new(device: &Device) -> Self {
+        let (texture, view) = Self::create_texture(device, Extent3d::default());
+
+        Self {
+            irradiance_volumes: Vec::new(),
+            view,
+            texture,
+            data: HashMap::default(),
+            written: Vec::new(),
+            pending: Vec::new(),
+            bindings_changed: true,
+        }
+    }
+
+    fn create_texture(device: &Device, size: Extent3d) -> (SharedTexture, SharedTextureView) {
+        let texture = device.create_shared_texture(&TextureDescriptor {
+            label: Some("Lumi Irradiance Volume Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: Self::FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+
+        let view = texture.create_view(&Default::default());
+
+        (texture, view)
+    }
+
+    /// Returns the probes of the volume on `entity`, either baked or captured.
+    pub fn data(&self, entity: Entity) -> Option<&Arc<IrradianceVolumeData>> {
+        self.data.get(&entity)
+    }
+
+    /// Stores the probes of `entity`, they're written to the texture in the next extract.
+    pub fn insert_data(&mut self, entity: Entity, data: Arc<IrradianceVolumeData>) {
+        self.data.insert(entity, data);
+    }
+
+    fn is_written(&self, volumes: &[(Entity, Arc<IrradianceVolumeData>)]) -> bool {
+        volumes.len() == self.written.len()
+            && (volumes.iter().zip(self.written.iter()))
+                .all(|((a, a_data), (b, b_data))| a == b && Arc::ptr_eq(a_data, b_data))
+    }
+
+    /// Writes `volumes` into a new texture, stacked along z.
+    fn write_volumes(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        volumes: Vec<(Entity, Arc<IrradianceVolumeData>)>,
+    ) {
+        let mut size = Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 0,
+        };
+
+        for (_, data) in volumes.iter() {
+            size.width = u32::max(size.width, data.resolution.x);
+            size.height = u32::max(size.height, data.resolution.y);
+            size.depth_or_array_layers += data.resolution.z * SphericalHarmonics::TEXELS;
+        }
+
+        size.depth_or_array_layers = u32::max(size.depth_or_array_layers, 1);
+
+        let (texture, view) = Self::create_texture(device, size);
+
+        let mut layer = 0;
+        for (_, data) in volumes.iter() {
+            let depth = data.resolution.z * SphericalHarmonics::TEXELS;
+
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture: texture.texture(),
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                &data.texture_data(),
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(data.resolution.x * 8),
+                    rows_per_image: NonZeroU32::new(data.resolution.y),
+                },
+                Extent3d {
+                    width: data.resolution.x,
+                    height: data.resolution.y,
+                    depth_or_array_layers: depth,
+                },
+            );
+
+            layer += depth;
+        }
+
+        self.texture = texture;
+        self.view = view;
+        self.written = volumes;
+    }
+}
+
+pub fn extract_irradiance_volume_system(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut prepared: ResMutInit<PreparedIrradianceVolumes>,
+    volume_query: Extract<Query<(Entity, &IrradianceVolume, Option<&GlobalTransform>)>>,
+    changed_query: Extract<
+        Query<
+            Entity,
+            (
+                With<IrradianceVolume>,
+                Or<(Changed<IrradianceVolume>, Changed<GlobalTransform>)>,
+            ),
+        >,
+    >,
+    render_query: Query<Entity, With<IrradianceVolume>>,
+) {
+    for entity in changed_query.iter() {
+        let (_, volume, _) = volume_query.get(entity).unwrap();
+        commands.entity(entity).insert(volume.clone());
+
+        if let Some(ref data) = volume.data {
+            prepared.insert_data(entity, data.clone());
+        } else if !prepared.pending.contains(&entity) {
+            prepared.pending.push(entity);
+        }
+    }
+
+    // remove volumes that were removed from the main world
+    for entity in render_query.iter() {
+        if !volume_query.contains(entity) {
+            commands.entity(entity).remove::<IrradianceVolume>();
+        }
+    }
+
+    prepared
+        .data
+        .retain(|&entity, _| volume_query.contains(entity));
+    prepared
+        .pending
+        .retain(|&entity| volume_query.contains(entity));
+
+    // smaller volumes are sampled first, so they take precedence where volumes overlap
+    let mut volumes = volume_query
+        .iter()
+        .filter(|(entity, _, _)| prepared.data.contains_key(entity))
+        .collect::<Vec<_>>();
+    volumes.sort_by(|(_, a, _), (_, b, _)| {
+        let a = a.half_extents.x * a.half_extents.y * a.half_extents.z;
+        let b = b.half_extents.x * b.half_extents.y * b.half_extents.z;
+        a.total_cmp(&b)
+    });
+
+    let written = volumes
+        .iter()
+        .map(|&(entity, _, _)| (entity, prepared.data[&entity].clone()))
+        .collect::<Vec<_>>();
+
+    let mut bindings_changed = false;
+
+    if !prepared.is_written(&written) {
+        prepared.write_volumes(&device, &queue, written);
+        bindings_changed = true;
+    }
+
+    prepared.irradiance_volumes.clear();
+
+    let mut layer = 0;
+    for (entity, volume, transform) in volumes {
+        let transform = transform.copied().unwrap_or_default();
+        let data = prepared.data[&entity].clone();
+        let raw = volume.raw(&transform, &data, layer);
+
+        prepared.irradiance_volumes.push(raw);
+
+        layer += data.resolution.z * SphericalHarmonics::TEXELS;
+    }
+
+    prepared.bindings_changed = bindings_changed;
+}
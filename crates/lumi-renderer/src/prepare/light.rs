@@ -1,74 +1,246 @@
 use lumi_bind::Bind;
 use lumi_core::{StorageBuffer, UniformBuffer};
-use lumi_util::HashMap;
+use lumi_macro::ShaderType;
+use lumi_util::math::{Mat4, Vec3};
 
 use shiv::{
     query::Query,
-    system::{Res, ResMut},
+    system::{Res, ResMut, ResMutInit},
     world::Entity,
 };
 use shiv_transform::GlobalTransform;
 
 use crate::{
-    AmbientLight, DirectionalLight, Extract, PointLight, RawAmbientLight, RawDirectionalLight,
-    RawPointLight,
+    ActiveCamera, Camera, CameraTarget, DirectionalLight, DiskLight, Extract, PointLight,
+    PreparedLightTextures, RawAreaLight, RawDirectionalLight, RawPointLight, RawSpotLight,
+    RectLight, RenderDevice, RenderLayers, RenderQueue, ShadowKind, ShadowRequest, ShadowSettings,
+    SpotLight, TubeLight,
 };
 
+/// The number of lights in each buffer of [`PreparedLights`], and the number of probes.
+///
+/// The probe counts are set by [`extract_probe_system`](crate::extract_probe_system), which runs
+/// after [`ExtractSystem::Light`](crate::ExtractSystem::Light).
+#[derive(Clone, Copy, Debug, Default, PartialEq, ShaderType)]
+pub struct RawLightCounts {
+    pub point_count: u32,
+    pub spot_count: u32,
+    pub directional_count: u32,
+    pub area_count: u32,
+    pub reflection_probe_count: u32,
+    pub irradiance_volume_count: u32,
+}
+
 #[derive(Default, Bind)]
 pub struct PreparedLights {
     #[uniform]
-    pub ambient_light: UniformBuffer<RawAmbientLight>,
-    #[uniform]
-    pub point_light_count: UniformBuffer<u32>,
+    pub light_counts: UniformBuffer<RawLightCounts>,
     #[storage_buffer]
     pub point_lights: StorageBuffer<Vec<RawPointLight>>,
-    #[uniform]
-    pub directional_light_count: UniformBuffer<u32>,
+    #[storage_buffer]
+    pub spot_lights: StorageBuffer<Vec<RawSpotLight>>,
     #[storage_buffer]
     pub directional_lights: StorageBuffer<Vec<RawDirectionalLight>>,
-    pub next_cascade_index: u32,
-    pub cascade_indices: HashMap<Entity, u32>,
+    /// Rect, disk and tube lights.
+    #[storage_buffer]
+    pub area_lights: StorageBuffer<Vec<RawAreaLight>>,
+    /// Shadow atlas tiles requested by lights, indexed by tile.
+    pub shadow_requests: Vec<ShadowRequest>,
     pub bindings_changed: bool,
 }
 
 impl PreparedLights {
     #[inline]
     pub fn clear(&mut self) {
-        *self.ambient_light = RawAmbientLight::default();
-        *self.point_light_count = 0;
-        *self.directional_light_count = 0;
+        *self.light_counts = RawLightCounts::default();
 
         self.point_lights.clear();
+        self.spot_lights.clear();
         self.directional_lights.clear();
+        self.area_lights.clear();
+
+        self.shadow_requests.clear();
+    }
+
+    /// Returns true if any light casts contact shadows, which trace against the hi-z.
+    #[inline]
+    pub fn has_contact_shadows(&self) -> bool {
+        let point = self.point_lights.iter();
+        let directional = self.directional_lights.iter();
+
+        let mut lengths = (point.map(|light| light.contact_shadow_length))
+            .chain(directional.map(|light| light.contact_shadow_length));
 
-        self.next_cascade_index = 0;
-        self.cascade_indices.clear();
+        lengths.any(|length| length > 0.0)
+    }
+
+    /// Requests a shadow atlas tile, returning the index of the tile.
+    #[inline]
+    pub fn request_shadow(&mut self, request: ShadowRequest) -> u32 {
+        let index = self.shadow_requests.len() as u32;
+        self.shadow_requests.push(request);
+        index
     }
 }
 
+/// Scales `resolution` by the importance of a light with `range` at `distance` from the camera.
+fn shadow_resolution(resolution: u32, range: f32, distance: f32) -> u32 {
+    let importance = f32::clamp(range / distance, 1.0 / 8.0, 1.0);
+    (resolution as f32 * importance) as u32
+}
+
 pub fn extract_light_system(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
     mut prepared_lights: ResMut<PreparedLights>,
-    ambient_light: Option<Res<AmbientLight>>,
+    mut light_textures: ResMutInit<PreparedLightTextures>,
+    shadow_settings: Option<Res<ShadowSettings>>,
+    active_camera: Option<Res<ActiveCamera>>,
+    cameras: Extract<Query<(Entity, &Camera, Option<&GlobalTransform>)>>,
     point_lights: Extract<Query<(Entity, &PointLight, Option<&GlobalTransform>)>>,
+    spot_lights: Extract<Query<(Entity, &SpotLight, Option<&GlobalTransform>)>>,
     directional_lights: Extract<Query<(Entity, &DirectionalLight, Option<&GlobalTransform>)>>,
+    rect_lights: Extract<Query<(Entity, &RectLight, Option<&GlobalTransform>)>>,
+    disk_lights: Extract<Query<(Entity, &DiskLight, Option<&GlobalTransform>)>>,
+    tube_lights: Extract<Query<(Entity, &TubeLight, Option<&GlobalTransform>)>>,
+    render_layers_query: Extract<Query<&RenderLayers>>,
 ) {
     prepared_lights.clear();
+    light_textures.clear();
 
     let point_light_cap = prepared_lights.point_lights.capacity();
+    let spot_light_cap = prepared_lights.spot_lights.capacity();
     let directional_light_cap = prepared_lights.directional_lights.capacity();
+    let area_light_cap = prepared_lights.area_lights.capacity();
 
-    // prepare ambient light
-    if let Some(ambient_light) = ambient_light {
-        prepared_lights.ambient_light.set(ambient_light.raw());
-    }
+    let shadow_settings = shadow_settings.as_deref().cloned().unwrap_or_default();
+
+    // find the camera to fit shadows to, falling back to the first camera rendering to the main
+    // target, before any camera has been rendered
+    let camera = match active_camera {
+        Some(active) => cameras
+            .get(active.camera)
+            .map(|(_, camera, transform)| (camera, transform, active.aspect)),
+        None => cameras
+            .iter()
+            .find(|(_, camera, _)| camera.enabled && camera.target == CameraTarget::Main)
+            .map(|(_, camera, transform)| (camera, transform, 1.0)),
+    };
+
+    let render_layers = |entity: Entity| {
+        let render_layers = render_layers_query.get(entity);
+        render_layers.copied().unwrap_or_default()
+    };
+
+    let camera_position = camera.map(|(_, transform, _)| match transform {
+        Some(transform) => transform.translation,
+        None => Vec3::ZERO,
+    });
 
     // prepare point lights
-    for (_, light, transform) in point_lights.iter() {
-        let position = transform.map(|t| t.translation).unwrap_or_default();
-        let raw_light = light.raw(position);
+    for (entity, light, transform) in point_lights.iter() {
+        let transform = transform.copied().unwrap_or_default();
+        let position = transform.translation;
+        let render_layers = render_layers(entity);
+
+        let shadow_index = if light.shadows {
+            let mut resolution = shadow_settings.resolution(light.shadow_resolution);
+
+            if let Some(camera_position) = camera_position {
+                let distance = camera_position.distance(position);
+                resolution = shadow_resolution(resolution, light.range, distance);
+            }
+
+            // the faces are stored in consecutive tiles
+            let index = prepared_lights.shadow_requests.len() as u32;
+            let light_index = prepared_lights.point_lights.len() as u32;
+
+            for face in 0..PointLight::CUBE_FACES {
+                prepared_lights.request_shadow(ShadowRequest {
+                    kind: ShadowKind::Point,
+                    entity,
+                    light: light_index,
+                    layer: face,
+                    view_proj: light.view_proj(position, face),
+                    frustum: light.frustum(position, face),
+                    cascade: None,
+                    resolution,
+                    bias: light.shadow_bias,
+                    render_layers,
+                });
+            }
+
+            Some(index)
+        } else {
+            None
+        };
+
+        let mut raw_light = light.raw(&transform, shadow_index);
+        raw_light.render_layers = render_layers.bits();
+
+        if let Some(ref cookie) = light.cookie {
+            raw_light.cookie_index = light_textures.cookie_index(cookie);
+        }
+
+        if let Some(ref profile) = light.ies_profile {
+            raw_light.profile_index = light_textures.profile_index(profile);
+        }
 
         prepared_lights.point_lights.push(raw_light);
-        *prepared_lights.point_light_count += 1;
+        prepared_lights.light_counts.point_count += 1;
+    }
+
+    // prepare spot lights
+    for (entity, light, transform) in spot_lights.iter() {
+        let transform = transform.copied().unwrap_or_default();
+        let position = transform.translation;
+        let render_layers = render_layers(entity);
+
+        // the shadow projection uses the world space direction
+        let mut world_light = light.clone();
+        world_light.direction = transform.matrix.mul_vec3(light.direction);
+        world_light.direction = world_light.direction.normalize_or_zero();
+
+        let shadow_index = if light.shadows {
+            let mut resolution = shadow_settings.resolution(light.shadow_resolution);
+
+            if let Some(camera_position) = camera_position {
+                let distance = camera_position.distance(position);
+                resolution = shadow_resolution(resolution, light.range, distance);
+            }
+
+            let light_index = prepared_lights.spot_lights.len() as u32;
+            let index = prepared_lights.request_shadow(ShadowRequest {
+                kind: ShadowKind::Spot,
+                entity,
+                light: light_index,
+                layer: 0,
+                view_proj: world_light.view_proj(position),
+                frustum: world_light.frustum(position),
+                cascade: None,
+                resolution,
+                bias: light.shadow_bias,
+                render_layers,
+            });
+
+            Some(index)
+        } else {
+            None
+        };
+
+        let mut raw_light = light.raw(&transform, shadow_index);
+        raw_light.render_layers = render_layers.bits();
+
+        if let Some(ref cookie) = light.cookie {
+            raw_light.cookie_index = light_textures.cookie_index(cookie);
+        }
+
+        if let Some(ref profile) = light.ies_profile {
+            raw_light.profile_index = light_textures.profile_index(profile);
+        }
+
+        prepared_lights.spot_lights.push(raw_light);
+        prepared_lights.light_counts.spot_count += 1;
     }
 
     // prepare directional lights
@@ -79,25 +251,99 @@ pub fn extract_light_system(
         light.direction = transform.matrix.mul_vec3(light.direction);
         light.direction = light.direction.normalize_or_zero();
 
-        let cascade = if light.shadows {
-            let index = prepared_lights.next_cascade_index;
+        let render_layers = render_layers(entity);
 
-            prepared_lights.cascade_indices.insert(entity, index);
-            prepared_lights.next_cascade_index += DirectionalLight::CASCADES as u32;
+        let (cascade, cascade_count) = match camera {
+            Some((camera, camera_transform, aspect)) if light.shadows => {
+                let index = prepared_lights.shadow_requests.len() as u32;
+                let light_index = prepared_lights.directional_lights.len() as u32;
+                let view = camera_transform.map_or(Mat4::IDENTITY, |t| t.compute_matrix());
 
-            index
-        } else {
-            0
+                let resolution = shadow_settings.resolution(light.shadow_resolution);
+                let cascades = light.cascades(camera, view, aspect, resolution);
+                let count = cascades.len() as u32;
+
+                for (layer, cascade) in cascades.into_iter().enumerate() {
+                    prepared_lights.request_shadow(ShadowRequest {
+                        kind: ShadowKind::Directional,
+                        entity,
+                        light: light_index,
+                        layer: layer as u32,
+                        view_proj: cascade.view_proj(),
+                        frustum: cascade.frustum(),
+                        cascade: Some(cascade),
+                        resolution,
+                        bias: light.shadow_bias,
+                        render_layers,
+                    });
+                }
+
+                (index, count)
+            }
+            _ => (0, 0),
         };
 
-        let raw_light = light.raw(cascade);
+        let mut raw_light = light.raw(cascade, cascade_count);
+        raw_light.render_layers = render_layers.bits();
 
         prepared_lights.directional_lights.push(raw_light);
-        *prepared_lights.directional_light_count += 1;
+        prepared_lights.light_counts.directional_count += 1;
+    }
+
+    // prepare area lights
+    for (entity, light, transform) in rect_lights.iter() {
+        let transform = transform.copied().unwrap_or_default();
+
+        let mut raw_light = light.raw(&transform);
+        raw_light.render_layers = render_layers(entity).bits();
+
+        prepared_lights.area_lights.push(raw_light);
+        prepared_lights.light_counts.area_count += 1;
+    }
+
+    for (entity, light, transform) in disk_lights.iter() {
+        let transform = transform.copied().unwrap_or_default();
+
+        let mut raw_light = light.raw(&transform);
+        raw_light.render_layers = render_layers(entity).bits();
+
+        prepared_lights.area_lights.push(raw_light);
+        prepared_lights.light_counts.area_count += 1;
+    }
+
+    for (entity, light, transform) in tube_lights.iter() {
+        let transform = transform.copied().unwrap_or_default();
+
+        let mut raw_light = light.raw(&transform);
+        raw_light.render_layers = render_layers(entity).bits();
+
+        prepared_lights.area_lights.push(raw_light);
+        prepared_lights.light_counts.area_count += 1;
     }
 
     let point = prepared_lights.point_lights.capacity() != point_light_cap;
+    let spot = prepared_lights.spot_lights.capacity() != spot_light_cap;
     let directional = prepared_lights.directional_lights.capacity() != directional_light_cap;
+    let area = prepared_lights.area_lights.capacity() != area_light_cap;
+
+    prepared_lights.bindings_changed = point || spot || directional || area;
+
+    light_textures.prepare(&device, &queue);
+}
 
-    prepared_lights.bindings_changed = point || directional;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_resolution() {
+        // lights covering the camera keep their full resolution
+        assert_eq!(shadow_resolution(1024, 10.0, 5.0), 1024);
+        assert_eq!(shadow_resolution(1024, 10.0, 0.0), 1024);
+
+        assert_eq!(shadow_resolution(1024, 10.0, 20.0), 512);
+
+        // distant lights never go below an eighth
+        assert_eq!(shadow_resolution(1024, 10.0, 1000.0), 128);
+    }
 }
@@ -0,0 +1,304 @@
+use std::num::NonZeroU32;
+
+use lumi_bind::Bind;
+use lumi_core::{
+    Device, Extent3d, Image, ImageCopyTexture, ImageDataLayout, Origin3d, Queue, SharedDevice,
+    SharedTexture, SharedTextureView, TextureAspect, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+};
+use lumi_id::Id;
+use shiv::world::{FromWorld, World};
+
+use crate::{IesProfile, RenderDevice};
+
+/// The layers of a texture array, holding one image each.
+struct LightTextureArray {
+    label: &'static str,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    texture: SharedTexture,
+    view: SharedTextureView,
+    ids: Vec<Id<Image>>,
+}
+
+impl LightTextureArray {
+    fn new(
+        device: &Device,
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Self {
+        let (texture, view) = Self::create_texture(device, label, width, height, format, 1);
+
+        Self {
+            label,
+            width,
+            height,
+            format,
+            texture,
+            view,
+            ids: Vec::new(),
+        }
+    }
+
+    fn create_texture(
+        device: &Device,
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        layers: u32,
+    ) -> (SharedTexture, SharedTextureView) {
+        let texture = device.create_shared_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        (texture, view)
+    }
+
+    #[inline]
+    fn layers(&self) -> u32 {
+        self.texture.size().depth_or_array_layers
+    }
+
+    /// Writes `images` into the layers of the array, returns true if the texture was recreated.
+    fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        images: &[Image],
+        write: impl Fn(&Image, u32, u32) -> Vec<u8>,
+    ) -> bool {
+        let ids = images.iter().map(Image::id).collect::<Vec<_>>();
+
+        if ids == self.ids {
+            return false;
+        }
+
+        let layers = u32::max(images.len() as u32, 1).next_power_of_two();
+        let resized = layers > self.layers();
+
+        if resized {
+            let (texture, view) = Self::create_texture(
+                device,
+                self.label,
+                self.width,
+                self.height,
+                self.format,
+                layers,
+            );
+
+            self.texture = texture;
+            self.view = view;
+        }
+
+        let bytes_per_pixel = self.format.describe().block_size as u32;
+
+        for (layer, image) in images.iter().enumerate() {
+            // layers are only rewritten when their image changed
+            if !resized && self.ids.get(layer) == Some(&image.id()) {
+                continue;
+            }
+
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture: self.texture.texture(),
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                &write(image, self.width, self.height),
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_pixel * self.width),
+                    rows_per_image: None,
+                },
+                Extent3d {
+                    width: self.width,
+                    height: self.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        self.ids = ids;
+
+        resized
+    }
+}
+
+/// Bilinearly resamples an 8 bit rgba `image` to `width` by `height`.
+///
+/// Images in other formats are replaced by white.
+fn resample_rgba8(image: &Image, width: u32, height: u32) -> Vec<u8> {
+    let data = image.data();
+    let size = (width * height * 4) as usize;
+
+    let is_rgba8 = matches!(
+        data.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    );
+
+    if !is_rgba8 || data.width == 0 || data.height == 0 {
+        return vec![255; size];
+    }
+
+    let texel = |x: u32, y: u32, c: usize| {
+        let index = ((y * data.width + x) * 4) as usize + c;
+        data.data[index] as f32
+    };
+
+    let mut bytes = Vec::with_capacity(size);
+
+    for y in 0..height {
+        let v = (y as f32 + 0.5) / height as f32 * data.height as f32 - 0.5;
+        let v = v.clamp(0.0, (data.height - 1) as f32);
+        let y0 = v as u32;
+        let y1 = u32::min(y0 + 1, data.height - 1);
+        let ty = v.fract();
+
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32 * data.width as f32 - 0.5;
+            let u = u.clamp(0.0, (data.width - 1) as f32);
+            let x0 = u as u32;
+            let x1 = u32::min(x0 + 1, data.width - 1);
+            let tx = u.fract();
+
+            for c in 0..4 {
+                let top = texel(x0, y0, c) * (1.0 - tx) + texel(x1, y0, c) * tx;
+                let bottom = texel(x0, y1, c) * (1.0 - tx) + texel(x1, y1, c) * tx;
+                let value = top * (1.0 - ty) + bottom * ty;
+
+                bytes.push(value.round() as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+/// Light cookies and IES profiles used by point and spot lights, each stored in a layer of a
+/// texture array.
+#[derive(Bind)]
+pub struct PreparedLightTextures {
+    cookies: LightTextureArray,
+    profiles: LightTextureArray,
+    #[texture(name = "light_cookies", dimension = d2_array)]
+    #[sampler(name = "light_texture_sampler")]
+    pub cookie_view: SharedTextureView,
+    #[texture(name = "light_profiles", dimension = d2_array)]
+    pub profile_view: SharedTextureView,
+    /// The cookies used this frame, indexed by layer.
+    pub cookie_images: Vec<Image>,
+    /// The lookup textures of the IES profiles used this frame, indexed by layer.
+    pub profile_images: Vec<Image>,
+    pub bindings_changed: bool,
+}
+
+impl FromWorld for PreparedLightTextures {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        Self::new(device)
+    }
+}
+
+impl PreparedLightTextures {
+    /// The size of each cookie layer, cookies are resampled to this size.
+    pub const COOKIE_SIZE: u32 = 256;
+
+    pub fn new(device: &Device) -> Self {
+        let cookies = LightTextureArray::new(
+            device,
+            "Lumi Light Cookies",
+            Self::COOKIE_SIZE,
+            Self::COOKIE_SIZE,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let profiles = LightTextureArray::new(
+            device,
+            "Lumi Light Profiles",
+            IesProfile::WIDTH,
+            IesProfile::HEIGHT,
+            IesProfile::FORMAT,
+        );
+
+        Self {
+            cookie_view: cookies.view.clone(),
+            profile_view: profiles.view.clone(),
+            cookies,
+            profiles,
+            cookie_images: Vec::new(),
+            profile_images: Vec::new(),
+            bindings_changed: true,
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.cookie_images.clear();
+        self.profile_images.clear();
+    }
+
+    /// Returns the layer of `cookie`, adding it if it isn't used yet this frame.
+    #[inline]
+    pub fn cookie_index(&mut self, cookie: &Image) -> i32 {
+        Self::index(&mut self.cookie_images, cookie)
+    }
+
+    /// Returns the layer of `profile`, adding it if it isn't used yet this frame.
+    #[inline]
+    pub fn profile_index(&mut self, profile: &IesProfile) -> i32 {
+        Self::index(&mut self.profile_images, profile.image())
+    }
+
+    fn index(images: &mut Vec<Image>, image: &Image) -> i32 {
+        match images.iter().position(|other| other.id() == image.id()) {
+            Some(index) => index as i32,
+            None => {
+                images.push(image.clone());
+                images.len() as i32 - 1
+            }
+        }
+    }
+
+    /// Writes the images used this frame into the texture arrays.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        let cookies = self
+            .cookies
+            .prepare(device, queue, &self.cookie_images, resample_rgba8);
+
+        // profiles are created with the size and format of the array
+        let profiles = self
+            .profiles
+            .prepare(device, queue, &self.profile_images, |image, _, _| {
+                image.data().data.clone()
+            });
+
+        self.cookie_view = self.cookies.view.clone();
+        self.profile_view = self.profiles.view.clone();
+
+        self.bindings_changed = cookies || profiles;
+    }
+}
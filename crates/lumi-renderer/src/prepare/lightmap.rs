@@ -0,0 +1,39 @@
+use lumi_bind::Bind;
+use lumi_core::Image;
+use shiv::{
+    query::{Changed, Query, With},
+    system::Commands,
+    world::{Component, Entity},
+};
+
+use crate::{Extract, Lightmap, RawLightmap};
+
+#[derive(Component, Bind)]
+pub struct PreparedLightmap {
+    #[texture(name = "lightmap_texture")]
+    #[sampler(name = "lightmap_sampler")]
+    pub image: Image,
+    #[uniform(name = "lightmap")]
+    pub lightmap: RawLightmap,
+}
+
+pub fn extract_lightmap_system(
+    mut commands: Commands,
+    lightmap_query: Extract<Query<(Entity, &Lightmap), Changed<Lightmap>>>,
+    main_query: Extract<Query<(), With<Lightmap>>>,
+    render_query: Query<Entity, With<PreparedLightmap>>,
+) {
+    for (entity, lightmap) in lightmap_query.iter() {
+        commands.entity(entity).insert(PreparedLightmap {
+            image: lightmap.image.clone(),
+            lightmap: lightmap.raw(),
+        });
+    }
+
+    // remove lightmaps that were removed from the main world
+    for entity in render_query.iter() {
+        if !main_query.contains(entity) {
+            commands.entity(entity).remove::<PreparedLightmap>();
+        }
+    }
+}
@@ -1,14 +1,30 @@
+mod atlas;
 mod camera;
+mod cluster;
 mod environment;
+mod fog_volume;
+mod irradiance_volume;
 mod light;
+mod light_texture;
+mod lightmap;
 mod mesh;
+mod probe;
+mod reflection_probe;
 mod shadow;
 mod transform;
 
+pub use atlas::*;
 pub use camera::*;
+pub use cluster::*;
 pub use environment::*;
+pub use fog_volume::*;
+pub use irradiance_volume::*;
 pub use light::*;
+pub use light_texture::*;
+pub use lightmap::*;
 pub use mesh::*;
+pub use probe::*;
+pub use reflection_probe::*;
 pub use shadow::*;
 pub use transform::*;
 
@@ -21,9 +37,14 @@ use crate::{ExtractStage, Renderer, RendererPlugin};
 pub enum ExtractSystem {
     Transform,
     Light,
+    Lightmap,
     Mesh,
     Camera,
     Environment,
+    FogVolume,
+    IrradianceVolume,
+    ReflectionProbe,
+    Probe,
     Shadow,
 }
 
@@ -41,6 +62,14 @@ impl RendererPlugin for CoreExtractPlugin {
                 ExtractStage::PreExtract,
                 insert_state_system.label(ExtractSystem::Shadow),
             )
+            .add_system_to_stage(
+                ExtractStage::PreExtract,
+                clear_shadow_targets_system.label(ExtractSystem::Shadow),
+            )
+            .add_system_to_stage(
+                ExtractStage::PreExtract,
+                extract_shadow_settings_system.label(ExtractSystem::Shadow),
+            )
             .add_system_to_stage(
                 ExtractStage::Extract,
                 extract_transform_system.label(ExtractSystem::Transform),
@@ -51,7 +80,7 @@ impl RendererPlugin for CoreExtractPlugin {
             )
             .add_system_to_stage(
                 ExtractStage::Extract,
-                extract_directional_shadow_system
+                extract_shadow_system
                     .label(ExtractSystem::Shadow)
                     .after(ExtractSystem::Light),
             )
@@ -63,6 +92,30 @@ impl RendererPlugin for CoreExtractPlugin {
                 ExtractStage::Extract,
                 extract_environment_system.label(ExtractSystem::Environment),
             )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_fog_volume_system.label(ExtractSystem::FogVolume),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_irradiance_volume_system.label(ExtractSystem::IrradianceVolume),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_reflection_probe_system.label(ExtractSystem::ReflectionProbe),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_probe_system
+                    .label(ExtractSystem::Probe)
+                    .after(ExtractSystem::Light)
+                    .after(ExtractSystem::ReflectionProbe)
+                    .after(ExtractSystem::IrradianceVolume),
+            )
+            .add_system_to_stage(
+                ExtractStage::Extract,
+                extract_lightmap_system.label(ExtractSystem::Lightmap),
+            )
             .add_system_to_stage(
                 ExtractStage::Extract,
                 extract_mesh_system.label(ExtractSystem::Mesh),
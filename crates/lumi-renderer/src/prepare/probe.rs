@@ -0,0 +1,64 @@
+use lumi_bind::Bind;
+use lumi_core::StorageBuffer;
+use lumi_macro::ShaderType;
+use lumi_util::math::{Mat4, Vec3};
+use shiv::system::{Res, ResMut, ResMutInit};
+
+use crate::{PreparedIrradianceVolumes, PreparedLights, PreparedReflectionProbes};
+
+/// A [`ReflectionProbe`](crate::ReflectionProbe) or an
+/// [`IrradianceVolume`](crate::IrradianceVolume), both are packed into [`PreparedProbes`].
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct RawProbe {
+    pub probe_from_world: Mat4,
+    /// The position of a reflection probe.
+    pub position: Vec3,
+    /// `0` for boxes and `1` for spheres.
+    pub shape: u32,
+    pub half_extents: Vec3,
+    /// The radius of a spherical reflection probe.
+    pub radius: f32,
+    /// The number of probes along each axis of an irradiance volume.
+    pub resolution: Vec3,
+    pub blend_distance: f32,
+    /// The intensity of the probe divided by the exposure it was captured with.
+    pub intensity: f32,
+    /// The first layer of the probe in the reflection probe or irradiance volume texture.
+    pub layer: i32,
+}
+
+/// The reflection probes followed by the irradiance volumes, their counts are stored in
+/// [`PreparedLights::light_counts`].
+#[derive(Default, Bind)]
+pub struct PreparedProbes {
+    #[storage_buffer]
+    pub probes: StorageBuffer<Vec<RawProbe>>,
+    pub bindings_changed: bool,
+}
+
+pub fn extract_probe_system(
+    mut prepared: ResMutInit<PreparedProbes>,
+    mut prepared_lights: ResMut<PreparedLights>,
+    reflection_probes: Res<PreparedReflectionProbes>,
+    irradiance_volumes: Res<PreparedIrradianceVolumes>,
+) {
+    let capacity = prepared.probes.capacity();
+    prepared.probes.clear();
+
+    prepared
+        .probes
+        .extend_from_slice(&reflection_probes.reflection_probes);
+    prepared
+        .probes
+        .extend_from_slice(&irradiance_volumes.irradiance_volumes);
+
+    let counts = &mut prepared_lights.light_counts;
+    counts.reflection_probe_count = reflection_probes.reflection_probes.len() as u32;
+    counts.irradiance_volume_count = irradiance_volumes.irradiance_volumes.len() as u32;
+
+    // the counts only change when the textures are rewritten, and are uploaded with the lights
+    let probes_changed = reflection_probes.bindings_changed || irradiance_volumes.bindings_changed;
+    prepared_lights.bindings_changed |= probes_changed;
+
+    prepared.bindings_changed = prepared.probes.capacity() != capacity;
+}
@@ -0,0 +1,214 @@
+use lumi_bake::BakedEnvironment;
+use lumi_bind::Bind;
+use lumi_core::{
+    CommandEncoder, Device, Extent3d, ImageCopyTexture, Origin3d, SharedDevice, SharedTexture,
+    SharedTextureView, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension,
+};
+use lumi_util::HashMap;
+use shiv::{
+    query::{Changed, Or, Query, With},
+    system::{Commands, Res, ResMut, ResMutInit},
+    world::{Entity, FromWorld, World},
+};
+use shiv_transform::GlobalTransform;
+
+use crate::{Extract, RawProbe, ReflectionProbe, RenderDevice};
+
+/// The prefiltered capture of a [`ReflectionProbe`].
+struct ReflectionProbeCapture {
+    indirect: SharedTexture,
+    /// The exposure of the camera the probe was captured with.
+    exposure: f32,
+}
+
+#[derive(Bind)]
+pub struct PreparedReflectionProbes {
+    /// The probes, they're packed into [`PreparedProbes`](crate::PreparedProbes).
+    pub reflection_probes: Vec<RawProbe>,
+    #[texture(name = "reflection_probe_textures", dimension = cube_array)]
+    #[sampler(name = "reflection_probe_sampler")]
+    pub view: SharedTextureView,
+    pub texture: SharedTexture,
+    captures: HashMap<Entity, ReflectionProbeCapture>,
+    /// The probe stored in each layer of [`PreparedReflectionProbes::texture`].
+    layers: Vec<Entity>,
+    /// Probes that need to be captured, by
+    /// [`Renderer::capture_reflection_probes`](crate::Renderer::capture_reflection_probes).
+    pub pending: Vec<Entity>,
+    pub bindings_changed: bool,
+}
+
+impl FromWorld for PreparedReflectionProbes {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        Self::new(device)
+    }
+}
+
+impl PreparedReflectionProbes {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    pub fn new(device: &Device) -> Self {
+        let (texture, view) = Self::create_texture(device, 1);
+
+        Self {
+            reflection_probes: Vec::new(),
+            view,
+            texture,
+            captures: HashMap::default(),
+            layers: Vec::new(),
+            pending: Vec::new(),
+            bindings_changed: true,
+        }
+    }
+
+    fn create_texture(device: &Device, layers: u32) -> (SharedTexture, SharedTextureView) {
+        let size = ReflectionProbe::INDIRECT_SIZE;
+
+        let texture = device.create_shared_texture(&TextureDescriptor {
+            label: Some("Lumi Reflection Probe Texture"),
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers * 6,
+            },
+            mip_level_count: 30 - size.leading_zeros(),
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::CubeArray),
+            ..Default::default()
+        });
+
+        (texture, view)
+    }
+
+    /// Stores the capture of `entity`, it's written to the texture in the next extract.
+    pub fn insert_capture(&mut self, entity: Entity, baked: BakedEnvironment, exposure: f32) {
+        let capture = ReflectionProbeCapture {
+            indirect: baked.indirect,
+            exposure,
+        };
+
+        self.captures.insert(entity, capture);
+
+        // force the layers to be rewritten
+        self.layers.clear();
+    }
+
+    fn write_layers(&mut self, device: &Device, encoder: &mut CommandEncoder, layers: Vec<Entity>) {
+        let (texture, view) = Self::create_texture(device, u32::max(layers.len() as u32, 1));
+        let size = ReflectionProbe::INDIRECT_SIZE;
+
+        for (layer, entity) in layers.iter().enumerate() {
+            let capture = &self.captures[entity];
+
+            for mip_level in 0..texture.mip_level_count() {
+                encoder.copy_texture_to_texture(
+                    ImageCopyTexture {
+                        texture: capture.indirect.texture(),
+                        mip_level,
+                        origin: Origin3d::ZERO,
+                        aspect: TextureAspect::All,
+                    },
+                    ImageCopyTexture {
+                        texture: texture.texture(),
+                        mip_level,
+                        origin: Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32 * 6,
+                        },
+                        aspect: TextureAspect::All,
+                    },
+                    Extent3d {
+                        width: size >> mip_level,
+                        height: size >> mip_level,
+                        depth_or_array_layers: 6,
+                    },
+                );
+            }
+        }
+
+        self.texture = texture;
+        self.view = view;
+        self.layers = layers;
+    }
+}
+
+pub fn extract_reflection_probe_system(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut encoder: ResMut<CommandEncoder>,
+    mut prepared: ResMutInit<PreparedReflectionProbes>,
+    probe_query: Extract<Query<(Entity, &ReflectionProbe, Option<&GlobalTransform>)>>,
+    changed_query: Extract<
+        Query<
+            Entity,
+            (
+                With<ReflectionProbe>,
+                Or<(Changed<ReflectionProbe>, Changed<GlobalTransform>)>,
+            ),
+        >,
+    >,
+    render_query: Query<Entity, With<ReflectionProbe>>,
+) {
+    for entity in changed_query.iter() {
+        let (_, probe, _) = probe_query.get(entity).unwrap();
+        commands.entity(entity).insert(probe.clone());
+
+        if !prepared.pending.contains(&entity) {
+            prepared.pending.push(entity);
+        }
+    }
+
+    // remove probes that were removed from the main world
+    for entity in render_query.iter() {
+        if !probe_query.contains(entity) {
+            commands.entity(entity).remove::<ReflectionProbe>();
+        }
+    }
+
+    prepared
+        .captures
+        .retain(|&entity, _| probe_query.contains(entity));
+    prepared
+        .pending
+        .retain(|&entity| probe_query.contains(entity));
+
+    // smaller probes are sampled first, so they take precedence where probes overlap
+    let mut probes = probe_query
+        .iter()
+        .filter(|(entity, _, _)| prepared.captures.contains_key(entity))
+        .collect::<Vec<_>>();
+    probes.sort_by(|(_, a, _), (_, b, _)| a.volume.volume().total_cmp(&b.volume.volume()));
+
+    let layers = probes
+        .iter()
+        .map(|&(entity, _, _)| entity)
+        .collect::<Vec<_>>();
+
+    let mut bindings_changed = false;
+
+    if layers != prepared.layers {
+        prepared.write_layers(&device, &mut encoder, layers);
+        bindings_changed = true;
+    }
+
+    prepared.reflection_probes.clear();
+
+    for (layer, (entity, probe, transform)) in probes.into_iter().enumerate() {
+        let transform = transform.copied().unwrap_or_default();
+        let exposure = prepared.captures[&entity].exposure;
+        let raw = probe.raw(&transform, exposure, layer as i32);
+
+        prepared.reflection_probes.push(raw);
+    }
+
+    prepared.bindings_changed = bindings_changed;
+}
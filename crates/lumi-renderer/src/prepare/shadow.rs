@@ -1,120 +1,144 @@
-use std::{
-    num::NonZeroU32,
-    ops::{Deref, DerefMut},
-};
+use std::ops::{Deref, DerefMut};
 
 use lumi_bind::{Bind, Binding, BindingLayout};
-use lumi_bounds::CascadeFrustum;
+use lumi_bounds::{BoundingShape, Frustum};
 use lumi_core::{
-    CommandEncoder, Device, Extent3d, IndexFormat, LoadOp, Operations, Queue,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipelineDescriptor, SharedBuffer,
-    SharedDevice, SharedRenderPipeline, SharedTexture, SharedTextureView, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, UniformBuffer,
-    VertexBufferLayout, VertexState, VertexStepMode,
+    CommandEncoder, DepthBiasState, Device, Extent3d, IndexFormat, LoadOp, Operations,
+    PipelineLayout, Queue, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipelineDescriptor, SharedBuffer, SharedDevice, SharedRenderPipeline, SharedTexture,
+    SharedTextureView, StorageBuffer, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, UniformBuffer, VertexBufferLayout, VertexState, VertexStepMode,
 };
 use lumi_id::{Id, IdMap};
+use lumi_macro::ShaderType;
 use lumi_mesh::Mesh;
-use lumi_shader::{DefaultShader, ShaderProcessor, ShaderRef};
-use lumi_util::math::Mat4;
+use lumi_shader::{DefaultShader, Shader, ShaderProcessor, ShaderRef};
+use lumi_util::{
+    math::{Mat4, Vec4},
+    HashMap, HashSet,
+};
 use shiv::{
     query::{Changed, Query, Without},
-    system::{Commands, Res, ResInit, ResMut, ResMutInit},
+    system::{Commands, Res, ResMut, ResMutInit},
     world::{Component, Entity, FromWorld, World},
 };
-use shiv_transform::GlobalTransform;
 
 use crate::{
-    DirectionalLight, Extract, ExtractedMeshes, PreparedLights, PreparedMeshes, PreparedTransform,
-    RenderDevice, RenderQueue,
+    DirectionalCascade, Extract, ExtractedMeshes, PreparedLights, PreparedMeshes,
+    PreparedTransform, RenderDevice, RenderLayers, RenderQueue, ShadowAtlas, ShadowBias,
+    ShadowTile,
 };
 
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct RawShadowTile {
+    pub view_proj: Mat4,
+    /// The offset and size of the tile in uv space of the atlas, zero if no tile was allocated.
+    pub rect: Vec4,
+}
+
 #[derive(Bind)]
 pub struct PreparedShadows {
-    pub cascade_texture: SharedTexture,
-    #[texture(name = "directional_shadow_maps", dimension = d2_array, sample_type = depth)]
+    pub atlas_texture: SharedTexture,
+    #[texture(name = "shadow_atlas", dimension = d2, sample_type = depth)]
     #[sampler(name = "shadow_map_sampler")]
-    pub cascade_view: SharedTextureView,
-    pub cascade_view_proj_buffers: Vec<UniformBuffer<Mat4>>,
-    pub cascade_frustums: Vec<CascadeFrustum>,
+    pub atlas_view: SharedTextureView,
+    #[storage_buffer]
+    pub shadow_tiles: StorageBuffer<Vec<RawShadowTile>>,
+    pub atlas: ShadowAtlas,
+    pub view_proj_buffers: IdMap<ShadowTarget, UniformBuffer<Mat4>>,
     pub bindings_changed: bool,
 }
 
 impl FromWorld for PreparedShadows {
     fn from_world(world: &mut World) -> Self {
         let device = world.resource::<RenderDevice>();
+        let settings = world.get_resource::<ShadowSettings>();
+        let settings = settings.cloned().unwrap_or_default();
 
-        Self::new(device, 0)
+        Self::new(device, settings.atlas_size, settings.format)
     }
 }
 
 impl PreparedShadows {
-    pub const SHADOW_TEXTURE_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+    pub fn new(device: &Device, atlas_size: u32, format: TextureFormat) -> Self {
+        let atlas = ShadowAtlas::new(atlas_size);
 
-    pub fn new(device: &Device, cascade_count: u32) -> Self {
-        let cascade_count = u32::max(cascade_count, 4);
-
-        let cascade_texture = Self::create_cascade_texture(device, cascade_count);
-        let cascade_view = cascade_texture.create_view(&Default::default());
-
-        let mut cascade_view_proj_buffers = Vec::with_capacity(cascade_count as usize);
-        cascade_view_proj_buffers.resize_with(cascade_count as usize, Default::default);
-
-        let mut cascade_frustums = Vec::with_capacity(cascade_count as usize);
-        cascade_frustums.resize_with(cascade_count as usize, Default::default);
+        let atlas_texture = Self::create_atlas_texture(device, atlas.size(), format);
+        let atlas_view = atlas_texture.create_view(&Default::default());
 
         Self {
-            cascade_texture,
-            cascade_view,
-            cascade_view_proj_buffers,
-            cascade_frustums,
+            atlas_texture,
+            atlas_view,
+            shadow_tiles: StorageBuffer::default(),
+            atlas,
+            view_proj_buffers: IdMap::default(),
             bindings_changed: true,
         }
     }
 
-    pub fn create_cascade_texture(device: &Device, cascade_count: u32) -> SharedTexture {
+    pub fn create_atlas_texture(
+        device: &Device,
+        size: u32,
+        format: TextureFormat,
+    ) -> SharedTexture {
         device.create_shared_texture(&TextureDescriptor {
-            label: Some("Lumi Shadows Cascade Texture"),
+            label: Some("Lumi Shadow Atlas Texture"),
             size: Extent3d {
-                width: 2048,
-                height: 2048,
-                depth_or_array_layers: u32::max(cascade_count, 4),
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: Self::SHADOW_TEXTURE_FORMAT,
+            format,
             usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
         })
     }
 
+    /// Recreates the atlas if `settings` changed its size or format.
     #[inline]
-    pub fn resize_cascades(&mut self, device: &Device, cascade_count: u32) {
-        if self.cascade_texture.size().depth_or_array_layers < u32::max(cascade_count, 4) {
-            self.cascade_texture = Self::create_cascade_texture(device, cascade_count);
-            self.cascade_view = self.cascade_texture.create_view(&Default::default());
-            self.cascade_view_proj_buffers
-                .resize_with(cascade_count as usize, Default::default);
+    pub fn resize_atlas(&mut self, device: &Device, settings: &ShadowSettings) {
+        let size = settings.atlas_size.next_power_of_two();
+
+        if self.atlas.size() != size || self.atlas_texture.format() != settings.format {
+            self.atlas = ShadowAtlas::new(size);
+            self.atlas_texture = Self::create_atlas_texture(device, size, settings.format);
+            self.atlas_view = self.atlas_texture.create_view(&Default::default());
 
             self.bindings_changed = true;
         }
     }
 
     #[inline]
-    pub fn get_cascade_view(&self, index: u32) -> SharedTextureView {
-        self.cascade_texture.create_view(&TextureViewDescriptor {
-            label: Some("Lumi Shadows Cascade View"),
-            base_array_layer: index,
-            array_layer_count: NonZeroU32::new(1),
-            ..Default::default()
-        })
+    pub fn target_intersects_shape<T: BoundingShape>(
+        &self,
+        target: &ShadowTarget,
+        shape: &T,
+        transform: Mat4,
+    ) -> bool {
+        target.frustum.intersects_shape(shape, transform, true)
     }
+}
 
-    #[inline]
-    pub fn get_target_view(&self, target: &ShadowTarget) -> SharedTextureView {
-        match target.kind {
-            ShadowKind::Directional => self.get_cascade_view(target.index),
-        }
-    }
+/// A request for a tile in the shadow atlas, made by a light.
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowRequest {
+    pub kind: ShadowKind,
+    pub entity: Entity,
+    /// The index of the light in the [`PreparedLights`] of its kind.
+    pub light: u32,
+    /// The cascade or cube face of the light.
+    pub layer: u32,
+    pub view_proj: Mat4,
+    pub frustum: Frustum,
+    /// The cascade of a directional light, snapped to the tile it is allocated.
+    pub cascade: Option<DirectionalCascade>,
+    /// The largest resolution of the tile.
+    pub resolution: u32,
+    pub bias: ShadowBias,
+    /// The [`RenderLayers`] of meshes casting shadows.
+    pub render_layers: RenderLayers,
 }
 
 #[derive(Bind)]
@@ -123,9 +147,102 @@ pub struct ShadowCasterBindings {
     pub view_proj: SharedBuffer,
 }
 
+/// Global shadow quality settings, applied to the shadow maps of all lights.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// The width and height of the shadow atlas shared by all lights.
+    ///
+    /// This is the GPU memory budget for shadows, when full, shadow resolutions are lowered.
+    pub atlas_size: u32,
+    /// Scale applied to the shadow resolution of every light.
+    pub resolution_scale: f32,
+    /// The depth format of the shadow atlas.
+    ///
+    /// [`TextureFormat::Depth16Unorm`] halves memory usage and bandwidth at the cost of precision.
+    pub format: TextureFormat,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self::high()
+    }
+}
+
+impl ShadowSettings {
+    pub fn low() -> Self {
+        Self {
+            atlas_size: 2048,
+            resolution_scale: 0.5,
+            format: TextureFormat::Depth16Unorm,
+        }
+    }
+
+    pub fn medium() -> Self {
+        Self {
+            atlas_size: 4096,
+            resolution_scale: 0.5,
+            format: TextureFormat::Depth32Float,
+        }
+    }
+
+    pub fn high() -> Self {
+        Self {
+            atlas_size: 4096,
+            resolution_scale: 1.0,
+            format: TextureFormat::Depth32Float,
+        }
+    }
+
+    pub fn ultra() -> Self {
+        Self {
+            atlas_size: 8192,
+            resolution_scale: 2.0,
+            format: TextureFormat::Depth32Float,
+        }
+    }
+
+    /// Returns the scaled shadow map `resolution` of a light.
+    pub fn resolution(&self, resolution: u32) -> u32 {
+        let resolution = (resolution as f32 * self.resolution_scale) as u32;
+        resolution.clamp(1, self.atlas_size)
+    }
+}
+
+pub fn extract_shadow_settings_system(
+    mut commands: Commands,
+    settings: Extract<Option<Res<ShadowSettings>>>,
+) {
+    if let Some(settings) = settings.deref() {
+        if settings.is_changed() {
+            commands.insert_resource(settings.as_ref().clone());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShadowPipelineKey {
+    pub format: TextureFormat,
+    pub constant_bias: i32,
+    /// The bits of the slope bias, as `f32` doesn't implement `Hash`.
+    pub slope_bias: u32,
+}
+
+impl ShadowPipelineKey {
+    #[inline]
+    pub fn new(format: TextureFormat, bias: &ShadowBias) -> Self {
+        Self {
+            format,
+            constant_bias: bias.constant,
+            slope_bias: bias.slope.to_bits(),
+        }
+    }
+}
+
 pub struct ShadowPipeline {
     pub bindings_layout: BindingLayout,
-    pub render_pipeline: SharedRenderPipeline,
+    pub pipeline_layout: PipelineLayout,
+    pub vertex_shader: Shader,
+    pub render_pipelines: HashMap<ShadowPipelineKey, SharedRenderPipeline>,
 }
 
 impl FromWorld for ShadowPipeline {
@@ -148,11 +265,26 @@ impl FromWorld for ShadowPipeline {
         let device = world.resource::<RenderDevice>();
         let pipeline_layout = bindings_layout.create_pipeline_layout(device);
 
-        let render_pipeline = device.create_shared_render_pipeline(&RenderPipelineDescriptor {
+        Self {
+            bindings_layout,
+            pipeline_layout,
+            vertex_shader,
+            render_pipelines: HashMap::default(),
+        }
+    }
+}
+
+impl ShadowPipeline {
+    pub fn create_render_pipeline(
+        &mut self,
+        device: &Device,
+        key: &ShadowPipelineKey,
+    ) -> SharedRenderPipeline {
+        device.create_shared_render_pipeline(&RenderPipelineDescriptor {
             label: Some("Lumi Shadows RenderPipeline"),
-            layout: Some(&pipeline_layout),
+            layout: Some(&self.pipeline_layout),
             vertex: VertexState {
-                module: vertex_shader.shader_module(device),
+                module: self.vertex_shader.shader_module(device),
                 entry_point: "vertex",
                 buffers: &[VertexBufferLayout {
                     array_stride: 12,
@@ -163,39 +295,63 @@ impl FromWorld for ShadowPipeline {
             fragment: None,
             primitive: Default::default(),
             depth_stencil: Some(lumi_core::DepthStencilState {
-                format: PreparedShadows::SHADOW_TEXTURE_FORMAT,
+                format: key.format,
                 depth_write_enabled: true,
                 depth_compare: lumi_core::CompareFunction::LessEqual,
                 stencil: Default::default(),
-                bias: Default::default(),
+                bias: DepthBiasState {
+                    constant: key.constant_bias,
+                    slope_scale: f32::from_bits(key.slope_bias),
+                    clamp: 0.0,
+                },
             }),
             multisample: Default::default(),
             multiview: None,
-        });
+        })
+    }
 
-        Self {
-            bindings_layout,
-            render_pipeline,
+    /// Creates the render pipeline for `key`, if it doesn't exist yet.
+    #[inline]
+    pub fn prepare_render_pipeline(&mut self, device: &Device, key: ShadowPipelineKey) {
+        if !self.render_pipelines.contains_key(&key) {
+            let render_pipeline = self.create_render_pipeline(device, &key);
+            self.render_pipelines.insert(key, render_pipeline);
         }
     }
+
+    #[inline]
+    pub fn get_render_pipeline(&self, key: &ShadowPipelineKey) -> Option<&SharedRenderPipeline> {
+        self.render_pipelines.get(key)
+    }
 }
 
-#[derive(Clone, Copy, Debug, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ShadowKind {
     Directional,
+    Spot,
+    Point,
 }
 
-#[derive(Clone, Copy, Debug, Hash)]
+#[derive(Clone, Copy, Debug)]
 pub struct ShadowTarget {
     pub kind: ShadowKind,
     pub entity: Entity,
+    /// The cascade or cube face of the light.
+    pub layer: u32,
+    /// The index of the tile in [`PreparedShadows::shadow_tiles`].
     pub index: u32,
+    pub tile: ShadowTile,
+    pub frustum: Frustum,
+    pub pipeline: ShadowPipelineKey,
+    /// The [`RenderLayers`] of meshes casting shadows.
+    pub render_layers: RenderLayers,
 }
 
 impl ShadowTarget {
+    /// Returns an id that is stable across frames, as long as the light exists.
     #[inline]
     pub fn id(&self) -> Id<Self> {
-        Id::from_hash(self)
+        Id::from_hash((self.kind, self.entity, self.layer))
     }
 }
 
@@ -223,6 +379,9 @@ impl DerefMut for ShadowTargets {
 #[derive(Component, Default)]
 pub struct ShadowRenderState {
     pub bindings: IdMap<ShadowTarget, Binding>,
+    /// The view projection buffers `bindings` are bound to, targets get a new buffer whenever
+    /// they are reallocated.
+    pub view_proj_buffers: IdMap<ShadowTarget, SharedBuffer>,
     pub transform: Mat4,
 }
 
@@ -235,66 +394,163 @@ pub fn insert_state_system(
     }
 }
 
-pub fn extract_directional_shadow_system(
-    device: Res<RenderDevice>,
-    queue: Res<RenderQueue>,
-    shadow_pipeline: ResInit<ShadowPipeline>,
+pub fn clear_shadow_targets_system(
     mut prepared_shadows: ResMutInit<PreparedShadows>,
     mut shadow_targets: ResMut<ShadowTargets>,
-    prepared_lights: Res<PreparedLights>,
-    light_query: Extract<Query<(Entity, &DirectionalLight, Option<&GlobalTransform>)>>,
-    mut prepared_query: Query<
-        (&PreparedTransform, &mut ShadowRenderState),
-        Changed<PreparedTransform>,
-    >,
 ) {
     shadow_targets.clear();
 
     prepared_shadows.bindings_changed = false;
-    prepared_shadows.resize_cascades(&device, prepared_lights.next_cascade_index);
+}
 
-    let mut cascade_index = 0;
-    for (entity, light, transform) in light_query.iter() {
-        if !light.shadows {
-            continue;
-        }
+/// Allocates a tile in `atlas` for each of `requests`.
+///
+/// The resolutions of all requests are scaled down together until they fit into the atlas,
+/// requests that still don't get a tile are `None`.
+pub fn allocate_shadow_tiles(
+    atlas: &mut ShadowAtlas,
+    requests: &[ShadowRequest],
+) -> Vec<Option<ShadowTile>> {
+    let mut resolutions = requests
+        .iter()
+        .map(|request| request.resolution)
+        .collect::<Vec<_>>();
+    atlas.fit(&mut resolutions);
+
+    // allocate the largest tiles first, to pack the atlas without gaps
+    let mut order = (0..requests.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| std::cmp::Reverse(resolutions[index]));
+
+    atlas.clear();
+
+    let mut tiles = vec![None; requests.len()];
+    for index in order {
+        tiles[index] = atlas.allocate(resolutions[index]);
+    }
 
-        let transform = transform.copied().unwrap_or_default();
-        let mut light = light.clone();
+    tiles
+}
 
-        light.direction = transform.matrix.mul_vec3(light.direction);
-        light.direction = light.direction.normalize_or_zero();
+pub fn extract_shadow_system(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut shadow_pipeline: ResMutInit<ShadowPipeline>,
+    shadow_settings: Option<Res<ShadowSettings>>,
+    mut prepared_shadows: ResMutInit<PreparedShadows>,
+    mut shadow_targets: ResMut<ShadowTargets>,
+    mut prepared_lights: ResMut<PreparedLights>,
+    mut prepared_query: Query<(Entity, &PreparedTransform, &mut ShadowRenderState)>,
+    changed_query: Query<Entity, Changed<PreparedTransform>>,
+) {
+    let shadow_settings = shadow_settings.as_deref().cloned().unwrap_or_default();
+    prepared_shadows.resize_atlas(&device, &shadow_settings);
+
+    let prepared_lights = &mut *prepared_lights;
+    let requests = &prepared_lights.shadow_requests;
+    let tiles_cap = prepared_shadows.shadow_tiles.capacity();
+
+    prepared_shadows.shadow_tiles.clear();
+    prepared_shadows
+        .shadow_tiles
+        .resize(requests.len(), RawShadowTile::default());
+
+    let tiles = allocate_shadow_tiles(&mut prepared_shadows.atlas, requests);
+
+    // lights missing any of their tiles are rendered without shadows, instead of sampling an
+    // empty tile
+    let unshadowed = (requests.iter().zip(&tiles))
+        .filter(|(_, tile)| tile.is_none())
+        .map(|(request, _)| (request.kind, request.light))
+        .collect::<HashSet<_>>();
+
+    for &(kind, light) in unshadowed.iter() {
+        let light = light as usize;
+
+        match kind {
+            ShadowKind::Point => prepared_lights.point_lights[light].shadow_index = -1,
+            ShadowKind::Spot => prepared_lights.spot_lights[light].shadow_index = -1,
+            ShadowKind::Directional => prepared_lights.directional_lights[light].cascade_count = 0,
+        }
+    }
 
-        for cascade in 0..DirectionalLight::CASCADES {
-            let target = ShadowTarget {
-                kind: ShadowKind::Directional,
-                entity,
-                index: cascade_index,
-            };
+    for (index, (request, tile)) in requests.iter().zip(tiles).enumerate() {
+        let tile = match tile {
+            Some(tile) if !unshadowed.contains(&(request.kind, request.light)) => tile,
+            _ => continue,
+        };
 
-            shadow_targets.push(target);
+        let mut view_proj = request.view_proj;
+        let mut frustum = request.frustum;
 
-            let frustum = light.cascade_frustum(cascade);
-            prepared_shadows.cascade_frustums[cascade_index as usize] = frustum;
+        // cascades are snapped to the tile they got, which may be smaller than requested
+        if let Some(mut cascade) = request.cascade {
+            cascade.snap(tile.size);
 
-            let view_proj = &mut prepared_shadows.cascade_view_proj_buffers[cascade_index as usize];
-            view_proj.set(light.view_proj(cascade));
+            view_proj = cascade.view_proj();
+            frustum = cascade.frustum();
+        }
 
-            let caster_bindings = ShadowCasterBindings {
-                view_proj: view_proj.buffer(&device, &queue),
-            };
+        let atlas_size = prepared_shadows.atlas.size();
+        prepared_shadows.shadow_tiles[index] = RawShadowTile {
+            view_proj,
+            rect: tile.uv_rect(atlas_size),
+        };
+
+        let pipeline = ShadowPipelineKey::new(shadow_settings.format, &request.bias);
+        shadow_pipeline.prepare_render_pipeline(&device, pipeline);
+
+        let target = ShadowTarget {
+            kind: request.kind,
+            entity: request.entity,
+            layer: request.layer,
+            index: index as u32,
+            tile,
+            frustum,
+            pipeline,
+            render_layers: request.render_layers,
+        };
+
+        shadow_targets.push(target);
+
+        let view_proj_buffer = prepared_shadows
+            .view_proj_buffers
+            .get_or_insert_with(target.id(), Default::default);
+        view_proj_buffer.set(view_proj);
+
+        let caster_bindings = ShadowCasterBindings {
+            view_proj: view_proj_buffer.buffer(&device, &queue),
+        };
+
+        prepare_target(
+            &device,
+            &queue,
+            &caster_bindings,
+            &shadow_pipeline,
+            target,
+            &mut prepared_query,
+            &changed_query,
+        );
+    }
 
-            prepare_target(
-                &device,
-                &queue,
-                &caster_bindings,
-                &shadow_pipeline,
-                target,
-                &mut prepared_query,
-            );
+    // remove buffers and bindings of targets that weren't allocated this frame, casters are
+    // rebound to the new buffer when the target is allocated again
+    let target_ids = shadow_targets
+        .iter()
+        .map(ShadowTarget::id)
+        .collect::<HashSet<_>>();
+    prepared_shadows
+        .view_proj_buffers
+        .retain(|id, _| target_ids.contains(id));
+
+    for (_, _, mut state) in prepared_query.iter_mut() {
+        state.bindings.retain(|id, _| target_ids.contains(id));
+        state
+            .view_proj_buffers
+            .retain(|id, _| target_ids.contains(id));
+    }
 
-            cascade_index += 1;
-        }
+    if prepared_shadows.shadow_tiles.capacity() != tiles_cap {
+        prepared_shadows.bindings_changed = true;
     }
 }
 
@@ -304,14 +560,23 @@ fn prepare_target(
     caster_bindings: &ShadowCasterBindings,
     shadow_pipeline: &ShadowPipeline,
     target: ShadowTarget,
-    prepared_query: &mut Query<
-        (&PreparedTransform, &mut ShadowRenderState),
-        Changed<PreparedTransform>,
-    >,
+    prepared_query: &mut Query<(Entity, &PreparedTransform, &mut ShadowRenderState)>,
+    changed_query: &Query<Entity, Changed<PreparedTransform>>,
 ) {
     let target_id = target.id();
 
-    for (transform, mut state) in prepared_query.iter_mut() {
+    for (entity, transform, mut state) in prepared_query.iter_mut() {
+        let bound_buffer = state.view_proj_buffers.get(target_id);
+        let buffer_changed = bound_buffer != Some(&caster_bindings.view_proj);
+
+        if !buffer_changed && !changed_query.contains(entity) {
+            continue;
+        }
+
+        state
+            .view_proj_buffers
+            .insert(target_id, caster_bindings.view_proj.clone());
+
         let bindings = state.bindings.get_or_insert_with(target_id, || {
             shadow_pipeline.bindings_layout.create_bindings(&device)
         });
@@ -331,30 +596,45 @@ pub fn render_shadow_system(
     prepared_shadows: Res<PreparedShadows>,
     shadow_pipeline: Res<ShadowPipeline>,
     shadow_targets: Res<ShadowTargets>,
-    render_query: Query<(&ExtractedMeshes, &ShadowRenderState)>,
+    render_query: Query<(&ExtractedMeshes, &PreparedTransform, &ShadowRenderState)>,
 ) {
+    // all targets render to the same atlas, so it must only be cleared once
+    let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Lumi Shadow Pass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+            view: &prepared_shadows.atlas_view,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    });
+
     for target in shadow_targets.iter() {
         let target_id = target.id();
-        let target_view = prepared_shadows.get_target_view(target);
-
-        let frustum = prepared_shadows.cascade_frustums[target.index as usize];
-
-        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-            label: Some("Lumi Shadow Pass"),
-            color_attachments: &[],
-            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                view: &target_view,
-                depth_ops: Some(Operations {
-                    load: LoadOp::Clear(1.0),
-                    store: true,
-                }),
-                stencil_ops: None,
-            }),
-        });
 
-        render_pass.set_pipeline(&shadow_pipeline.render_pipeline);
+        let render_pipeline = shadow_pipeline
+            .get_render_pipeline(&target.pipeline)
+            .unwrap();
+        render_pass.set_pipeline(render_pipeline);
+
+        let tile = target.tile;
+        render_pass.set_viewport(
+            tile.x as f32,
+            tile.y as f32,
+            tile.size as f32,
+            tile.size as f32,
+            0.0,
+            1.0,
+        );
+
+        for (meshes, transform, state) in render_query.iter() {
+            if !target.render_layers.intersects(&transform.render_layers) {
+                continue;
+            }
 
-        for (meshes, state) in render_query.iter() {
             let bindings = if let Some(state) = state.bindings.get(target_id) {
                 state
             } else {
@@ -365,7 +645,7 @@ pub fn render_shadow_system(
                 let prepared_mesh = prepared_meshes.get(mesh_id).unwrap();
 
                 if let Some(aabb) = prepared_mesh.aabb {
-                    if !frustum.intersects_shape(&aabb, state.transform) {
+                    if !prepared_shadows.target_intersects_shape(target, &aabb, state.transform) {
                         continue;
                     }
                 }
@@ -385,3 +665,82 @@ pub fn render_shadow_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_resolution() {
+        assert_eq!(ShadowSettings::high().resolution(1024), 1024);
+        assert_eq!(ShadowSettings::low().resolution(1024), 512);
+
+        // resolutions are clamped to the atlas
+        assert_eq!(ShadowSettings::ultra().resolution(8192), 8192);
+        assert_eq!(ShadowSettings::low().resolution(0), 1);
+    }
+
+    fn request(kind: ShadowKind, layer: u32, resolution: u32) -> ShadowRequest {
+        ShadowRequest {
+            kind,
+            entity: Entity::from_raw_parts(0, 0),
+            light: 0,
+            layer,
+            view_proj: Mat4::IDENTITY,
+            frustum: Frustum::default(),
+            cascade: None,
+            resolution,
+            bias: ShadowBias::default(),
+            render_layers: RenderLayers::default(),
+        }
+    }
+
+    #[test]
+    fn test_allocate_directional_and_spot() {
+        let settings = ShadowSettings::default();
+        let directional = crate::DirectionalLight::default();
+        let spot = crate::SpotLight::default();
+
+        let mut requests = (0..directional.cascades)
+            .map(|cascade| {
+                let resolution = settings.resolution(directional.shadow_resolution);
+                request(ShadowKind::Directional, cascade, resolution)
+            })
+            .collect::<Vec<_>>();
+        let resolution = settings.resolution(spot.shadow_resolution);
+        requests.push(request(ShadowKind::Spot, 0, resolution));
+
+        let mut atlas = ShadowAtlas::new(settings.atlas_size);
+        let tiles = allocate_shadow_tiles(&mut atlas, &requests);
+
+        // the cascades alone would fill the atlas, so every request gets a smaller tile
+        let tiles = tiles.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        assert!(tiles[..4].iter().all(|tile| tile.size == 1024));
+        assert_eq!(tiles[4].size, 512);
+
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                let overlaps = a.x < b.x + b.size
+                    && b.x < a.x + a.size
+                    && a.y < b.y + b.size
+                    && b.y < a.y + a.size;
+
+                assert!(!overlaps, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_allocate_full_atlas() {
+        let mut atlas = ShadowAtlas::new(64);
+
+        // more minimum sized tiles than fit into the atlas
+        let requests = (0..6)
+            .map(|face| request(ShadowKind::Point, face, 1024))
+            .collect::<Vec<_>>();
+        let tiles = allocate_shadow_tiles(&mut atlas, &requests);
+
+        assert_eq!(tiles.iter().filter(|tile| tile.is_some()).count(), 4);
+        assert!(tiles.iter().flatten().all(|tile| tile.size != 0));
+    }
+}
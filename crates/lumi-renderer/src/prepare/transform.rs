@@ -1,5 +1,5 @@
 use lumi_bind::Bind;
-use lumi_core::{BufferInitDescriptor, BufferUsages, SharedBuffer, SharedDevice};
+use lumi_core::{BufferInitDescriptor, BufferUsages, Device, SharedBuffer, SharedDevice};
 use lumi_util::{bytemuck, math::Mat4};
 
 use shiv::{
@@ -9,13 +9,40 @@ use shiv::{
 };
 use shiv_transform::GlobalTransform;
 
-use crate::{Extract, RenderDevice, RenderQueue};
+use crate::{Extract, RenderDevice, RenderLayers, RenderQueue};
 
+/// The transform and [`RenderLayers`] of an entity.
 #[derive(Component, Debug, Bind)]
 pub struct PreparedTransform {
     #[uniform(name = "transform")]
     pub transform_buffer: SharedBuffer,
     pub transform: Mat4,
+    #[uniform(name = "render_layers")]
+    pub render_layers_buffer: SharedBuffer,
+    pub render_layers: RenderLayers,
+}
+
+impl PreparedTransform {
+    pub fn new(device: &Device, transform: Mat4, render_layers: RenderLayers) -> Self {
+        let transform_buffer = device.create_shared_buffer_init(&BufferInitDescriptor {
+            label: Some("Lumi Transform Buffer"),
+            contents: bytemuck::bytes_of(&transform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let render_layers_buffer = device.create_shared_buffer_init(&BufferInitDescriptor {
+            label: Some("Lumi Render Layers Buffer"),
+            contents: bytemuck::bytes_of(&render_layers.bits()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        Self {
+            transform_buffer,
+            transform,
+            render_layers_buffer,
+            render_layers,
+        }
+    }
 }
 
 pub fn extract_transform_system(
@@ -24,35 +51,31 @@ pub fn extract_transform_system(
     queue: Res<RenderQueue>,
     transform_query: Extract<Query<(Entity, &GlobalTransform), Changed<GlobalTransform>>>,
     no_transform_query: Extract<Query<Entity, Without<GlobalTransform>>>,
-    mut prepared_query: Query<&mut PreparedTransform>,
+    render_layers_query: Extract<Query<&RenderLayers>>,
+    mut prepared_query: Query<(Entity, &mut PreparedTransform)>,
 ) {
+    let render_layers = |entity: Entity| {
+        let render_layers = render_layers_query.get(entity);
+        render_layers.copied().unwrap_or_default()
+    };
+
     for (entity, transform) in transform_query.iter() {
         let matrix = transform.compute_matrix();
 
-        if let Some(mut prepared) = prepared_query.get_mut(entity) {
+        if let Some((_, mut prepared)) = prepared_query.get_mut(entity) {
             if prepared.transform != matrix {
                 queue.write_buffer(&prepared.transform_buffer, 0, bytemuck::bytes_of(&matrix));
 
                 prepared.transform = matrix;
             }
         } else {
-            let buffer = device.create_shared_buffer_init(&BufferInitDescriptor {
-                label: Some("Lumi Transform Buffer"),
-                contents: bytemuck::bytes_of(&matrix),
-                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            });
-
-            let prepared = PreparedTransform {
-                transform_buffer: buffer,
-                transform: matrix,
-            };
-
+            let prepared = PreparedTransform::new(&device, matrix, render_layers(entity));
             commands.entity(entity).insert(prepared);
         }
     }
 
     for entity in no_transform_query.iter() {
-        if let Some(mut prepared) = prepared_query.get_mut(entity) {
+        if let Some((_, mut prepared)) = prepared_query.get_mut(entity) {
             if prepared.transform != Mat4::IDENTITY {
                 queue.write_buffer(
                     &prepared.transform_buffer,
@@ -63,18 +86,20 @@ pub fn extract_transform_system(
                 prepared.transform = Mat4::IDENTITY;
             }
         } else {
-            let buffer = device.create_shared_buffer_init(&BufferInitDescriptor {
-                label: Some("Lumi Transform Buffer"),
-                contents: bytemuck::bytes_of(&Mat4::IDENTITY),
-                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            });
+            let prepared = PreparedTransform::new(&device, Mat4::IDENTITY, render_layers(entity));
+            commands.entity(entity).insert(prepared);
+        }
+    }
 
-            let prepared = PreparedTransform {
-                transform_buffer: buffer,
-                transform: Mat4::IDENTITY,
-            };
+    // compare every entity, as removing `RenderLayers` isn't tracked by change detection
+    for (entity, mut prepared) in prepared_query.iter_mut() {
+        let render_layers = render_layers(entity);
 
-            commands.entity(entity).insert(prepared);
+        if prepared.render_layers != render_layers {
+            let bits = render_layers.bits();
+            queue.write_buffer(&prepared.render_layers_buffer, 0, bytemuck::bytes_of(&bits));
+
+            prepared.render_layers = render_layers;
         }
     }
 }
@@ -0,0 +1,147 @@
+use lumi_bake::BakedEnvironment;
+use lumi_core::{Device, Queue};
+use lumi_util::math::Vec3;
+
+use shiv::{bundle::Bundle, world::Component};
+use shiv_transform::{GlobalTransform, Transform};
+
+use crate::{PreparedReflectionProbes, PreparedTransform, RawProbe, Renderer};
+
+/// The volume a [`ReflectionProbe`] affects, in the local space of the probe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProbeVolume {
+    /// A box with half extents along each local axis.
+    Box {
+        half_extents: Vec3,
+    },
+    Sphere {
+        radius: f32,
+    },
+}
+
+impl Default for ProbeVolume {
+    fn default() -> Self {
+        Self::Box {
+            half_extents: Vec3::splat(5.0),
+        }
+    }
+}
+
+impl ProbeVolume {
+    /// Returns the volume in cubic meters, used to prefer smaller probes where probes overlap.
+    pub fn volume(&self) -> f32 {
+        match *self {
+            Self::Box { half_extents } => half_extents.x * half_extents.y * half_extents.z * 8.0,
+            Self::Sphere { radius } => radius * radius * radius * 4.0 / 3.0 * std::f32::consts::PI,
+        }
+    }
+}
+
+/// A local source of specular image based lighting.
+///
+/// The scene is captured into a cubemap from the position of the probe when
+/// [`Renderer::capture_reflection_probes`](crate::Renderer::capture_reflection_probes) is called,
+/// after the probe was added or changed. Reflections of surfaces inside the volume are parallax
+/// corrected against the volume, and blended with the environment near its edges.
+#[derive(Component, Clone, Debug)]
+pub struct ReflectionProbe {
+    pub volume: ProbeVolume,
+    /// The distance in meters from the edge of the volume, over which the probe fades in.
+    pub blend_distance: f32,
+    /// The resolution of each captured cube face.
+    pub resolution: u32,
+    /// The near plane used when capturing.
+    pub near: f32,
+    pub intensity: f32,
+}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        Self {
+            volume: ProbeVolume::default(),
+            blend_distance: 0.5,
+            resolution: 256,
+            near: 0.05,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl ReflectionProbe {
+    /// The size of the prefiltered cubemap of each probe.
+    pub const INDIRECT_SIZE: u32 = 128;
+
+    pub fn raw(&self, transform: &GlobalTransform, exposure: f32, layer: i32) -> RawProbe {
+        let (shape, half_extents, radius) = match self.volume {
+            ProbeVolume::Box { half_extents } => (0, half_extents, 0.0),
+            ProbeVolume::Sphere { radius } => (1, Vec3::ZERO, radius),
+        };
+
+        RawProbe {
+            probe_from_world: transform.compute_matrix().inverse(),
+            position: transform.translation,
+            shape,
+            half_extents,
+            radius,
+            resolution: Vec3::ZERO,
+            blend_distance: f32::max(self.blend_distance, 0.0001),
+            intensity: self.intensity / exposure,
+            layer,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle)]
+pub struct ReflectionProbeBundle {
+    pub probe: ReflectionProbe,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl Renderer {
+    /// Captures the [`ReflectionProbe`]s that were added or changed since they were last
+    /// captured.
+    ///
+    /// This should be called after [`Renderer::extract`] and before rendering any cameras, the
+    /// captures are used from the next extract.
+    pub fn capture_reflection_probes(&mut self, device: &Device, queue: &Queue) {
+        let pending = match self.world.get_resource_mut::<PreparedReflectionProbes>() {
+            Some(mut prepared) => std::mem::take(&mut prepared.pending),
+            None => return,
+        };
+
+        for entity in pending {
+            let probe = match self.world.get::<ReflectionProbe>(entity) {
+                Some(probe) => probe.clone(),
+                None => continue,
+            };
+
+            let position = match self.world.get::<PreparedTransform>(entity) {
+                Some(prepared) => prepared.transform.w_axis.truncate(),
+                None => continue,
+            };
+
+            let capture = self.capture_cube(
+                device,
+                queue,
+                entity,
+                position,
+                probe.resolution,
+                probe.near,
+            );
+
+            let baked = BakedEnvironment::from_faces(
+                device,
+                queue,
+                &capture.faces,
+                capture.size,
+                ReflectionProbe::INDIRECT_SIZE,
+                32,
+                32,
+            );
+
+            let mut prepared = self.world.resource_mut::<PreparedReflectionProbes>();
+            prepared.insert_capture(entity, baked, capture.exposure);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use shiv::world::Component;
+
+/// A bitmask of the layers an entity is visible on.
+///
+/// Cameras only draw meshes sharing a layer with them, and lights only illuminate and cast shadows
+/// from meshes sharing a layer with them. Entities without [`RenderLayers`] are on layer `0`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl Default for RenderLayers {
+    #[inline]
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+impl RenderLayers {
+    /// The number of available layers.
+    pub const COUNT: u32 = u32::BITS;
+    pub const ALL: Self = Self(u32::MAX);
+    pub const NONE: Self = Self(0);
+
+    /// Returns the mask containing only `layer`.
+    ///
+    /// # Panics
+    /// - If `layer` is not less than [`Self::COUNT`].
+    #[inline]
+    pub const fn layer(layer: u32) -> Self {
+        assert!(layer < Self::COUNT, "render layer out of range");
+        Self(1 << layer)
+    }
+
+    /// Returns the mask with `layer` added.
+    #[inline]
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | Self::layer(layer).0)
+    }
+
+    /// Returns the mask with `layer` removed.
+    #[inline]
+    pub const fn without(self, layer: u32) -> Self {
+        Self(self.0 & !Self::layer(layer).0)
+    }
+
+    #[inline]
+    pub const fn contains(&self, layer: u32) -> bool {
+        self.0 & Self::layer(layer).0 != 0
+    }
+
+    /// Returns true if `self` and `other` share any layer.
+    #[inline]
+    pub const fn intersects(&self, other: &Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_layers() {
+        let layers = RenderLayers::default().with(3).with(31);
+
+        assert!(layers.contains(0));
+        assert!(layers.contains(3));
+        assert!(layers.contains(31));
+        assert!(!layers.contains(1));
+        assert_eq!(layers.bits(), 1 | 1 << 3 | 1 << 31);
+
+        let layers = layers.without(0).without(1);
+        assert!(!layers.contains(0));
+        assert_eq!(layers, RenderLayers::layer(3).with(31));
+    }
+
+    #[test]
+    fn test_intersects() {
+        let layers = RenderLayers::layer(1).with(2);
+
+        assert!(layers.intersects(&RenderLayers::layer(2)));
+        assert!(layers.intersects(&RenderLayers::ALL));
+        assert!(!layers.intersects(&RenderLayers::default()));
+        assert!(!layers.intersects(&RenderLayers::NONE));
+        assert!(!RenderLayers::NONE.intersects(&RenderLayers::ALL));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_layer_out_of_range() {
+        RenderLayers::layer(RenderLayers::COUNT);
+    }
+}
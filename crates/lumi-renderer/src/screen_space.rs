@@ -1,35 +1,82 @@
 use lumi_bind::Bind;
-use lumi_core::{CommandEncoder, Device, Extent3d, SharedTextureView};
+use lumi_core::{CommandEncoder, Device, Extent3d, SharedTextureView, UniformBuffer};
+use lumi_macro::ShaderType;
+use lumi_util::math::Mat4;
 use shiv::{
     query::Query,
     system::{Commands, Res, ResInit, ResMut},
     world::Component,
 };
 
-use crate::{MipChain, MipChainPipeline, RenderDevice, RenderQueue, TransparentDraws, View};
+use crate::{
+    AmbientOcclusionTarget, GlobalIlluminationSettings, GlobalIlluminationTarget, HiZTarget,
+    MipChain, MipChainPipeline, PreparedCamera, RawScreenSpaceReflection, RenderDevice,
+    RenderQueue, ScreenSpaceReflectionSettings, TransparentDraws, View,
+};
+
+/// The settings of the screen space effects and the camera the mip chain was last rendered with,
+/// packed in one uniform.
+#[derive(Clone, Copy, Debug, PartialEq, ShaderType)]
+pub struct RawScreenSpace {
+    pub reflection: RawScreenSpaceReflection,
+    pub previous_view_proj: Mat4,
+    pub previous_exposure: f32,
+}
 
 #[derive(Clone, Bind)]
-pub struct ScreenSpaceBindings {
+pub struct ScreenSpaceBindings<'a> {
+    #[uniform]
+    pub screen_space: &'a UniformBuffer<RawScreenSpace>,
     #[texture]
     #[sampler(name = "ssr_sampler")]
     pub ssr_texture: SharedTextureView,
+    #[texture(sample_type = float)]
+    pub hi_z_texture: SharedTextureView,
+    #[texture]
+    pub ao_texture: SharedTextureView,
+    #[texture]
+    pub gi_texture: SharedTextureView,
 }
 
 #[derive(Component)]
 pub struct ScreenSpaceTarget {
     pub mip_chain: MipChain,
+    pub ambient_occlusion: AmbientOcclusionTarget,
+    pub global_illumination: GlobalIlluminationTarget,
+    pub hi_z: HiZTarget,
+    pub screen_space: UniformBuffer<RawScreenSpace>,
 }
 
 impl ScreenSpaceTarget {
     pub fn new(device: &Device, pipeline: &MipChainPipeline, size: Extent3d) -> Self {
         let mip_chain = MipChain::new(device, &pipeline.down_layout, size.width, size.height, None);
 
-        Self { mip_chain }
+        let ambient_occlusion = AmbientOcclusionTarget::new(device, size.width, size.height);
+        let global_illumination = GlobalIlluminationTarget::new(device, size.width, size.height);
+        let hi_z = HiZTarget::new(device, size.width, size.height);
+
+        let screen_space = RawScreenSpace {
+            reflection: ScreenSpaceReflectionSettings::default().raw(),
+            previous_view_proj: Mat4::IDENTITY,
+            previous_exposure: 1.0,
+        };
+
+        Self {
+            mip_chain,
+            ambient_occlusion,
+            global_illumination,
+            hi_z,
+            screen_space: UniformBuffer::new(screen_space),
+        }
     }
 
     pub fn bindings(&self) -> ScreenSpaceBindings {
         ScreenSpaceBindings {
+            screen_space: &self.screen_space,
             ssr_texture: self.mip_chain.view.clone(),
+            hi_z_texture: self.hi_z.view.clone(),
+            ao_texture: self.ambient_occlusion.view.clone(),
+            gi_texture: self.global_illumination.view.clone(),
         }
     }
 }
@@ -39,16 +86,36 @@ pub fn screen_space_resize_system(
     device: Res<RenderDevice>,
     view: Res<View>,
     pipeline: ResInit<MipChainPipeline>,
+    ssr_settings: Option<Res<ScreenSpaceReflectionSettings>>,
     mut query: Query<&mut ScreenSpaceTarget>,
 ) {
+    let ssr_settings = ssr_settings.as_deref().cloned().unwrap_or_default();
+
     if let Some(mut target) = query.get_mut(view.camera) {
         let size = view.frame_buffer.size();
 
         if target.mip_chain.size() != size {
             (target.mip_chain).resize(&device, size.width, size.height, None);
         }
+
+        if target.ambient_occlusion.size() != size {
+            (target.ambient_occlusion).resize(&device, size.width, size.height);
+        }
+
+        if target.global_illumination.size() != size {
+            (target.global_illumination).resize(&device, size.width, size.height);
+        }
+
+        if target.hi_z.size() != size {
+            (target.hi_z).resize(&device, size.width, size.height);
+        }
+
+        if target.screen_space.reflection != ssr_settings.raw() {
+            target.screen_space.reflection = ssr_settings.raw();
+        }
     } else {
-        let target = ScreenSpaceTarget::new(&device, &pipeline, view.frame_buffer.size());
+        let mut target = ScreenSpaceTarget::new(&device, &pipeline, view.frame_buffer.size());
+        target.screen_space.reflection = ssr_settings.raw();
         commands.entity(view.camera).insert(target);
     }
 }
@@ -60,17 +127,27 @@ pub fn screen_space_render_system(
     pipeline: ResInit<MipChainPipeline>,
     view: Res<View>,
     transparent_draws: Res<TransparentDraws>,
-    mut query: Query<&mut ScreenSpaceTarget>,
+    gi_settings: Option<Res<GlobalIlluminationSettings>>,
+    ssr_settings: Option<Res<ScreenSpaceReflectionSettings>>,
+    mut query: Query<(&PreparedCamera, &mut ScreenSpaceTarget)>,
 ) {
-    if transparent_draws.is_empty() {
+    // global illumination and reflections gather light from the mip chain of the previous frame
+    let gi_enabled = gi_settings.map_or(false, |settings| settings.enabled);
+    let ssr_enabled = ssr_settings.map_or(false, |settings| settings.enabled);
+
+    if transparent_draws.is_empty() && !gi_enabled && !ssr_enabled {
         return;
     }
 
     // ScreenSpaceTarget was inserted in screen_space_resize_system
-    let mut target = query.get_mut(view.camera).unwrap();
+    let (camera, mut target) = query.get_mut(view.camera).unwrap();
 
     target
         .mip_chain
         .prepare_downsample_bindings(&device, &queue, &view.frame_buffer.hdr_view, 4.0);
     target.mip_chain.downsample(&pipeline, &mut encoder);
+
+    target.screen_space.previous_view_proj = camera.camera.view_proj;
+    target.screen_space.previous_exposure = camera.camera.exposure;
+    target.screen_space.buffer(&device, &queue);
 }
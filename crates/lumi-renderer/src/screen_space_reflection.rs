@@ -0,0 +1,400 @@
+use std::{num::NonZeroU32, ops::Deref};
+
+use lumi_bind::{Bind, BindingLayout, Bindings};
+use lumi_core::{
+    BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d,
+    FragmentState, LoadOp, Operations, Queue, RenderPass, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, SharedDevice, SharedRenderPipeline,
+    SharedTexture, SharedTextureView, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor, VertexState,
+};
+use lumi_macro::ShaderType;
+use lumi_shader::{Shader, ShaderDefs, ShaderProcessor, ShaderRef};
+use shiv::{
+    system::{Commands, Res},
+    world::{FromWorld, World},
+};
+
+use crate::{Extract, RenderDevice};
+
+/// Screen space reflections of opaque surfaces.
+///
+/// Reflection rays are traced against a hierarchical depth buffer and gather light from the
+/// previous frame, rays that miss fall back to the environment.
+#[derive(Clone, Debug)]
+pub struct ScreenSpaceReflectionSettings {
+    pub enabled: bool,
+    /// The maximum number of steps through the depth hierarchy.
+    pub max_steps: u32,
+    /// The assumed thickness in meters of surfaces in the depth buffer.
+    pub thickness: f32,
+    /// The maximum distance in meters a ray is traced.
+    pub max_distance: f32,
+    /// Surfaces rougher than this don't trace reflections, reflections fade out towards it.
+    pub max_roughness: f32,
+}
+
+impl Default for ScreenSpaceReflectionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_steps: 64,
+            thickness: 0.5,
+            max_distance: 50.0,
+            max_roughness: 0.6,
+        }
+    }
+}
+
+impl ScreenSpaceReflectionSettings {
+    #[inline]
+    pub fn raw(&self) -> RawScreenSpaceReflection {
+        RawScreenSpaceReflection {
+            enabled: self.enabled as u32,
+            max_steps: self.max_steps,
+            thickness: self.thickness,
+            max_distance: self.max_distance,
+            max_roughness: self.max_roughness,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ShaderType)]
+pub struct RawScreenSpaceReflection {
+    pub enabled: u32,
+    pub max_steps: u32,
+    pub thickness: f32,
+    pub max_distance: f32,
+    pub max_roughness: f32,
+}
+
+pub fn extract_screen_space_reflection_settings_system(
+    mut commands: Commands,
+    settings: Extract<Option<Res<ScreenSpaceReflectionSettings>>>,
+) {
+    if let Some(settings) = settings.deref() {
+        if settings.is_changed() {
+            commands.insert_resource(settings.as_ref().clone());
+        }
+    }
+}
+
+/// A hierarchical depth buffer, where every mip stores the closest depth of the texels it
+/// covers in the mip above it.
+pub struct HiZTarget {
+    pub texture: SharedTexture,
+    pub view: SharedTextureView,
+    pub views: Vec<SharedTextureView>,
+}
+
+impl HiZTarget {
+    pub const FORMAT: TextureFormat = TextureFormat::R32Float;
+
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let mip_levels = Self::mip_levels_for_size(width, height);
+
+        let texture = device.create_shared_texture(&TextureDescriptor {
+            label: Some("Lumi Hi-Z texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: mip_levels,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        });
+
+        let views = (0..mip_levels)
+            .map(|mip_level| {
+                texture.create_view(&TextureViewDescriptor {
+                    label: Some("Lumi Hi-Z view"),
+                    base_mip_level: mip_level,
+                    mip_level_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        Self {
+            view: texture.create_view(&Default::default()),
+            texture,
+            views,
+        }
+    }
+
+    pub fn mip_levels_for_size(width: u32, height: u32) -> u32 {
+        32 - u32::max(width, height).leading_zeros()
+    }
+
+    pub fn size(&self) -> Extent3d {
+        self.texture.size()
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let size = self.size();
+
+        if size.width != width || size.height != height {
+            *self = Self::new(device, width, height);
+        }
+    }
+
+    fn begin_render_pass<'a>(
+        &'a self,
+        encoder: &'a mut CommandEncoder,
+        mip_level: usize,
+    ) -> RenderPass<'a> {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Lumi Hi-Z Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.views[mip_level],
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// Copies the depth bound in `bindings` to the first mip and reduces it down the mips.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &HiZPipeline,
+        bindings: &HiZBindings,
+    ) {
+        let copy_pipeline = if bindings.multisampled {
+            &pipeline.multisampled_copy_pipeline
+        } else {
+            &pipeline.copy_pipeline
+        };
+
+        let mut copy_pass = self.begin_render_pass(encoder, 0);
+
+        copy_pass.set_pipeline(copy_pipeline);
+        bindings.copy.apply(&mut copy_pass);
+        copy_pass.draw(0..3, 0..1);
+
+        drop(copy_pass);
+
+        for (i, bindings) in bindings.downsample.iter().enumerate() {
+            let mut downsample_pass = self.begin_render_pass(encoder, i + 1);
+
+            downsample_pass.set_pipeline(&pipeline.downsample_pipeline);
+            bindings.apply(&mut downsample_pass);
+            downsample_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[derive(Bind)]
+struct DepthBindings<'a> {
+    #[texture(sample_type = depth)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct MultisampledDepthBindings<'a> {
+    #[texture(sample_type = depth, multisampled = true)]
+    depth_texture: &'a SharedTextureView,
+}
+
+#[derive(Bind)]
+struct HiZDownsampleBindings<'a> {
+    #[texture(sample_type = float)]
+    hi_z_source_texture: &'a SharedTextureView,
+}
+
+/// The bindings of the hierarchical depth passes of a single camera.
+pub struct HiZBindings {
+    pub copy: Bindings,
+    pub downsample: Vec<Bindings>,
+    pub multisampled: bool,
+}
+
+impl HiZBindings {
+    pub fn new(device: &Device, pipeline: &HiZPipeline, multisampled: bool) -> Self {
+        let copy_layout = if multisampled {
+            &pipeline.multisampled_copy_layout
+        } else {
+            &pipeline.copy_layout
+        };
+
+        Self {
+            copy: copy_layout.create_bindings(device),
+            downsample: Vec::new(),
+            multisampled,
+        }
+    }
+
+    pub fn bind(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        pipeline: &HiZPipeline,
+        depth: &SharedTextureView,
+        target: &HiZTarget,
+    ) {
+        if self.multisampled {
+            let depth_bindings = MultisampledDepthBindings {
+                depth_texture: depth,
+            };
+
+            self.copy.bind(device, queue, &depth_bindings);
+        } else {
+            let depth_bindings = DepthBindings {
+                depth_texture: depth,
+            };
+
+            self.copy.bind(device, queue, &depth_bindings);
+        }
+
+        self.copy.update_bind_groups(device);
+
+        let mip_levels = target.mip_levels() as usize;
+        self.downsample.resize_with(mip_levels - 1, || {
+            pipeline.downsample_layout.create_bindings(device)
+        });
+
+        for (i, bindings) in self.downsample.iter_mut().enumerate() {
+            let downsample_bindings = HiZDownsampleBindings {
+                hi_z_source_texture: &target.views[i],
+            };
+
+            bindings.bind(device, queue, &downsample_bindings);
+            bindings.update_bind_groups(device);
+        }
+    }
+}
+
+pub struct HiZPipeline {
+    pub copy_layout: BindingLayout,
+    pub copy_pipeline: SharedRenderPipeline,
+    pub multisampled_copy_layout: BindingLayout,
+    pub multisampled_copy_pipeline: SharedRenderPipeline,
+    pub downsample_layout: BindingLayout,
+    pub downsample_pipeline: SharedRenderPipeline,
+}
+
+impl HiZPipeline {
+    fn create_pipeline(
+        device: &Device,
+        label: &str,
+        layout: &BindingLayout,
+        vertex: &Shader,
+        fragment: &Shader,
+    ) -> SharedRenderPipeline {
+        let pipeline_layout = layout.create_pipeline_layout(device);
+
+        device.create_shared_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &vertex.shader_module(device),
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &fragment.shader_module(device),
+                entry_point: "fragment",
+                targets: &[Some(ColorTargetState {
+                    format: HiZTarget::FORMAT,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    }
+}
+
+impl FromWorld for HiZPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut shader_processor = world.resource_mut::<ShaderProcessor>();
+
+        let mut multisampled_defs = ShaderDefs::new();
+        multisampled_defs.push("MULTISAMPLED");
+
+        let mut vertex = shader_processor
+            .process(
+                ShaderRef::module("lumi/fullscreen_vert.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut copy = shader_processor
+            .process(
+                ShaderRef::module("lumi/hi_z_copy_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        let mut multisampled_copy = shader_processor
+            .process(
+                ShaderRef::module("lumi/hi_z_copy_frag.wgsl"),
+                &multisampled_defs,
+            )
+            .unwrap();
+        let mut downsample = shader_processor
+            .process(
+                ShaderRef::module("lumi/hi_z_downsample_frag.wgsl"),
+                &Default::default(),
+            )
+            .unwrap();
+        vertex.rebind_with(&mut copy).unwrap();
+        vertex.rebind_with(&mut multisampled_copy).unwrap();
+        vertex.rebind_with(&mut downsample).unwrap();
+
+        let copy_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&copy)
+            .bind::<DepthBindings>();
+
+        let multisampled_copy_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&multisampled_copy)
+            .bind::<MultisampledDepthBindings>();
+
+        let downsample_layout = BindingLayout::new()
+            .with_shader(&vertex)
+            .with_shader(&downsample)
+            .bind::<HiZDownsampleBindings>();
+
+        let device = world.resource::<RenderDevice>();
+
+        let copy_pipeline =
+            Self::create_pipeline(device, "Lumi Hi-Z Copy", &copy_layout, &vertex, &copy);
+        let multisampled_copy_pipeline = Self::create_pipeline(
+            device,
+            "Lumi Multisampled Hi-Z Copy",
+            &multisampled_copy_layout,
+            &vertex,
+            &multisampled_copy,
+        );
+        let downsample_pipeline = Self::create_pipeline(
+            device,
+            "Lumi Hi-Z Downsample",
+            &downsample_layout,
+            &vertex,
+            &downsample,
+        );
+
+        Self {
+            copy_layout,
+            copy_pipeline,
+            multisampled_copy_layout,
+            multisampled_copy_pipeline,
+            downsample_layout,
+            downsample_pipeline,
+        }
+    }
+}
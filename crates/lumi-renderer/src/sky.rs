@@ -1,3 +1,5 @@
+use std::ops::Deref;
+
 use lumi_bind::{Bind, Binding, BindingLayout};
 use lumi_core::{
     BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder, CompareFunction,
@@ -50,7 +52,8 @@ impl FromWorld for SkyPipeline {
             .with_shader(&vertex_shader)
             .with_shader(&fragment_shader)
             .bind::<PreparedCamera>()
-            .bind::<SkyBindings>();
+            .bind::<SkyBindings>()
+            .bind::<PreparedEnvironment>();
 
         let pipeline_layout = bindings_layout.create_pipeline_layout(device);
         let render_pipeline = Self::create_render_pipeline(
@@ -153,6 +156,7 @@ pub fn sky_render_system(
 
     bindings.bind(&device, &queue, camera);
     bindings.bind(&device, &queue, &sky_bindings);
+    bindings.bind(&device, &queue, environment.deref());
 
     bindings.update_bind_groups(&device);
 
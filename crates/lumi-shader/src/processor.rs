@@ -298,16 +298,30 @@ impl ShaderProcessor {
         add_module!("camera.wgsl", "wgsl/camera.wgsl");
         add_module!("mesh.wgsl", "wgsl/mesh.wgsl");
         add_module!("light.wgsl", "wgsl/light.wgsl");
+        add_module!("cluster.wgsl", "wgsl/cluster.wgsl");
+        add_module!("light_texture.wgsl", "wgsl/light_texture.wgsl");
         add_module!("fullscreen.wgsl", "wgsl/fullscreen.wgsl");
         add_module!("tonemapping.wgsl", "wgsl/tonemapping.wgsl");
         add_module!("standard_material.wgsl", "wgsl/standard_material.wgsl");
         add_module!("integrated_brdf.wgsl", "wgsl/integrated_brdf.wgsl");
+        add_module!("ltc.wgsl", "wgsl/ltc.wgsl");
         add_module!("pbr_types.wgsl", "wgsl/pbr_types.wgsl");
         add_module!("pbr.wgsl", "wgsl/pbr.wgsl");
         add_module!("ssr.wgsl", "wgsl/ssr.wgsl");
+        add_module!("contact_shadow.wgsl", "wgsl/contact_shadow.wgsl");
+        add_module!("ambient_occlusion.wgsl", "wgsl/ambient_occlusion.wgsl");
+        add_module!("probe.wgsl", "wgsl/probe.wgsl");
+        add_module!("reflection_probe.wgsl", "wgsl/reflection_probe.wgsl");
+        add_module!("irradiance_volume.wgsl", "wgsl/irradiance_volume.wgsl");
+        add_module!("lightmap.wgsl", "wgsl/lightmap.wgsl");
+        add_module!(
+            "environment_settings.wgsl",
+            "wgsl/environment_settings.wgsl"
+        );
         add_module!("environment.wgsl", "wgsl/environment.wgsl");
         add_module!("pbr_light.wgsl", "wgsl/pbr_light.wgsl");
         add_module!("sky.wgsl", "wgsl/sky.wgsl");
+        add_module!("fog.wgsl", "wgsl/fog.wgsl");
         add_module!("poisson.wgsl", "wgsl/poisson.wgsl");
         add_module!("shadow.wgsl", "wgsl/shadow.wgsl");
         add_module!("shadow_mesh.wgsl", "wgsl/shadow_mesh.wgsl");
@@ -317,6 +331,18 @@ impl ShaderProcessor {
         add_module!("fxaa_frag.wgsl", "wgsl/fxaa_frag.wgsl");
         add_module!("fullscreen_vert.wgsl", "wgsl/fullscreen_vert.wgsl");
         add_module!("bloom_frag.wgsl", "wgsl/bloom_frag.wgsl");
+        add_module!("gtao_frag.wgsl", "wgsl/gtao_frag.wgsl");
+        add_module!("gtao_denoise_frag.wgsl", "wgsl/gtao_denoise_frag.wgsl");
+        add_module!("hi_z.wgsl", "wgsl/hi_z.wgsl");
+        add_module!("hi_z_copy_frag.wgsl", "wgsl/hi_z_copy_frag.wgsl");
+        add_module!("hi_z_downsample_frag.wgsl", "wgsl/hi_z_downsample_frag.wgsl");
+        add_module!("global_illumination.wgsl", "wgsl/global_illumination.wgsl");
+        add_module!("ssgi.wgsl", "wgsl/ssgi.wgsl");
+        add_module!("ssgi_frag.wgsl", "wgsl/ssgi_frag.wgsl");
+        add_module!("ssgi_accumulate_frag.wgsl", "wgsl/ssgi_accumulate_frag.wgsl");
+        add_module!("fog_scatter.wgsl", "wgsl/fog_scatter.wgsl");
+        add_module!("fog_integrate.wgsl", "wgsl/fog_integrate.wgsl");
+        add_module!("fog_frag.wgsl", "wgsl/fog_frag.wgsl");
         add_module!("tonemapping_frag.wgsl", "wgsl/tonemapping_frag.wgsl");
         add_module!("standard_frag.wgsl", "wgsl/standard_frag.wgsl");
     }
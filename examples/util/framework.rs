@@ -38,10 +38,7 @@ pub fn framework<T: App>() -> ! {
     .unwrap();
     let (device, queue) = future::block_on(adapter.request_device(
         &DeviceDescriptor {
-            limits: Limits {
-                max_uniform_buffers_per_shader_stage: 15,
-                ..Default::default()
-            },
+            limits: Renderer::limits(),
             ..Default::default()
         },
         None,
@@ -128,6 +125,8 @@ pub fn framework<T: App>() -> ! {
             };
 
             renderer.extract(&device, &queue, &mut world);
+            renderer.capture_reflection_probes(&device, &queue);
+            renderer.capture_irradiance_volumes(&device, &queue);
             //renderer.render(&device, &queue, render_target);
 
             let full_output = platform.end_frame(Some(&window));
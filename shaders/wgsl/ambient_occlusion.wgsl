@@ -0,0 +1,21 @@
+#include <lumi/camera.wgsl>
+
+@group(0) @binding(0)
+var ao_texture: texture_2d<f32>;
+
+// the screen space ambient occlusion at `frag_coord`, surfaces not in the depth buffer like
+// transparent objects aren't occluded
+fn ambient_occlusion(frag_coord: vec4<f32>, position: vec3<f32>) -> f32 {
+	let ao = textureLoad(ao_texture, vec2<i32>(frag_coord.xy), 0);
+	let depth = -(camera.inverse_view * vec4<f32>(position, 1.0)).z;
+
+	if abs(ao.g - depth) > depth * 0.02 {
+		return 1.0;
+	}
+
+	return ao.r;
+}
+
+fn specular_occlusion(nov: f32, ao: f32, roughness: f32) -> f32 {
+	return saturate(pow(nov + ao, exp2(-16.0 * roughness - 1.0)) - 1.0 + ao);
+}
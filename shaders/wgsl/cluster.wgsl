@@ -0,0 +1,41 @@
+#include <lumi/camera.wgsl>
+
+struct Clusters {
+	dimensions: vec3<u32>,
+	z_scale: f32,
+	z_bias: f32,
+}
+
+struct Cluster {
+	offset: u32,
+	point_count: u32,
+	spot_count: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> clusters: Clusters;
+
+@group(0) @binding(0)
+var<storage, read> cluster_lights: array<Cluster>;
+
+@group(0) @binding(0)
+var<storage, read> cluster_light_indices: array<u32>;
+
+fn cluster_index(position: vec3<f32>) -> u32 {
+	let clip = world_to_clip(position);
+	let ndc = clip.xy / clip.w;
+
+	let dimensions = vec3<f32>(clusters.dimensions);
+	let tile = clamp(floor((ndc * 0.5 + 0.5) * dimensions.xy), vec2<f32>(0.0), dimensions.xy - 1.0);
+
+	// depth slices are distributed exponentially between the near plane and the furthest light
+	let depth = -(camera.inverse_view * vec4<f32>(position, 1.0)).z;
+	let slice = clamp(floor(log(depth) * clusters.z_scale + clusters.z_bias), 0.0, dimensions.z - 1.0);
+
+	let index = vec3<u32>(vec3<f32>(tile, slice));
+	return index.x + index.y * clusters.dimensions.x + index.z * clusters.dimensions.x * clusters.dimensions.y;
+}
+
+fn get_cluster(position: vec3<f32>) -> Cluster {
+	return cluster_lights[cluster_index(position)];
+}
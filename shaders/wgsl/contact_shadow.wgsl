@@ -0,0 +1,46 @@
+#include <lumi/camera.wgsl>
+#include <lumi/hi_z.wgsl>
+
+let CONTACT_SHADOW_STEPS: u32 = 16u;
+
+// marches `length` meters from `position` towards the light along `l` through the depth buffer,
+// returning zero when a surface is found between them and one otherwise
+fn contact_shadow(frag_coord: vec4<f32>, position: vec3<f32>, l: vec3<f32>, length: f32) -> f32 {
+	if length <= 0.0 {
+		return 1.0;
+	}
+
+	let size = vec2<f32>(textureDimensions(hi_z_texture));
+	let step_length = length / f32(CONTACT_SHADOW_STEPS);
+	let noise = fract(52.9829189 * fract(dot(frag_coord.xy, vec2<f32>(0.06711056, 0.00583715))));
+
+	for (var i = 0u; i < CONTACT_SHADOW_STEPS; i += 1u) {
+		let sample_position = position + l * step_length * (f32(i) + noise + 0.5);
+		let clip = camera.view_proj * vec4<f32>(sample_position, 1.0);
+
+		// samples in front of the near plane can't be tested
+		if clip.z < 0.0 {
+			break;
+		}
+
+		let uv = clip.xy * (vec2<f32>(0.5, -0.5) / clip.w) + 0.5;
+
+		if any(uv < vec2<f32>(0.0)) || any(uv >= vec2<f32>(1.0)) {
+			break;
+		}
+
+		let depth = textureLoad(hi_z_texture, vec2<i32>(uv * size), 0).r;
+		let surface = clip_to_world(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0));
+
+		let sample_depth = -(camera.inverse_view * vec4<f32>(sample_position, 1.0)).z;
+		let surface_depth = -(camera.inverse_view * vec4<f32>(surface, 1.0)).z;
+		let difference = sample_depth - surface_depth;
+
+		// the sample is behind a surface, thin enough to be in contact with it
+		if difference > sample_depth * 0.001 && difference < length {
+			return 0.0;
+		}
+	}
+
+	return 1.0;
+}
@@ -1,5 +1,6 @@
 #include <lumi/sky.wgsl>
 #include <lumi/camera.wgsl>
+#include <lumi/environment_settings.wgsl>
 
 @group(0) @binding(0)
 var sky_texture: texture_cube<f32>;
@@ -19,6 +20,6 @@ fn fragment(sky: Sky) -> @location(0) vec4<f32> {
 		
 	let ray = normalize(world_far - world_near);
 
-	let color = textureSample(sky_texture, sky_sampler, ray);
+	let color = textureSample(sky_texture, sky_sampler, environment_direction(ray));
 	return color;
 }
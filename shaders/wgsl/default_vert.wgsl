@@ -19,5 +19,9 @@ fn vertex(vertex: Vertex) -> MeshOut {
 
 	mesh.uv_0 = vertex.uv_0;
 
+#ifdef LIGHTMAP
+	mesh.uv_1 = vertex.uv_1;
+#endif
+
 	return mesh;
 }
@@ -2,6 +2,11 @@
 #include <lumi/pbr_types.wgsl>
 #include <lumi/camera.wgsl>
 #include <lumi/ssr.wgsl>
+#include <lumi/ambient_occlusion.wgsl>
+#include <lumi/global_illumination.wgsl>
+#include <lumi/reflection_probe.wgsl>
+#include <lumi/irradiance_volume.wgsl>
+#include <lumi/environment_settings.wgsl>
 
 @group(0) @binding(0)
 var environment_diffuse: texture_cube<f32>;
@@ -12,8 +17,12 @@ var environment_specular: texture_cube<f32>;
 @group(0) @binding(0)
 var environment_sampler: sampler;
 
-fn env_diffuse(diffuse_color: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
-	let irradiance = textureSample(environment_diffuse, environment_sampler, n).rgb;
+// the diffuse light from irradiance volumes, falling back to the environment outside them
+fn env_diffuse(diffuse_color: vec3<f32>, position: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+	let volumes = irradiance_volumes_diffuse(position, n);
+	let direction = environment_direction(n);
+	let fallback = textureSample(environment_diffuse, environment_sampler, direction).rgb * environment_settings.color;
+	let irradiance = volumes.rgb + fallback * (1.0 - volumes.a);
 	let diffuse = irradiance * diffuse_color;
 
 	return diffuse;
@@ -22,21 +31,28 @@ fn env_diffuse(diffuse_color: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
 fn prefiltered_radiance_offset(n: vec3<f32>, roughness: f32, offset: f32) -> vec3<f32> {	
 	let levels = f32(textureNumLevels(environment_specular) - 1);
 	let lod = (roughness + offset) / levels;
-	let radiance = textureSampleLevel(environment_specular, environment_sampler, n, lod).rgb;
+	let radiance = textureSampleLevel(environment_specular, environment_sampler, environment_direction(n), lod).rgb;
 	return radiance;
 }
 
 fn prefiltered_radiance(n: vec3<f32>, roughness: f32) -> vec3<f32> {	
 	let levels = f32(textureNumLevels(environment_specular) - 1);
 	let lod = roughness * levels;
-	let radiance = textureSampleLevel(environment_specular, environment_sampler, n, lod).rgb;
+	let radiance = textureSampleLevel(environment_specular, environment_sampler, environment_direction(n), lod).rgb;
 	return radiance;
 }
 
 fn env_indirect(perceptual_roughness: f32, r: vec3<f32>) -> vec3<f32> {
 	let levels = textureNumLevels(environment_specular);
 	let lod = perceptual_roughness * f32(levels - 1);
-	return textureSampleLevel(environment_specular, environment_sampler, r, lod).rgb;
+	return textureSampleLevel(environment_specular, environment_sampler, environment_direction(r), lod).rgb;
+}
+
+// the specular radiance from reflection probes, falling back to the environment outside them
+fn env_specular(position: vec3<f32>, perceptual_roughness: f32, r: vec3<f32>) -> vec3<f32> {
+	let probes = reflection_probes_indirect(position, r, perceptual_roughness);
+	let fallback = env_indirect(perceptual_roughness, r) * environment_settings.color;
+	return probes.rgb + fallback * (1.0 - probes.a);
 }
 
 #ifdef SUBSURFACE
@@ -44,7 +60,7 @@ fn env_subsurface(
 	pixel: PbrPixel, 
 	irradiance: vec3<f32>,
 ) -> vec3<f32> {
-	let view_dependent = prefiltered_radiance_offset(-pixel.v, pixel.roughness, (1.0 + pixel.thickness) / 5.0);
+	let view_dependent = prefiltered_radiance_offset(-pixel.v, pixel.roughness, (1.0 + pixel.thickness) / 5.0) * environment_settings.color;
 	let attenuation = (1.0 - pixel.thickness) / (2.0 * PI);
 
 	return pixel.subsurface_color * (irradiance + view_dependent) * attenuation;
@@ -119,10 +135,30 @@ fn env_refractions(
 fn environment(pixel: PbrPixel) -> vec3<f32> {	
 	let e = pixel.f0 * pixel.dfg.x + pixel.f0 * pixel.dfg.y;
 
-	let diffuse_irradiance = env_diffuse(pixel.diffuse_color, pixel.n);
+#ifdef LIGHTMAP
+	let diffuse_irradiance = pixel.lightmap * pixel.diffuse_color;
+#endif
+#ifndef LIGHTMAP
+	let diffuse_irradiance = env_diffuse(pixel.diffuse_color, pixel.position, pixel.n);
+#endif
 	var diffuse = diffuse_irradiance;
-	let r = mix(pixel.r, pixel.n, pixel.roughness * pixel.roughness);
-	var specular = env_indirect(pixel.roughness, r);
+
+	var reflected = pixel.r;
+#ifdef ANISOTROPY
+	// bend the reflection towards the direction of the anisotropy, stretching it across the surface
+	let anisotropic_direction = select(pixel.anisotropic_t, pixel.anisotropic_b, pixel.anisotropy >= 0.0);
+	let anisotropic_tangent = cross(anisotropic_direction, pixel.v);
+	let anisotropic_normal = cross(anisotropic_tangent, anisotropic_direction);
+	let bend_factor = abs(pixel.anisotropy) * saturate(5.0 * pixel.perceptual_roughness);
+	let bent_normal = normalize(mix(pixel.n, anisotropic_normal, bend_factor));
+	reflected = reflect(-pixel.v, bent_normal);
+#endif
+
+	let r = mix(reflected, pixel.n, pixel.roughness * pixel.roughness);
+	var specular = env_specular(pixel.position, pixel.roughness, r);
+
+	let reflection = screen_space_reflections(pixel.position, r, pixel.roughness);
+	specular = mix(specular, reflection.rgb, reflection.a);
 
 	diffuse *= 1.0 - e;
 	specular *= e;
@@ -131,6 +167,14 @@ fn environment(pixel: PbrPixel) -> vec3<f32> {
 	diffuse += env_subsurface(pixel, diffuse_irradiance);
 #endif
 
+#ifdef SHEEN
+	diffuse *= pixel.sheen_scaling;
+	specular *= pixel.sheen_scaling;
+
+	let sheen_r = mix(pixel.r, pixel.n, pixel.sheen_roughness * pixel.sheen_roughness);
+	specular += env_specular(pixel.position, pixel.sheen_roughness, sheen_r) * pixel.sheen_color * pixel.sheen_dfg;
+#endif
+
 #ifdef CLEARCOAT
 	let fc = f_schlick(0.04, 1.0, pixel.clearcoat_nov) * pixel.clearcoat;
 	let attenuation = 1.0 - fc;
@@ -138,11 +182,17 @@ fn environment(pixel: PbrPixel) -> vec3<f32> {
 	diffuse *= attenuation;
 	specular *= attenuation;
 
-	specular += env_indirect(pixel.clearcoat_roughness, pixel.clearcoat_r) * fc;
+	specular += env_specular(pixel.position, pixel.clearcoat_roughness, pixel.clearcoat_r) * fc;
 #endif
 
-	diffuse *= ambient_light.color * camera.exposure;
-	specular *= ambient_light.color * camera.exposure;
+	let ao = ambient_occlusion(pixel.frag_coord, pixel.position) * pixel.occlusion;
+	diffuse *= ao;
+	specular *= specular_occlusion(pixel.nov, ao, pixel.roughness);
+
+	diffuse += screen_space_gi(pixel.frag_coord, pixel.position) * pixel.diffuse_color * (1.0 - e);
+
+	diffuse *= camera.exposure;
+	specular *= camera.exposure;
 
 #ifdef TRANSMISSION
 	let ft = env_refractions(pixel, e) * pixel.transmission;
@@ -0,0 +1,15 @@
+struct EnvironmentSettings {
+	color: vec3<f32>,
+	rotation: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> environment_settings: EnvironmentSettings;
+
+// rotates a world space direction into the space of the environment maps
+fn environment_direction(direction: vec3<f32>) -> vec3<f32> {
+	let c = cos(environment_settings.rotation);
+	let s = sin(environment_settings.rotation);
+
+	return vec3<f32>(c * direction.x - s * direction.z, direction.y, s * direction.x + c * direction.z);
+}
@@ -0,0 +1,47 @@
+#include <lumi/camera.wgsl>
+
+struct Fog {
+	albedo: vec3<f32>,
+	density: f32,
+	height: f32,
+	height_falloff: f32,
+	anisotropy: f32,
+	near: f32,
+	far: f32,
+}
+
+struct FogVolume {
+	volume_from_world: mat4x4<f32>,
+	half_extents: vec3<f32>,
+	density: f32,
+	albedo: vec3<f32>,
+	blend_distance: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> fog: Fog;
+
+// the view depth at `slice` of the froxel grid, slices are distributed exponentially so that
+// froxels near the camera are smaller
+fn fog_slice_depth(slice: f32, slices: f32) -> f32 {
+	return fog.near * pow(fog.far / fog.near, slice / slices);
+}
+
+// the inverse of `fog_slice_depth`
+fn fog_depth_slice(depth: f32, slices: f32) -> f32 {
+	return log(max(depth, fog.near) / fog.near) / log(fog.far / fog.near) * slices;
+}
+
+fn fog_camera_forward() -> vec3<f32> {
+	return -normalize(camera.view[2].xyz);
+}
+
+// the world space direction through `uv` on the screen
+fn fog_ray(uv: vec2<f32>) -> vec3<f32> {
+	let ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+
+	let world_near = clip_to_world(vec4<f32>(ndc, 0.0, 1.0));
+	let world_far = clip_to_world(vec4<f32>(ndc, 0.5, 1.0));
+
+	return normalize(world_far - world_near);
+}
@@ -0,0 +1,38 @@
+#include <lumi/fullscreen.wgsl>
+#include <lumi/fog.wgsl>
+
+@group(0) @binding(0)
+var fog_texture: texture_3d<f32>;
+
+@group(0) @binding(0)
+var fog_sampler: sampler;
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_multisampled_2d;
+#endif
+
+#ifndef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_2d;
+#endif
+
+// composites the fog over the hdr target, which is blended as `color + target * alpha`
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = vec2<i32>(fs.v_position.xy);
+	let depth = textureLoad(depth_texture, coord, 0);
+
+	let ndc = fs.uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+	let world = clip_to_world(vec4<f32>(ndc, depth, 1.0));
+	let view_depth = dot(world - camera.position, fog_camera_forward());
+
+	let slices = f32(textureDimensions(fog_texture).z);
+	let slice = fog_depth_slice(view_depth, slices);
+
+	// each froxel stores the fog up to its far side
+	let uvw = vec3<f32>(fs.uv, clamp((slice - 0.5) / slices, 0.0, 1.0));
+	let fog = textureSampleLevel(fog_texture, fog_sampler, uvw, 0.0);
+
+	return vec4<f32>(fog.rgb * camera.exposure, fog.a);
+}
@@ -0,0 +1,44 @@
+#include <lumi/fog.wgsl>
+
+@group(0) @binding(0)
+var fog_scatter_texture: texture_3d<f32>;
+
+@group(0) @binding(0)
+var fog_texture: texture_storage_3d<rgba16float, write>;
+
+// accumulates the froxels front to back, storing the light scattered towards the camera and the
+// transmittance from the camera to the far side of each froxel
+@compute @workgroup_size(8, 8, 1)
+fn integrate(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(fog_texture);
+
+	if any(vec2<i32>(global_id.xy) >= dimensions.xy) {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions.xy);
+	let ray = fog_ray(uv);
+	let slices = f32(dimensions.z);
+	let ray_scale = 1.0 / dot(ray, fog_camera_forward());
+
+	var scattering = vec3<f32>(0.0);
+	var transmittance = 1.0;
+
+	for (var z = 0; z < dimensions.z; z += 1) {
+		let coord = vec3<i32>(vec2<i32>(global_id.xy), z);
+		let froxel = textureLoad(fog_scatter_texture, coord, 0);
+
+		let near = select(0.0, fog_slice_depth(f32(z), slices), z > 0);
+		let far = fog_slice_depth(f32(z + 1), slices);
+		let length = (far - near) * ray_scale;
+
+		let extinction = max(froxel.a, 0.000001);
+		let froxel_transmittance = exp(-extinction * length);
+
+		// the scattering integrated over the length of the froxel
+		scattering += transmittance * (froxel.rgb - froxel.rgb * froxel_transmittance) / extinction;
+		transmittance *= froxel_transmittance;
+
+		textureStore(fog_texture, coord, vec4<f32>(scattering, transmittance));
+	}
+}
@@ -0,0 +1,125 @@
+#include <lumi/fog.wgsl>
+#include <lumi/shadow.wgsl>
+#include <lumi/environment_settings.wgsl>
+
+@group(0) @binding(0)
+var<uniform> fog_volume_count: u32;
+
+@group(0) @binding(0)
+var<storage, read> fog_volumes: array<FogVolume>;
+
+@group(0) @binding(0)
+var environment_diffuse: texture_cube<f32>;
+
+@group(0) @binding(0)
+var environment_sampler: sampler;
+
+@group(0) @binding(0)
+var fog_scatter_texture: texture_storage_3d<rgba16float, write>;
+
+struct FogMedium {
+	scattering: vec3<f32>,
+	extinction: f32,
+}
+
+fn fog_medium(position: vec3<f32>) -> FogMedium {
+	var medium: FogMedium;
+
+	// height fog, falling off exponentially above `fog.height`
+	let height_density = fog.density * exp(-max(position.y - fog.height, 0.0) * fog.height_falloff);
+	medium.scattering = fog.albedo * height_density;
+	medium.extinction = height_density;
+
+	for (var i = 0u; i < fog_volume_count; i += 1u) {
+		let volume = fog_volumes[i];
+
+		let local = (volume.volume_from_world * vec4<f32>(position, 1.0)).xyz;
+		let distance = volume.half_extents - abs(local);
+		let weight = saturate(min(distance.x, min(distance.y, distance.z)) / volume.blend_distance);
+
+		medium.scattering += volume.albedo * volume.density * weight;
+		medium.extinction += volume.density * weight;
+	}
+
+	return medium;
+}
+
+// the henyey-greenstein phase function
+fn fog_phase(cos_theta: f32) -> f32 {
+	let g = fog.anisotropy;
+	let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+	return (1.0 - g * g) / (4.0 * 3.14159265359 * denominator * sqrt(denominator));
+}
+
+// a single tap of the cascades of `light`, froxels are too large to benefit from filtering
+fn fog_directional_shadow(light: DirectionalLight, position: vec3<f32>) -> f32 {
+	let atlas_size = vec2<f32>(textureDimensions(shadow_atlas));
+
+	for (var i = 0u; i < light.cascade_count; i += 1u) {
+		let tile = shadow_tiles[light.cascade + i];
+
+		if tile.rect.z == 0.0 {
+			continue;
+		}
+
+		let light_space = tile.view_proj * vec4<f32>(position, 1.0);
+		let light_space = light_space.xyz / light_space.w;
+
+		if light_space.z < 0.0 || light_space.z > 1.0 || any(abs(light_space.xy) > vec2<f32>(1.0)) {
+			continue;
+		}
+
+		let uv = shadow_tile_uv(tile, light_space.xy);
+		let depth = textureLoad(shadow_atlas, vec2<i32>(uv * atlas_size), 0);
+
+		return select(0.0, 1.0, light_space.z - 0.0005 <= depth);
+	}
+
+	return 1.0;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn scatter(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let dimensions = textureDimensions(fog_scatter_texture);
+
+	if any(vec3<i32>(global_id) >= dimensions) {
+		return;
+	}
+
+	let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(dimensions.xy);
+	let ray = fog_ray(uv);
+	let depth = fog_slice_depth(f32(global_id.z) + 0.5, f32(dimensions.z));
+	let position = camera.position + ray * depth / dot(ray, fog_camera_forward());
+
+	let medium = fog_medium(position);
+
+	var light = vec3<f32>(0.0);
+
+	for (var i = 0u; i < light_counts.directional_count; i += 1u) {
+		let directional_light = directional_lights[i];
+
+		let phase = fog_phase(dot(ray, directional_light.direction));
+		let shadow = fog_directional_shadow(directional_light, position);
+		light += directional_light.color * directional_light.intensity * phase * shadow;
+	}
+
+	for (var i = 0u; i < light_counts.point_count; i += 1u) {
+		let point_light = point_lights[i];
+
+		let light_to_froxel = position - point_light.position;
+		let distance_squared = dot(light_to_froxel, light_to_froxel);
+		let factor = distance_squared / (point_light.range * point_light.range);
+		let attenuation = pow(saturate(1.0 - factor * factor), 2.0) / max(distance_squared, 0.0001);
+
+		let phase = fog_phase(dot(ray, normalize(light_to_froxel)));
+		light += point_light.color * point_light.intensity * attenuation * phase;
+	}
+
+	// the ambient light scattered isotropically, averaged between the sky and the ground
+	let up = textureSampleLevel(environment_diffuse, environment_sampler, environment_direction(vec3<f32>(0.0, 1.0, 0.0)), 0.0).rgb;
+	let down = textureSampleLevel(environment_diffuse, environment_sampler, environment_direction(vec3<f32>(0.0, -1.0, 0.0)), 0.0).rgb;
+	light += (up + down) * 0.5 * environment_settings.color;
+
+	let scattering = medium.scattering * light;
+	textureStore(fog_scatter_texture, vec3<i32>(global_id), vec4<f32>(scattering, medium.extinction));
+}
@@ -0,0 +1,17 @@
+#include <lumi/camera.wgsl>
+
+@group(0) @binding(0)
+var gi_texture: texture_2d<f32>;
+
+// the screen space indirect light at `frag_coord` divided by pi, surfaces not in the depth buffer
+// like transparent objects don't receive any
+fn screen_space_gi(frag_coord: vec4<f32>, position: vec3<f32>) -> vec3<f32> {
+	let gi = textureLoad(gi_texture, vec2<i32>(frag_coord.xy), 0);
+	let depth = -(camera.inverse_view * vec4<f32>(position, 1.0)).z;
+
+	if abs(gi.a - depth) > depth * 0.02 {
+		return vec3<f32>(0.0);
+	}
+
+	return gi.rgb;
+}
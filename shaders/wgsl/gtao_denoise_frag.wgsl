@@ -0,0 +1,36 @@
+#include <lumi/fullscreen.wgsl>
+
+@group(0) @binding(0)
+var ao_noisy_texture: texture_2d<f32>;
+
+let DENOISE_RADIUS: i32 = 2;
+
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = vec2<i32>(fs.v_position.xy);
+	let size = vec2<i32>(textureDimensions(ao_noisy_texture));
+
+	let center = textureLoad(ao_noisy_texture, coord, 0);
+	let depth = center.g;
+
+	var ao = 0.0;
+	var total_weight = 0.0;
+
+	for (var y = -DENOISE_RADIUS; y <= DENOISE_RADIUS; y += 1) {
+		for (var x = -DENOISE_RADIUS; x <= DENOISE_RADIUS; x += 1) {
+			let sample_coord = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+			let sample = textureLoad(ao_noisy_texture, sample_coord, 0);
+
+			// only blur across surfaces at a similar depth
+			let difference = abs(sample.g - depth) / max(depth, 0.0001);
+			let weight = saturate(1.0 - difference * 20.0);
+
+			ao += sample.r * weight;
+			total_weight += weight;
+		}
+	}
+
+	ao /= max(total_weight, 0.0001);
+
+	return vec4<f32>(ao, depth, 0.0, 0.0);
+}
@@ -0,0 +1,188 @@
+#include <lumi/fullscreen.wgsl>
+#include <lumi/camera.wgsl>
+
+struct AmbientOcclusion {
+	radius: f32,
+	intensity: f32,
+	falloff: f32,
+	slice_count: u32,
+	step_count: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> ambient_occlusion: AmbientOcclusion;
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_multisampled_2d;
+#endif
+
+#ifndef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_2d;
+#endif
+
+let PI: f32 = 3.141592653589793;
+let HALF_PI: f32 = 1.5707963267948966;
+
+fn load_depth(coord: vec2<i32>) -> f32 {
+	let size = vec2<i32>(textureDimensions(depth_texture));
+	let clamped = clamp(coord, vec2<i32>(0), size - 1);
+
+	return textureLoad(depth_texture, clamped, 0);
+}
+
+// reconstructs the view space position at pixel `coord`
+fn view_position(coord: vec2<f32>) -> vec3<f32> {
+	let size = vec2<f32>(textureDimensions(depth_texture));
+	// keep the far plane at a finite distance
+	let depth = min(load_depth(vec2<i32>(coord)), 0.99999);
+
+	let uv = (coord + 0.5) / size;
+	let clip = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+	let world = clip_to_world(clip);
+
+	return (camera.inverse_view * vec4<f32>(world, 1.0)).xyz;
+}
+
+// reconstructs the view space normal from the neighbouring depth, picking the smallest
+// derivative on each axis to avoid smearing normals across edges
+fn view_normal(coord: vec2<f32>, position: vec3<f32>) -> vec3<f32> {
+	let left = view_position(coord - vec2<f32>(1.0, 0.0));
+	let right = view_position(coord + vec2<f32>(1.0, 0.0));
+	let top = view_position(coord - vec2<f32>(0.0, 1.0));
+	let bottom = view_position(coord + vec2<f32>(0.0, 1.0));
+
+	var dx = right - position;
+	if abs(position.z - left.z) < abs(right.z - position.z) {
+		dx = position - left;
+	}
+
+	var dy = position - bottom;
+	if abs(position.z - top.z) < abs(bottom.z - position.z) {
+		dy = top - position;
+	}
+
+	return normalize(cross(dx, dy));
+}
+
+fn interleaved_gradient_noise(coord: vec2<f32>) -> f32 {
+	return fract(52.9829189 * fract(dot(coord, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+fn fast_acos(x: f32) -> f32 {
+	let abs_x = abs(x);
+	var res = -0.156583 * abs_x + HALF_PI;
+	res *= sqrt(1.0 - abs_x);
+
+	if x >= 0.0 {
+		return res;
+	} else {
+		return PI - res;
+	}
+}
+
+// returns the radius in pixels of a sphere of `radius` at view space `position`
+fn screen_radius(position: vec3<f32>, radius: f32) -> f32 {
+	let projection = camera.view_proj * camera.view;
+	let center = projection * vec4<f32>(position, 1.0);
+	let edge = projection * vec4<f32>(position + vec3<f32>(radius, 0.0, 0.0), 1.0);
+
+	let size = vec2<f32>(textureDimensions(depth_texture));
+	return abs(edge.x / edge.w - center.x / center.w) * 0.5 * size.x;
+}
+
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = floor(fs.v_position.xy);
+	let depth = load_depth(vec2<i32>(coord));
+
+	// the sky isn't occluded
+	if depth >= 1.0 {
+		return vec4<f32>(1.0, 0.0, 0.0, 0.0);
+	}
+
+	let position = view_position(coord);
+	let normal = view_normal(coord, position);
+	let v = normalize(-position);
+
+	let radius = ambient_occlusion.radius;
+	let pixel_radius = screen_radius(position, radius);
+
+	let falloff_range = max(ambient_occlusion.falloff * radius, 0.0001);
+	let falloff_from = radius * (1.0 - ambient_occlusion.falloff);
+	let falloff_mul = -1.0 / falloff_range;
+	let falloff_add = falloff_from / falloff_range + 1.0;
+
+	let noise_slice = interleaved_gradient_noise(coord);
+	let noise_sample = interleaved_gradient_noise(coord + vec2<f32>(5.588238, 47.0));
+
+	let slice_count = max(ambient_occlusion.slice_count, 1u);
+	let step_count = max(ambient_occlusion.step_count, 1u);
+
+	var visibility = 0.0;
+
+	for (var slice = 0u; slice < slice_count; slice += 1u) {
+		let phi = (f32(slice) + noise_slice) * PI / f32(slice_count);
+		let omega = vec2<f32>(cos(phi), sin(phi));
+
+		// screen space y points down, view space y points up
+		let screen_direction = vec2<f32>(omega.x, -omega.y);
+		let direction = vec3<f32>(omega, 0.0);
+
+		let ortho_direction = direction - dot(direction, v) * v;
+		let axis = normalize(cross(direction, v));
+		let projected_normal = normal - axis * dot(normal, axis);
+		let projected_normal_length = length(projected_normal);
+
+		let sign_normal = sign(dot(ortho_direction, projected_normal));
+		let cos_normal = saturate(dot(projected_normal, v) / max(projected_normal_length, 0.0001));
+		let n = sign_normal * fast_acos(cos_normal);
+
+		let low_horizon_cos0 = cos(n + HALF_PI);
+		let low_horizon_cos1 = cos(n - HALF_PI);
+
+		var horizon_cos0 = low_horizon_cos0;
+		var horizon_cos1 = low_horizon_cos1;
+
+		for (var i = 0u; i < step_count; i += 1u) {
+			var t = (f32(i) + noise_sample) / f32(step_count);
+
+			// distribute samples closer to the center
+			t *= t;
+
+			let offset = screen_direction * max(t * pixel_radius, f32(i) + 1.0);
+
+			let delta0 = view_position(coord + offset) - position;
+			let delta1 = view_position(coord - offset) - position;
+
+			let distance0 = length(delta0);
+			let distance1 = length(delta1);
+
+			let shc0 = dot(delta0 / max(distance0, 0.0001), v);
+			let shc1 = dot(delta1 / max(distance1, 0.0001), v);
+
+			let weight0 = saturate(distance0 * falloff_mul + falloff_add);
+			let weight1 = saturate(distance1 * falloff_mul + falloff_add);
+
+			horizon_cos0 = max(horizon_cos0, mix(low_horizon_cos0, shc0, weight0));
+			horizon_cos1 = max(horizon_cos1, mix(low_horizon_cos1, shc1, weight1));
+		}
+
+		var h0 = -fast_acos(clamp(horizon_cos1, -1.0, 1.0));
+		var h1 = fast_acos(clamp(horizon_cos0, -1.0, 1.0));
+
+		h0 = n + clamp(h0 - n, -HALF_PI, HALF_PI);
+		h1 = n + clamp(h1 - n, -HALF_PI, HALF_PI);
+
+		let arc0 = (cos_normal + 2.0 * h0 * sin(n) - cos(2.0 * h0 - n)) / 4.0;
+		let arc1 = (cos_normal + 2.0 * h1 * sin(n) - cos(2.0 * h1 - n)) / 4.0;
+
+		visibility += projected_normal_length * (arc0 + arc1);
+	}
+
+	visibility /= f32(slice_count);
+	visibility = pow(saturate(visibility), ambient_occlusion.intensity);
+
+	return vec4<f32>(visibility, -position.z, 0.0, 0.0);
+}
@@ -0,0 +1,3 @@
+// the closest depth of the opaque surfaces of this frame, with coarser mips covering more texels
+@group(0) @binding(0)
+var hi_z_texture: texture_2d<f32>;
@@ -0,0 +1,30 @@
+#include <lumi/fullscreen.wgsl>
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_multisampled_2d;
+#endif
+
+#ifndef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_2d;
+#endif
+
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = vec2<i32>(fs.v_position.xy);
+
+#ifdef MULTISAMPLED
+	// keep the closest depth of all samples
+	var depth = 1.0;
+	for (var i = 0; i < textureNumSamples(depth_texture); i += 1) {
+		depth = min(depth, textureLoad(depth_texture, coord, i));
+	}
+#endif
+
+#ifndef MULTISAMPLED
+	let depth = textureLoad(depth_texture, coord, 0);
+#endif
+
+	return vec4<f32>(depth, 0.0, 0.0, 0.0);
+}
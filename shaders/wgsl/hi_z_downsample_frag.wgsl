@@ -0,0 +1,46 @@
+#include <lumi/fullscreen.wgsl>
+
+@group(0) @binding(0)
+var hi_z_source_texture: texture_2d<f32>;
+
+fn load_source(coord: vec2<i32>) -> f32 {
+	let size = vec2<i32>(textureDimensions(hi_z_source_texture));
+	let clamped = clamp(coord, vec2<i32>(0), size - 1);
+
+	return textureLoad(hi_z_source_texture, clamped, 0).r;
+}
+
+// keeps the closest depth of the texels covered in the source mip
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = vec2<i32>(fs.v_position.xy);
+	let source_coord = coord * 2;
+
+	let source_size = vec2<i32>(textureDimensions(hi_z_source_texture));
+	let size = max(source_size / 2, vec2<i32>(1));
+
+	var depth = min(
+		min(load_source(source_coord), load_source(source_coord + vec2<i32>(1, 0))),
+		min(load_source(source_coord + vec2<i32>(0, 1)), load_source(source_coord + vec2<i32>(1, 1)))
+	);
+
+	// the last texel covers an extra row or column when the source size is odd
+	let extra_x = (source_size.x & 1) == 1 && coord.x == size.x - 1;
+	let extra_y = (source_size.y & 1) == 1 && coord.y == size.y - 1;
+
+	if extra_x {
+		depth = min(depth, load_source(source_coord + vec2<i32>(2, 0)));
+		depth = min(depth, load_source(source_coord + vec2<i32>(2, 1)));
+	}
+
+	if extra_y {
+		depth = min(depth, load_source(source_coord + vec2<i32>(0, 2)));
+		depth = min(depth, load_source(source_coord + vec2<i32>(1, 2)));
+	}
+
+	if extra_x && extra_y {
+		depth = min(depth, load_source(source_coord + vec2<i32>(2, 2)));
+	}
+
+	return vec4<f32>(depth, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,86 @@
+#include <lumi/probe.wgsl>
+
+@group(0) @binding(0)
+var irradiance_volume_texture: texture_3d<f32>;
+
+@group(0) @binding(0)
+var irradiance_volume_sampler: sampler;
+
+// samples one of the 7 texels holding the spherical harmonics of the probes, `coord` is in
+// texels of the volume
+fn irradiance_volume_texel(volume: Probe, coord: vec3<f32>, texel: f32) -> vec4<f32> {
+	let size = vec3<f32>(textureDimensions(irradiance_volume_texture));
+	let layer = f32(volume.layer) + texel * volume.resolution.z + coord.z;
+	let uvw = vec3<f32>(coord.xy, layer) / size;
+
+	return textureSampleLevel(irradiance_volume_texture, irradiance_volume_sampler, uvw, 0.0);
+}
+
+// the irradiance in direction `n` divided by pi, from the interpolated spherical harmonics at
+// `coord`
+fn irradiance_volume_irradiance(volume: Probe, coord: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+	let t0 = irradiance_volume_texel(volume, coord, 0.0);
+	let t1 = irradiance_volume_texel(volume, coord, 1.0);
+	let t2 = irradiance_volume_texel(volume, coord, 2.0);
+	let t3 = irradiance_volume_texel(volume, coord, 3.0);
+	let t4 = irradiance_volume_texel(volume, coord, 4.0);
+	let t5 = irradiance_volume_texel(volume, coord, 5.0);
+	let t6 = irradiance_volume_texel(volume, coord, 6.0);
+
+	let c0 = t0.xyz;
+	let c1 = vec3<f32>(t0.w, t1.xy);
+	let c2 = vec3<f32>(t1.zw, t2.x);
+	let c3 = t2.yzw;
+	let c4 = t3.xyz;
+	let c5 = vec3<f32>(t3.w, t4.xy);
+	let c6 = vec3<f32>(t4.zw, t5.x);
+	let c7 = t5.yzw;
+	let c8 = t6.xyz;
+
+	// the radiance convolved with the clamped cosine lobe, scaled by 1 / pi per band
+	var irradiance = c0 * 0.282095;
+	irradiance += (c1 * n.y + c2 * n.z + c3 * n.x) * 0.488603 * (2.0 / 3.0);
+	irradiance += (c4 * n.x * n.y + c5 * n.y * n.z + c7 * n.x * n.z) * 1.092548 * 0.25;
+	irradiance += c6 * (3.0 * n.z * n.z - 1.0) * 0.315392 * 0.25;
+	irradiance += c8 * (n.x * n.x - n.y * n.y) * 0.546274 * 0.25;
+
+	return max(irradiance, vec3<f32>(0.0));
+}
+
+// the diffuse irradiance from the irradiance volumes containing `position`
+//
+// returns the irradiance in rgb and how much of the environment is covered by volumes in a
+fn irradiance_volumes_diffuse(position: vec3<f32>, n: vec3<f32>) -> vec4<f32> {
+	var irradiance = vec3<f32>(0.0);
+	var remaining = 1.0;
+
+	// the volumes are stored after the reflection probes
+	let offset = light_counts.reflection_probe_count;
+
+	for (var i = 0u; i < light_counts.irradiance_volume_count; i += 1u) {
+		let volume = probes[offset + i];
+
+		let local = (volume.probe_from_world * vec4<f32>(position, 1.0)).xyz;
+		let distance = volume.half_extents - abs(local);
+		let weight = saturate(min(distance.x, min(distance.y, distance.z)) / volume.blend_distance);
+
+		if weight <= 0.0 {
+			continue;
+		}
+
+		// probes lie on the centers of the texels
+		let t = saturate(local / (volume.half_extents * 2.0) + 0.5);
+		let coord = t * (volume.resolution - 1.0) + 0.5;
+
+		let color = irradiance_volume_irradiance(volume, coord, n);
+
+		irradiance += color * volume.intensity * weight * remaining;
+		remaining *= 1.0 - weight;
+
+		if remaining <= 0.0 {
+			break;
+		}
+	}
+
+	return vec4<f32>(irradiance, 1.0 - remaining);
+}
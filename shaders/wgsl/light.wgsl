@@ -1,24 +1,73 @@
-struct AmbientLight {
+struct PointLight {
+	position: vec3<f32>,
 	color: vec3<f32>,
+	intensity: f32,
+	range: f32,
+	shadow_index: i32,
+	softness: f32,
+	normal_bias: f32,
+	orientation: mat3x3<f32>,
+	cookie_index: i32,
+	profile_index: i32,
+	render_layers: u32,
+	contact_shadow_length: f32,
 }
 
-struct PointLight {
+struct SpotLight {
 	position: vec3<f32>,
+	direction: vec3<f32>,
 	color: vec3<f32>,
 	intensity: f32,
 	range: f32,
+	angle_scale: f32,
+	angle_offset: f32,
+	shadow_index: i32,
+	softness: f32,
+	normal_bias: f32,
+	orientation: mat3x3<f32>,
+	cookie_scale: f32,
+	cookie_index: i32,
+	profile_index: i32,
+	render_layers: u32,
 }
 
 struct DirectionalLight {
 	direction: vec3<f32>,
 	color: vec3<f32>,
 	intensity: f32,
-	size: f32,
 	depth: f32,
 	softness: f32,
 	falloff: f32,
+	normal_bias: f32,
 	cascade: u32,
-	view_proj: mat4x4<f32>,
+	cascade_count: u32,
+	render_layers: u32,
+	contact_shadow_length: f32,
+}
+
+let AREA_LIGHT_RECT: u32 = 0u;
+let AREA_LIGHT_DISK: u32 = 1u;
+let AREA_LIGHT_TUBE: u32 = 2u;
+
+// a rect, disk or tube light depending on `kind`, tube lights lie along `right`
+struct AreaLight {
+	position: vec3<f32>,
+	kind: u32,
+	right: vec3<f32>,
+	radius: f32,
+	up: vec3<f32>,
+	intensity: f32,
+	color: vec3<f32>,
+	render_layers: u32,
+}
+
+struct LightCounts {
+	point_count: u32,
+	spot_count: u32,
+	directional_count: u32,
+	area_count: u32,
+	reflection_probe_count: u32,
+	irradiance_volume_count: u32,
 }
 
 struct Light {
@@ -29,15 +78,26 @@ struct Light {
 	attenuation: f32,
 }
 
+// the render layers of the mesh being shaded
 @group(0) @binding(0)
-var<uniform> ambient_light: AmbientLight;
+var<uniform> render_layers: u32;
 
 @group(0) @binding(0)
-var<uniform> point_light_count: u32;
+var<uniform> light_counts: LightCounts;
+
 @group(0) @binding(0)
 var<storage, read> point_lights: array<PointLight>;
 
 @group(0) @binding(0)
-var<uniform> directional_light_count: u32;
+var<storage, read> spot_lights: array<SpotLight>;
+
 @group(0) @binding(0)
 var<storage, read> directional_lights: array<DirectionalLight>;
+
+@group(0) @binding(0)
+var<storage, read> area_lights: array<AreaLight>;
+
+// returns true if a light on `layers` affects the mesh being shaded
+fn light_visible(layers: u32) -> bool {
+	return (layers & render_layers) != 0u;
+}
@@ -0,0 +1,59 @@
+#include <lumi/light.wgsl>
+
+@group(0) @binding(0)
+var light_cookies: texture_2d_array<f32>;
+
+@group(0) @binding(0)
+var light_profiles: texture_2d_array<f32>;
+
+@group(0) @binding(0)
+var light_texture_sampler: sampler;
+
+let LIGHT_TEXTURE_INV_PI = 0.31830988618379067153776752674503;
+
+// maps a light space direction to equirectangular uv, with v = 0 along the light axis
+fn light_texture_polar_uv(local: vec3<f32>) -> vec2<f32> {
+	let u = atan2(local.y, local.x) * 0.5 * LIGHT_TEXTURE_INV_PI;
+	let v = acos(clamp(local.z, -1.0, 1.0)) * LIGHT_TEXTURE_INV_PI;
+	return vec2<f32>(fract(u), v);
+}
+
+fn light_profile(index: i32, local: vec3<f32>) -> f32 {
+	if index < 0 {
+		return 1.0;
+	}
+
+	let uv = light_texture_polar_uv(local);
+	return textureSampleLevel(light_profiles, light_texture_sampler, uv, index, 0.0).r;
+}
+
+fn light_cookie(index: i32, uv: vec2<f32>) -> vec3<f32> {
+	if index < 0 {
+		return vec3<f32>(1.0);
+	}
+
+	return textureSampleLevel(light_cookies, light_texture_sampler, uv, index, 0.0).rgb;
+}
+
+// returns the tint of a point light in direction `l` from the light
+fn point_light_texture(light: PointLight, l: vec3<f32>) -> vec3<f32> {
+	let local = light.orientation * l;
+
+	let profile = light_profile(light.profile_index, local);
+	let cookie = light_cookie(light.cookie_index, light_texture_polar_uv(local));
+
+	return cookie * profile;
+}
+
+// returns the tint of a spot light in direction `l` from the light
+fn spot_light_texture(light: SpotLight, l: vec3<f32>) -> vec3<f32> {
+	let local = light.orientation * l;
+
+	let profile = light_profile(light.profile_index, local);
+
+	// project the cookie over the cone of the light
+	let projected = vec2<f32>(local.x, -local.y) / max(local.z, 0.0001);
+	let cookie = light_cookie(light.cookie_index, projected * light.cookie_scale * 0.5 + 0.5);
+
+	return cookie * profile;
+}
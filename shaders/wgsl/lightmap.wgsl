@@ -0,0 +1,22 @@
+#ifdef LIGHTMAP
+struct Lightmap {
+	scale: vec2<f32>,
+	offset: vec2<f32>,
+	intensity: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> lightmap: Lightmap;
+
+@group(0) @binding(0)
+var lightmap_texture: texture_2d<f32>;
+
+@group(0) @binding(0)
+var lightmap_sampler: sampler;
+
+// the baked irradiance divided by pi at `uv`, the `uv_1` of the mesh
+fn lightmap_irradiance(uv: vec2<f32>) -> vec3<f32> {
+	let uv = uv * lightmap.scale + lightmap.offset;
+	return textureSample(lightmap_texture, lightmap_sampler, uv).rgb * lightmap.intensity;
+}
+#endif
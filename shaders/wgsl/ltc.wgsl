@@ -0,0 +1,96 @@
+// the matrices in the top half and the magnitudes in the bottom half
+@group(0) @binding(0)
+var ltc_table: texture_2d<f32>;
+
+@group(0) @binding(0)
+var ltc_sampler: sampler;
+
+let LTC_LUT_SIZE = 64.0;
+
+fn ltc_uv(perceptual_roughness: f32, nov: f32) -> vec2<f32> {
+	let coord = vec2<f32>(perceptual_roughness, sqrt(1.0 - nov));
+	let uv = coord * (LTC_LUT_SIZE - 1.0) / LTC_LUT_SIZE + 0.5 / LTC_LUT_SIZE;
+
+	// the uv in the top half, which stays between texel centers so the halves never blend
+	return vec2<f32>(uv.x, uv.y * 0.5);
+}
+
+fn ltc_matrix(uv: vec2<f32>) -> mat3x3<f32> {
+	let t = textureSampleLevel(ltc_table, ltc_sampler, uv, 0.0);
+
+	return mat3x3<f32>(
+		vec3<f32>(t.x, 0.0, t.y),
+		vec3<f32>(0.0, 1.0, 0.0),
+		vec3<f32>(t.z, 0.0, t.w),
+	);
+}
+
+// returns the magnitude and fresnel of the fitted distribution
+fn ltc_magnitude(uv: vec2<f32>) -> vec2<f32> {
+	return textureSampleLevel(ltc_table, ltc_sampler, uv + vec2<f32>(0.0, 0.5), 0.0).xy;
+}
+
+// transforms world space directions into a tangent frame around `n`, aligned with `v`
+fn ltc_basis(n: vec3<f32>, v: vec3<f32>) -> mat3x3<f32> {
+	let t1 = normalize(v - n * dot(v, n));
+	let t2 = cross(n, t1);
+
+	return transpose(mat3x3<f32>(t1, t2, n));
+}
+
+// integral of the edge between `v1` and `v2` over the clamped cosine, as a vector form factor
+fn ltc_edge(v1: vec3<f32>, v2: vec3<f32>) -> vec3<f32> {
+	let x = dot(v1, v2);
+	let y = abs(x);
+
+	// rational fit of theta / sin(theta) / 2pi
+	let a = 0.8543985 + (0.4965155 + 0.0145206 * y) * y;
+	let b = 3.4175940 + (4.1616724 + y) * y;
+	let v = a / b;
+
+	var theta_sintheta = v;
+	if x <= 0.0 {
+		theta_sintheta = 0.5 * inverseSqrt(max(1.0 - x * x, 1e-7)) - v;
+	}
+
+	return cross(v1, v2) * theta_sintheta;
+}
+
+// approximates the form factor of the polygon, clipped to the horizon, from its vector form factor
+fn ltc_clipped_sphere(f: vec3<f32>) -> f32 {
+	let l = length(f);
+	return max((l * l + f.z) / (l + 1.0), 0.0);
+}
+
+// integrates the distribution `m` over the convex polygon `points`, seen from `position`
+fn ltc_evaluate(
+	polygon: array<vec3<f32>, 8>,
+	count: u32,
+	position: vec3<f32>,
+	m: mat3x3<f32>,
+) -> f32 {
+	var points = polygon;
+
+	let first = normalize(m * (points[0] - position));
+
+	var f = vec3<f32>(0.0);
+	var center = first;
+	var previous = first;
+
+	for (var i = 1u; i < count; i += 1u) {
+		let next = normalize(m * (points[i] - position));
+		f += ltc_edge(previous, next);
+
+		center += next;
+		previous = next;
+	}
+
+	f += ltc_edge(previous, first);
+
+	// the vector form factor points towards the polygon regardless of winding
+	if dot(f, center) < 0.0 {
+		f = -f;
+	}
+
+	return ltc_clipped_sphere(f);
+}
@@ -7,6 +7,10 @@ struct Vertex {
 	tangent: vec4<f32>,
 	@location(3)
 	uv_0: vec2<f32>,
+#ifdef LIGHTMAP
+	@location(4)
+	uv_1: vec2<f32>,
+#endif
 }
 
 struct MeshOut {
@@ -22,6 +26,10 @@ struct MeshOut {
 	w_bitangent: vec3<f32>,
 	@location(4)
 	uv_0: vec2<f32>,
+#ifdef LIGHTMAP
+	@location(5)
+	uv_1: vec2<f32>,
+#endif
 }
 
 struct Mesh {
@@ -39,6 +47,10 @@ struct Mesh {
 	w_bitangent: vec3<f32>,
 	@location(4)
 	uv_0: vec2<f32>,
+#ifdef LIGHTMAP
+	@location(5)
+	uv_1: vec2<f32>,
+#endif
 }
 
 @group(0) @binding(0)
@@ -4,10 +4,64 @@
 #include <lumi/pbr_types.wgsl>
 #include <lumi/camera.wgsl>
 
-fn pbr_light(pbr: Pbr) -> vec4<f32> {
+#ifdef ALPHA_DITHERED
+fn alpha_dither(coord: vec2<f32>) -> f32 {
+	return fract(52.9829189 * fract(dot(coord, vec2<f32>(0.06711056, 0.00583715))));
+}
+#endif
+
+// applies the alpha mode of the material, discarding the fragments of masked materials
+fn pbr_alpha(pbr: Pbr) -> f32 {
+	var alpha = pbr.base_color.a;
+
+#ifdef ALPHA_OPAQUE
+	alpha = 1.0;
+#endif
+
+#ifdef ALPHA_MASK
+#ifdef ALPHA_TO_COVERAGE
+	// sharpen alpha around the cutoff, coverage then resolves to an antialiased edge
+	alpha = clamp((alpha - pbr.alpha_cutoff) / max(fwidth(alpha), 0.0001) + 0.5, 0.0, 1.0);
+#endif
+
+#ifndef ALPHA_TO_COVERAGE
+	if alpha <= pbr.alpha_cutoff {
+		discard;
+	}
+
+	alpha = 1.0;
+#endif
+#endif
+
+#ifdef ALPHA_DITHERED
+	if alpha < alpha_dither(pbr.frag_coord.xy) {
+		discard;
+	}
+
+	alpha = 1.0;
+#endif
+
+	return alpha;
+}
+
+// discards the fragments of masked materials in the depth prepass, which has no color target
+// to resolve alpha to coverage with
+fn pbr_prepass(pbr: Pbr) {
+#ifdef ALPHA_MASK
 	if pbr.base_color.a <= pbr.alpha_cutoff {
 		discard;
 	}
+#endif
+
+#ifdef ALPHA_DITHERED
+	if pbr.base_color.a < alpha_dither(pbr.frag_coord.xy) {
+		discard;
+	}
+#endif
+}
+
+fn pbr_light(pbr: Pbr) -> vec4<f32> {
+	let alpha = pbr_alpha(pbr);
 
 	let pixel = get_pbr_pixel(pbr);
 
@@ -19,5 +73,5 @@ fn pbr_light(pbr: Pbr) -> vec4<f32> {
 	color *= camera.exposure;
 	color += environment(pixel);
 
-	return vec4<f32>(color, pbr.base_color.a);
+	return vec4<f32>(color, alpha);
 }
@@ -1,5 +1,9 @@
 #include <lumi/light.wgsl>
+#include <lumi/cluster.wgsl>
+#include <lumi/light_texture.wgsl>
 #include <lumi/shadow.wgsl>
+#include <lumi/contact_shadow.wgsl>
+#include <lumi/ltc.wgsl>
 #include <lumi/pbr_types.wgsl>
 
 let PI = 3.1415926535897932384626433832795;
@@ -19,6 +23,12 @@ fn get_distance_attenuation(distance_squared: f32, inverse_range_squared: f32) -
 	return attenuation * 1.0 / max(distance_squared, 0.0001);
 }
 
+fn get_spot_attenuation(l: vec3<f32>, direction: vec3<f32>, scale: f32, offset: f32) -> f32 {
+	let cd = dot(direction, -l);
+	let attenuation = saturate(cd * scale + offset);
+	return attenuation * attenuation;
+}
+
 fn fd_lambert() -> f32 {
 	return 1.0 / PI;
 }
@@ -39,6 +49,41 @@ fn v_smith(roughness: f32, nov: f32, nol: f32) -> f32 {
     return saturate_half(v);
 }
 
+fn d_ggx_anisotropic(at: f32, ab: f32, toh: f32, boh: f32, noh: f32) -> f32 {
+	let a2 = at * ab;
+	let d = vec3<f32>(ab * toh, at * boh, a2 * noh);
+	let d2 = dot(d, d);
+	let b2 = a2 / d2;
+	return saturate_half(a2 * b2 * b2 * (1.0 / PI));
+}
+
+fn v_smith_anisotropic(
+	at: f32,
+	ab: f32,
+	tov: f32,
+	bov: f32,
+	tol: f32,
+	bol: f32,
+	nov: f32,
+	nol: f32,
+) -> f32 {
+	let lambdaV = nol * length(vec3<f32>(at * tov, ab * bov, nov));
+	let lambdaL = nov * length(vec3<f32>(at * tol, ab * bol, nol));
+	let v = 0.5 / (lambdaV + lambdaL);
+	return saturate_half(v);
+}
+
+fn d_charlie(roughness: f32, noh: f32) -> f32 {
+	let inv_alpha = 1.0 / roughness;
+	let cos2h = noh * noh;
+	let sin2h = max(1.0 - cos2h, 0.0078125);
+	return (2.0 + inv_alpha) * pow(sin2h, inv_alpha * 0.5) / (2.0 * PI);
+}
+
+fn v_ashikhmin(nov: f32, nol: f32) -> f32 {
+	return saturate_half(1.0 / (4.0 * (nol + nov - nol * nov)));
+}
+
 fn v_kelemen(loh: f32) -> f32 {
 	return saturate_half(0.25 / (loh * loh));
 }
@@ -71,6 +116,39 @@ fn specular_lobe(
 	return d * v * f;
 }
 
+#ifdef ANISOTROPY
+fn anisotropic_lobe(
+	pixel: PbrPixel,
+	l: vec3<f32>,
+	h: vec3<f32>,
+	nol: f32,
+	noh: f32,
+	loh: f32,
+) -> vec3<f32> {
+	let t = pixel.anisotropic_t;
+	let b = pixel.anisotropic_b;
+
+	let min_roughness = 0.089 * 0.089;
+	let at = max(pixel.roughness * (1.0 + pixel.anisotropy), min_roughness);
+	let ab = max(pixel.roughness * (1.0 - pixel.anisotropy), min_roughness);
+
+	let d = d_ggx_anisotropic(at, ab, dot(t, h), dot(b, h), noh);
+	let v = v_smith_anisotropic(at, ab, dot(t, pixel.v), dot(b, pixel.v), dot(t, l), dot(b, l), pixel.nov, nol);
+	let f = fresnel(pixel.f0, loh);
+
+	return d * v * f;
+}
+#endif
+
+#ifdef SHEEN
+fn sheen_lobe(pixel: PbrPixel, nol: f32, noh: f32) -> vec3<f32> {
+	let d = d_charlie(pixel.sheen_roughness, noh);
+	let v = v_ashikhmin(pixel.nov, nol);
+
+	return d * v * pixel.sheen_color;
+}
+#endif
+
 fn clearcoat_lobe(
 	roughness: f32,
 	clearcoat: f32,
@@ -107,8 +185,19 @@ fn light_surface(
 	}
 
 	var diffuse_light = fd_burley(pixel.roughness, pixel.nov, nol, loh) * pixel.diffuse_color;
+#ifdef ANISOTROPY
+	var specular_light = anisotropic_lobe(pixel, light.l, h, nol, noh, loh);
+#endif
+#ifndef ANISOTROPY
 	var specular_light = specular_lobe(pixel.roughness, pixel.f0, pixel.nov, nol, noh, loh);
+#endif
 	
+#ifdef SHEEN
+	diffuse_light *= pixel.sheen_scaling;
+	specular_light *= pixel.sheen_scaling;
+	specular_light += sheen_lobe(pixel, nol, noh);
+#endif
+
 #ifdef TRANSMISSION
 	diffuse_light *= (1.0 - pixel.transmission);
 #endif
@@ -145,7 +234,13 @@ fn light_surface(
 #endif
 
 	let color = color * light.color * light.intensity * light.attenuation;
-	let max_color = pixel.base_color * light.color * light.intensity * light.attenuation * 4.0;
+	var max_albedo = pixel.base_color;
+
+#ifdef SHEEN
+	max_albedo = max(max_albedo, pixel.sheen_color);
+#endif
+
+	let max_color = max_albedo * light.color * light.intensity * light.attenuation * 4.0;
 
 	return clamp(color, vec3<f32>(0.0), max_color);
 }
@@ -159,12 +254,56 @@ fn point_light(
 	let inverse_range_squared = 1.0 / (point_light.range * point_light.range);
 	let range_attenuation = get_distance_attenuation(distance_squared, inverse_range_squared);	
 
+	var shadow: Shadow;
+	shadow.position = pixel.position;
+	shadow.normal = pixel.n;
+	shadow.frag_coord = pixel.frag_coord;
+
+	let l = normalize(light_to_frag);
+
 	var light: Light;
-	light.color = point_light.color;
+	light.color = point_light.color * point_light_texture(point_light, -l);
 	light.intensity = point_light.intensity;
-	light.l = normalize(light_to_frag);
+	light.l = l;
 	light.attenuation = range_attenuation;
-	light.occlusion = 1.0;
+	light.occlusion = point_shadow(point_light, shadow);
+	light.occlusion *= contact_shadow(
+		pixel.frag_coord,
+		pixel.position,
+		l,
+		point_light.contact_shadow_length
+	);
+	return light_surface(pixel, light);
+}
+
+fn spot_light(
+	spot_light: SpotLight,
+	pixel: PbrPixel,
+) -> vec3<f32> {
+	let light_to_frag = spot_light.position - pixel.position;
+	let distance_squared = dot(light_to_frag, light_to_frag);
+	let inverse_range_squared = 1.0 / (spot_light.range * spot_light.range);
+	let range_attenuation = get_distance_attenuation(distance_squared, inverse_range_squared);
+
+	let l = normalize(light_to_frag);
+	let angle_attenuation = get_spot_attenuation(
+		l,
+		spot_light.direction,
+		spot_light.angle_scale,
+		spot_light.angle_offset,
+	);
+
+	var shadow: Shadow;
+	shadow.position = pixel.position;
+	shadow.normal = pixel.n;
+	shadow.frag_coord = pixel.frag_coord;
+
+	var light: Light;
+	light.color = spot_light.color * spot_light_texture(spot_light, -l);
+	light.intensity = spot_light.intensity;
+	light.l = l;
+	light.attenuation = range_attenuation * angle_attenuation;
+	light.occlusion = spot_shadow(spot_light, shadow);
 	return light_surface(pixel, light);
 }
 
@@ -177,7 +316,7 @@ fn directional_light(
 	shadow.normal = pixel.n;
 	shadow.frag_coord = pixel.frag_coord;
 
-	let shadow = directional_shadow(directional_light, shadow, directional_light.view_proj, directional_light.cascade);
+	let shadow = directional_shadow(directional_light, shadow);
 
 	var light: Light;
 	light.color = directional_light.color;
@@ -185,20 +324,159 @@ fn directional_light(
 	light.l = -directional_light.direction;
 	light.attenuation = 1.0;
 	light.occlusion = shadow;
+	light.occlusion *= contact_shadow(
+		pixel.frag_coord,
+		pixel.position,
+		light.l,
+		directional_light.contact_shadow_length
+	);
 	return light_surface(pixel, light);
 }
 
+// shades the convex polygon `points` emitting `intensity` luminance with linearly transformed cosines
+fn area_light(
+	points: array<vec3<f32>, 8>,
+	count: u32,
+	color: vec3<f32>,
+	intensity: f32,
+	pixel: PbrPixel,
+) -> vec3<f32> {
+	let uv = ltc_uv(pixel.perceptual_roughness, pixel.nov);
+	let basis = ltc_basis(pixel.n, pixel.v);
+
+	// the diffuse lobe is a clamped cosine, so only the basis is needed
+	let diffuse = ltc_evaluate(points, count, pixel.position, basis);
+	let specular = ltc_evaluate(points, count, pixel.position, ltc_matrix(uv) * basis);
+
+	let magnitude = ltc_magnitude(uv);
+	let fresnel = pixel.f0 * magnitude.x + (pixel.f90 - pixel.f0) * magnitude.y;
+
+	var diffuse_light = pixel.diffuse_color * diffuse;
+
+#ifdef TRANSMISSION
+	diffuse_light *= (1.0 - pixel.transmission);
+#endif
+
+	let specular_light = fresnel * specular;
+
+	return (diffuse_light + specular_light) * color * intensity;
+}
+
+fn rect_light(
+	rect_light: AreaLight,
+	pixel: PbrPixel,
+) -> vec3<f32> {
+	// rect lights only emit along their -z axis
+	let normal = cross(rect_light.right, rect_light.up);
+	if dot(pixel.position - rect_light.position, normal) > 0.0 {
+		return vec3<f32>(0.0);
+	}
+
+	var points: array<vec3<f32>, 8>;
+	points[0] = rect_light.position - rect_light.right - rect_light.up;
+	points[1] = rect_light.position + rect_light.right - rect_light.up;
+	points[2] = rect_light.position + rect_light.right + rect_light.up;
+	points[3] = rect_light.position - rect_light.right + rect_light.up;
+
+	return area_light(points, 4u, rect_light.color, rect_light.intensity, pixel);
+}
+
+fn disk_light(
+	disk_light: AreaLight,
+	pixel: PbrPixel,
+) -> vec3<f32> {
+	// disk lights only emit along their -z axis
+	let normal = cross(disk_light.right, disk_light.up);
+	if dot(pixel.position - disk_light.position, normal) > 0.0 {
+		return vec3<f32>(0.0);
+	}
+
+	// approximate the disk with an octagon of the same area
+	let scale = sqrt(PI / (2.0 * sqrt(2.0)));
+
+	var points: array<vec3<f32>, 8>;
+	for (var i = 0u; i < 8u; i += 1u) {
+		let angle = f32(i) * PI / 4.0;
+		let offset = disk_light.right * cos(angle) + disk_light.up * sin(angle);
+		points[i] = disk_light.position + offset * scale;
+	}
+
+	return area_light(points, 8u, disk_light.color, disk_light.intensity, pixel);
+}
+
+fn tube_light(
+	tube_light: AreaLight,
+	pixel: PbrPixel,
+) -> vec3<f32> {
+	let axis = normalize(tube_light.right);
+	let to_light = tube_light.position - pixel.position;
+
+	// the tube is approximated by a rectangle facing the pixel
+	var side = cross(axis, to_light);
+	if dot(side, side) < 0.000001 {
+		side = cross(axis, select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(axis.x) > 0.9));
+	}
+	side = normalize(side) * tube_light.radius;
+
+	// extend the rectangle to match the area of the rounded ends
+	let extent = tube_light.right + axis * tube_light.radius * PI / 4.0;
+
+	var points: array<vec3<f32>, 8>;
+	points[0] = tube_light.position - extent - side;
+	points[1] = tube_light.position + extent - side;
+	points[2] = tube_light.position + extent + side;
+	points[3] = tube_light.position - extent + side;
+
+	return area_light(points, 4u, tube_light.color, tube_light.intensity, pixel);
+}
+
 fn pbr_lights(
 	pixel: PbrPixel,
 ) -> vec3<f32> {
 	var color = vec3<f32>(0.0);	
 
-	for (var i = 0u; i < point_light_count; i = i + 1u) {
-		color += point_light(point_lights[i], pixel);
+	// only iterate over the lights assigned to the cluster of the pixel
+	let cluster = get_cluster(pixel.position);
+
+	for (var i = 0u; i < cluster.point_count; i = i + 1u) {
+		let light = point_lights[cluster_light_indices[cluster.offset + i]];
+
+		if light_visible(light.render_layers) {
+			color += point_light(light, pixel);
+		}
 	}
 
-	for (var i = 0u; i < directional_light_count; i = i + 1u) {	
-		color += directional_light(directional_lights[i], pixel);
+	let spot_offset = cluster.offset + cluster.point_count;
+	for (var i = 0u; i < cluster.spot_count; i = i + 1u) {
+		let light = spot_lights[cluster_light_indices[spot_offset + i]];
+
+		if light_visible(light.render_layers) {
+			color += spot_light(light, pixel);
+		}
+	}
+
+	for (var i = 0u; i < light_counts.directional_count; i = i + 1u) {	
+		let light = directional_lights[i];
+
+		if light_visible(light.render_layers) {
+			color += directional_light(light, pixel);
+		}
+	}
+
+	for (var i = 0u; i < light_counts.area_count; i = i + 1u) {
+		let light = area_lights[i];
+
+		if !light_visible(light.render_layers) {
+			continue;
+		}
+
+		if light.kind == AREA_LIGHT_RECT {
+			color += rect_light(light, pixel);
+		} else if light.kind == AREA_LIGHT_DISK {
+			color += disk_light(light, pixel);
+		} else {
+			color += tube_light(light, pixel);
+		}
 	}
 
 	return color;
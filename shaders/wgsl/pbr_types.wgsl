@@ -2,6 +2,10 @@
 #include <lumi/mesh.wgsl>
 #include <lumi/integrated_brdf.wgsl>
 
+// the emissive factor applied on top of the emissive of materials, must match
+// `PBR_EMISSIVE_FACTOR` in lumi-material
+let PBR_EMISSIVE_FACTOR = 8.0;
+
 struct Pbr {
 	frag_coord: vec4<f32>,
 	w_position: vec3<f32>,	
@@ -12,9 +16,11 @@ struct Pbr {
 	metallic: f32,
 	roughness: f32,	
 	reflectance: f32,
+	specular_color: vec3<f32>,
 	emissive: vec3<f32>,
 	emissive_factor: f32,
 	emissive_exposure_compensation: f32,
+	occlusion: f32,
 
 #ifdef CLEARCOAT
 	clearcoat: f32,
@@ -22,6 +28,16 @@ struct Pbr {
 	clearcoat_normal: vec3<f32>,
 #endif
 
+#ifdef SHEEN
+	sheen_color: vec3<f32>,
+	sheen_roughness: f32,
+#endif
+
+#ifdef ANISOTROPY
+	anisotropy: f32,
+	anisotropy_direction: vec3<f32>,
+#endif
+
 #ifdef THICKNESS
 	thickness: f32,
 #endif
@@ -36,6 +52,10 @@ struct Pbr {
 	subsurface_power: f32,
 	subsurface_color: vec3<f32>,
 #endif
+
+#ifdef LIGHTMAP
+	lightmap: vec3<f32>,
+#endif
 }
 
 fn default_pbr(mesh: Mesh) -> Pbr {
@@ -50,9 +70,11 @@ fn default_pbr(mesh: Mesh) -> Pbr {
 	out.metallic = 0.01;
 	out.roughness = 0.089;	
 	out.reflectance = 0.5;
+	out.specular_color = vec3<f32>(1.0);
 	out.emissive = vec3<f32>(0.0);
-	out.emissive_factor = 8.0;
+	out.emissive_factor = PBR_EMISSIVE_FACTOR;
 	out.emissive_exposure_compensation = 0.0;
+	out.occlusion = 1.0;
 
 #ifdef CLEARCOAT
 	out.clearcoat = 0.0;
@@ -60,6 +82,16 @@ fn default_pbr(mesh: Mesh) -> Pbr {
 	out.clearcoat_normal = mesh.w_normal;
 #endif
 
+#ifdef SHEEN
+	out.sheen_color = vec3<f32>(0.0);
+	out.sheen_roughness = 0.089;
+#endif
+
+#ifdef ANISOTROPY
+	out.anisotropy = 0.0;
+	out.anisotropy_direction = mesh.w_tangent;
+#endif
+
 #ifdef TRANSMISSION
 	out.thickness = 1.0;
 	out.transmission = 1.0;
@@ -72,6 +104,10 @@ fn default_pbr(mesh: Mesh) -> Pbr {
 	out.subsurface_color = vec3<f32>(1.0);
 #endif
 
+#ifdef LIGHTMAP
+	out.lightmap = vec3<f32>(0.0);
+#endif
+
 	return out;
 }
 
@@ -103,12 +139,28 @@ struct PbrPixel {
 	f90: f32,
     dfg: vec3<f32>,
 
+	occlusion: f32,
+
 #ifdef CLEARCOAT
     clearcoat: f32,
     clearcoat_perceptual_roughness: f32,
     clearcoat_roughness: f32,
 #endif
 
+#ifdef SHEEN
+	sheen_color: vec3<f32>,
+	sheen_perceptual_roughness: f32,
+	sheen_roughness: f32,
+	sheen_dfg: f32,
+	sheen_scaling: f32,
+#endif
+
+#ifdef ANISOTROPY
+	anisotropy: f32,
+	anisotropic_t: vec3<f32>,
+	anisotropic_b: vec3<f32>,
+#endif
+
 #ifdef THICKNESS
 	thickness: f32,
 #endif
@@ -124,14 +176,23 @@ struct PbrPixel {
 	subsurface_power: f32,
 	subsurface_color: vec3<f32>,
 #endif
+
+#ifdef LIGHTMAP
+	lightmap: vec3<f32>,
+#endif
 }
 
 fn linear_to_perceptual_roughness(roughness: f32) -> f32 {
 	return sqrt(roughness);
 }
 
-fn compute_f0(base_color: vec3<f32>, metallic: f32, reflectance: f32) -> vec3<f32> {
-	let a = 0.16 * reflectance * reflectance * (1.0 - metallic);
+fn compute_f0(
+	base_color: vec3<f32>,
+	metallic: f32,
+	reflectance: f32,
+	specular_color: vec3<f32>
+) -> vec3<f32> {
+	let a = 0.16 * reflectance * reflectance * (1.0 - metallic) * specular_color;
 	let b = base_color * metallic;
 	return a + b;
 }
@@ -177,11 +238,32 @@ fn get_pbr_pixel(in: Pbr) -> PbrPixel {
 	pixel.clearcoat_perceptual_roughness = linear_to_perceptual_roughness(pixel.clearcoat_roughness);
 #endif
 
-	pixel.f0 = compute_f0(in.base_color.rgb, in.metallic, in.reflectance);
+	pixel.f0 = compute_f0(in.base_color.rgb, in.metallic, in.reflectance, in.specular_color);
 	pixel.f90 = compute_f90(pixel.f0);
 
 	pixel.dfg = sample_brdf(pixel.perceptual_roughness, pixel.nov);
 
+	// sheen
+#ifdef SHEEN
+	pixel.sheen_color = in.sheen_color;
+	pixel.sheen_roughness = clamp(in.sheen_roughness, min_roughness, 0.99);
+	pixel.sheen_perceptual_roughness = linear_to_perceptual_roughness(pixel.sheen_roughness);
+	pixel.sheen_dfg = sample_sheen_brdf(pixel.sheen_perceptual_roughness, pixel.nov);
+
+	// energy lost to the sheen layer, from the max albedo of the sheen lobe
+	let sheen_max = max(in.sheen_color.r, max(in.sheen_color.g, in.sheen_color.b));
+	pixel.sheen_scaling = clamp(1.0 - sheen_max * pixel.sheen_dfg, 0.0, 1.0);
+#endif
+
+	// anisotropy
+#ifdef ANISOTROPY
+	pixel.anisotropy = clamp(in.anisotropy, -1.0, 1.0);
+	pixel.anisotropic_t = normalize(in.anisotropy_direction - pixel.n * dot(in.anisotropy_direction, pixel.n));
+	pixel.anisotropic_b = cross(pixel.n, pixel.anisotropic_t);
+#endif
+
+	pixel.occlusion = in.occlusion;
+
 #ifdef THICKNESS
 	pixel.thickness = in.thickness;
 #endif
@@ -202,5 +284,9 @@ fn get_pbr_pixel(in: Pbr) -> PbrPixel {
 	pixel.subsurface_color = in.subsurface_color;
 #endif
 
+#ifdef LIGHTMAP
+	pixel.lightmap = in.lightmap;
+#endif
+
 	return pixel;
 }
@@ -0,0 +1,18 @@
+#include <lumi/light.wgsl>
+
+// a reflection probe or an irradiance volume
+struct Probe {
+	probe_from_world: mat4x4<f32>,
+	position: vec3<f32>,
+	shape: u32,
+	half_extents: vec3<f32>,
+	radius: f32,
+	resolution: vec3<f32>,
+	blend_distance: f32,
+	intensity: f32,
+	layer: i32,
+}
+
+// the reflection probes followed by the irradiance volumes
+@group(0) @binding(0)
+var<storage, read> probes: array<Probe>;
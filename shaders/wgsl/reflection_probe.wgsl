@@ -0,0 +1,80 @@
+#include <lumi/probe.wgsl>
+
+@group(0) @binding(0)
+var reflection_probe_textures: texture_cube_array<f32>;
+
+@group(0) @binding(0)
+var reflection_probe_sampler: sampler;
+
+// how much of the probe applies at `local`, fading out over the blend distance at the edges
+fn reflection_probe_weight(probe: Probe, local: vec3<f32>) -> f32 {
+	if probe.shape == 0u {
+		let distance = probe.half_extents - abs(local);
+		return saturate(min(distance.x, min(distance.y, distance.z)) / probe.blend_distance);
+	} else {
+		return saturate((probe.radius - length(local)) / probe.blend_distance);
+	}
+}
+
+// the distance along `direction` from `origin` to the edge of the volume, in local space
+fn reflection_probe_intersect(probe: Probe, origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+	if probe.shape == 0u {
+		let a = (probe.half_extents - origin) / direction;
+		let b = (-probe.half_extents - origin) / direction;
+		let far = max(a, b);
+		return min(far.x, min(far.y, far.z));
+	} else {
+		let a = dot(direction, direction);
+		let b = dot(origin, direction);
+		let c = dot(origin, origin) - probe.radius * probe.radius;
+		return (-b + sqrt(max(b * b - a * c, 0.0))) / a;
+	}
+}
+
+// samples the reflection probes containing `position` in the direction `r`, parallax corrected
+// against the volume of each probe
+//
+// returns the radiance in rgb and how much of the environment is covered by probes in a
+fn reflection_probes_indirect(
+	position: vec3<f32>,
+	r: vec3<f32>,
+	perceptual_roughness: f32,
+) -> vec4<f32> {
+	let levels = f32(textureNumLevels(reflection_probe_textures) - 1);
+	let lod = perceptual_roughness * levels;
+
+	var radiance = vec3<f32>(0.0);
+	var remaining = 1.0;
+
+	for (var i = 0u; i < light_counts.reflection_probe_count; i += 1u) {
+		let probe = probes[i];
+
+		let local = (probe.probe_from_world * vec4<f32>(position, 1.0)).xyz;
+		let weight = reflection_probe_weight(probe, local);
+
+		if weight <= 0.0 {
+			continue;
+		}
+
+		let local_r = (probe.probe_from_world * vec4<f32>(r, 0.0)).xyz;
+		let t = reflection_probe_intersect(probe, local, local_r);
+		let direction = position + r * t - probe.position;
+
+		let color = textureSampleLevel(
+			reflection_probe_textures,
+			reflection_probe_sampler,
+			direction,
+			probe.layer,
+			lod
+		).rgb;
+
+		radiance += color * probe.intensity * weight * remaining;
+		remaining *= 1.0 - weight;
+
+		if remaining <= 0.0 {
+			break;
+		}
+	}
+
+	return vec4<f32>(radiance, 1.0 - remaining);
+}
@@ -1,8 +1,16 @@
 #include <lumi/poisson.wgsl>
 #include <lumi/light.wgsl>
 
+struct ShadowTile {
+	view_proj: mat4x4<f32>,
+	rect: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var shadow_atlas: texture_depth_2d;
+
 @group(0) @binding(0)
-var directional_shadow_maps: texture_depth_2d_array;
+var<storage, read> shadow_tiles: array<ShadowTile>;
 
 @group(0) @binding(0)
 var shadow_map_sampler: sampler;
@@ -43,32 +51,23 @@ fn rotate(pos: vec2<f32>, trig: vec2<f32>) -> vec2<f32> {
 	);
 }
 
-fn valid_cascade(light_space: vec2<f32>) -> bool {	
-	let m = max(abs(light_space.x), abs(light_space.y));
-	return m < 8.0;
+fn shadow_tile_uv(tile: ShadowTile, ndc: vec2<f32>) -> vec2<f32> {
+	let uv = ndc * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+	return tile.rect.xy + uv * tile.rect.zw;
 }
 
-fn sample_cascade(light_space: vec2<f32>, index: u32) -> f32 {
-	var cascade = 0u;
-	let m = max(abs(light_space.x), abs(light_space.y));
-
-	if m > 1.0 { cascade = 1u; }
-	if m > 2.0 { cascade = 2u; }
-	if m > 4.0 { cascade = 3u; }
-
-	let d = pow(2.0, f32(cascade));
-
-	let uv = light_space / d * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+// samples the atlas clamped to `tile`, to prevent filtering from bleeding into other tiles
+fn sample_shadow_tile(tile: ShadowTile, uv: vec2<f32>) -> f32 {
+	let half_texel = 0.5 / f32(textureDimensions(shadow_atlas).x);
+	let min_uv = tile.rect.xy + half_texel;
+	let max_uv = tile.rect.xy + tile.rect.zw - half_texel;
 
-	let index = i32(index + cascade);
-	let depth = textureSample(directional_shadow_maps, shadow_map_sampler, uv, index);
-
-	return depth;
+	return textureSample(shadow_atlas, shadow_map_sampler, clamp(uv, min_uv, max_uv));
 }
 
 fn directional_find_blocker(
-	light_space: vec2<f32>,
-	index: u32,
+	tile: ShadowTile,
+	uv: vec2<f32>,
 	z0: f32,
 	bias: f32,
 	plane_bias: vec2<f32>,
@@ -85,9 +84,7 @@ fn directional_find_blocker(
 		var offset = poisson_disk[i] * radius;
 		offset = rotate(offset, trig);
 
-		let offset_light_space = light_space + offset;
-
-		let depth = sample_cascade(offset_light_space, index);
+		let depth = sample_shadow_tile(tile, uv + offset);
 		let bias = dot(offset, plane_bias);
 
 		if biased_depth + bias > depth {
@@ -102,8 +99,8 @@ fn directional_find_blocker(
 }
 
 fn directional_pcf_filter(
-	light_space: vec2<f32>,
-	index: u32,
+	tile: ShadowTile,
+	uv: vec2<f32>,
 	z0: f32,
 	bias: f32,
 	plane_bias: vec2<f32>,
@@ -119,9 +116,7 @@ fn directional_pcf_filter(
 		var offset = poisson_disk[i] * filter_radius;
 		offset = rotate(offset, trig);
 
-		let offset_light_space = light_space + offset;
-
-		let depth = sample_cascade(offset_light_space, index);
+		let depth = sample_shadow_tile(tile, uv + offset);
 		let bias = dot(offset, plane_bias);
 
 		if biased_depth + bias < depth {
@@ -134,18 +129,22 @@ fn directional_pcf_filter(
 
 fn directional_pcss(
 	light: DirectionalLight,
-	light_space: vec2<f32>,
-	index: u32,
+	tile: ShadowTile,
+	cascade_size: f32,
+	uv: vec2<f32>,
 	z: f32,
 	bias: f32,
 	plane_bias: vec2<f32>,
 	z_vs: f32,
 	trig: vec2<f32>,
 ) -> f32 {
-	let search_radius = light.softness / light.size;
+	// softness is given in world space, cascades cover `cascade_size` meters of the tile
+	let uv_scale = tile.rect.z / (32.0 * cascade_size);
+
+	let search_radius = light.softness * uv_scale;
 	let blocker = directional_find_blocker(
-		light_space,
-		index,
+		tile,
+		uv,
 		z,
 		bias,
 		plane_bias,
@@ -163,12 +162,12 @@ fn directional_pcss(
 	var penumbra = penumbra_radius_uv(z_vs, avg_z) * light.softness / 8.0 + 0.1;
 	penumbra = 1.0 - pow(1.0 - penumbra, light.falloff);
 	
-	var filter_radius = vec2<f32>(penumbra - 0.015 * light.softness) / light.size;
+	var filter_radius = vec2<f32>(penumbra - 0.015 * light.softness) * uv_scale;
 	filter_radius = min(vec2<f32>(search_radius), filter_radius);
 
 	return directional_pcf_filter(
-		light_space,
-		index,
+		tile,
+		uv,
 		z,
 		bias,
 		plane_bias,
@@ -178,33 +177,183 @@ fn directional_pcss(
 	);
 }
 
-fn directional_shadow(light: DirectionalLight, shadow: Shadow, view_proj: mat4x4<f32>, index: u32) -> f32 {
-	let light_space = view_proj * vec4<f32>(shadow.position, 1.0);
+fn directional_cascade_shadow(
+	light: DirectionalLight,
+	tile: ShadowTile,
+	cascade_size: f32,
+	light_space: vec3<f32>,
+	shadow: Shadow,
+) -> f32 {
+	let uv = shadow_tile_uv(tile, light_space.xy);
+	let plane_bias = plane_bias(vec3<f32>(uv, light_space.z));
+
+	let z = light_space.z;
+	let z_vs = z * light.depth;
+
+	let noise = shadow_noise(shadow.frag_coord.xyz);
+	let angle = noise * 2.0 * 3.14159265359;
+	let trig = vec2<f32>(cos(angle), sin(angle));
+
+	let bias_scale = 0.005;
+	let bias = 1.0 / light.depth * bias_scale;
+
+	return directional_pcss(light, tile, cascade_size, uv, z, bias, plane_bias, z_vs, trig);
+}
+
+fn directional_shadow(light: DirectionalLight, shadow: Shadow) -> f32 {
+	let atlas_size = f32(textureDimensions(shadow_atlas).x);
 
-	if light_space.w < 0.0 {
+	for (var i = 0u; i < light.cascade_count; i += 1u) {
+		let tile = shadow_tiles[light.cascade + i];
+
+		if tile.rect.z == 0.0 {
+			continue;
+		}
+
+		// the orthographic projection maps `cascade_size` meters to the width of the tile
+		let view_proj = tile.view_proj;
+		let scale = length(vec3<f32>(view_proj[0].x, view_proj[1].x, view_proj[2].x));
+		let cascade_size = 2.0 / scale;
+
+		let texel_size = cascade_size / (tile.rect.z * atlas_size);
+		let normal_offset = shadow.normal * light.normal_bias * texel_size;
+
+		let light_space = view_proj * vec4<f32>(shadow.position + normal_offset, 1.0);
+		let light_space = light_space.xyz / light_space.w;
+
+		if light_space.z < 0.0 || light_space.z > 1.0 {
+			continue;
+		}
+
+		// leave a margin at the edge of the cascade for filtering
+		if abs(light_space.x) < 0.95 && abs(light_space.y) < 0.95 {
+			return directional_cascade_shadow(light, tile, cascade_size, light_space, shadow);
+		}
+	}
+
+	return 1.0;
+}
+
+fn tile_pcf_filter(
+	tile: ShadowTile,
+	uv: vec2<f32>,
+	z: f32,
+	filter_radius: f32,
+	trig: vec2<f32>,
+	sample_count: u32,
+) -> f32 {
+	var sum = 0.0;
+
+	for (var i = 0u; i < sample_count; i += 1u) {
+		var offset = poisson_disk[i] * filter_radius;
+		offset = rotate(offset, trig);
+
+		let depth = sample_shadow_tile(tile, uv + offset);
+
+		if z < depth {
+			sum += 1.0;
+		}
+	}
+
+	return sum / f32(sample_count);
+}
+
+fn spot_shadow(light: SpotLight, shadow: Shadow) -> f32 {
+	if light.shadow_index < 0 {
+		return 1.0;
+	}
+
+	let tile = shadow_tiles[light.shadow_index];
+
+	if tile.rect.z == 0.0 {
+		return 1.0;
+	}
+
+	let atlas_size = f32(textureDimensions(shadow_atlas).x);
+
+	// approximate size of a texel at `distance`, exact for a 90 degree cone
+	let distance = length(light.position - shadow.position);
+	let texel_size = 2.0 * distance / (tile.rect.z * atlas_size);
+	let normal_offset = shadow.normal * light.normal_bias * texel_size;
+	let light_space = tile.view_proj * vec4<f32>(shadow.position + normal_offset, 1.0);
+
+	if light_space.w <= 0.0 {
 		return 1.0;
 	}
 
-	let plane_bias = plane_bias(light_space.xyz);
 	let light_space = light_space.xyz / light_space.w;
 
 	if light_space.z < 0.0 || light_space.z > 1.0 {
 		return 1.0;
 	}
-	
-	if !valid_cascade(light_space.xy) {
+
+	if abs(light_space.x) > 1.0 || abs(light_space.y) > 1.0 {
 		return 1.0;
 	}
 
-	let z = light_space.z;
-	let z_vs = z * light.depth;
+	let uv = shadow_tile_uv(tile, light_space.xy);
 
 	let noise = shadow_noise(shadow.frag_coord.xyz);
 	let angle = noise * 2.0 * 3.14159265359;
 	let trig = vec2<f32>(cos(angle), sin(angle));
 
-	let bias_scale = 0.005;
-	let bias = 1.0 / light.depth * bias_scale;
+	let bias = 0.00005;
+	let filter_radius = light.softness / atlas_size;
+
+	return tile_pcf_filter(tile, uv, light_space.z - bias, filter_radius, trig, 16u);
+}
+
+// returns the cube face looking along the major axis of `v`, in the order +X, -X, +Y, -Y, +Z, -Z
+fn cube_face(v: vec3<f32>) -> u32 {
+	let a = abs(v);
+
+	if a.x >= a.y && a.x >= a.z {
+		return select(1u, 0u, v.x > 0.0);
+	}
+
+	if a.y >= a.z {
+		return select(3u, 2u, v.y > 0.0);
+	}
+
+	return select(5u, 4u, v.z > 0.0);
+}
+
+fn point_shadow(light: PointLight, shadow: Shadow) -> f32 {
+	if light.shadow_index < 0 {
+		return 1.0;
+	}
+
+	let atlas_size = f32(textureDimensions(shadow_atlas).x);
+	let light_to_frag = shadow.position - light.position;
+
+	// the faces of the cube are stored in consecutive tiles
+	let face = cube_face(light_to_frag);
+	let tile = shadow_tiles[u32(light.shadow_index) + face];
+
+	if tile.rect.z == 0.0 {
+		return 1.0;
+	}
+
+	// size of a texel at `distance` on a 90 degree cube face
+	let distance = length(light_to_frag);
+	let texel_size = 2.0 * distance / (tile.rect.z * atlas_size);
+	let normal_offset = shadow.normal * light.normal_bias * texel_size;
+	let light_space = tile.view_proj * vec4<f32>(shadow.position + normal_offset, 1.0);
+
+	if light_space.w <= 0.0 || light_space.w > light.range {
+		return 1.0;
+	}
+
+	let light_space = light_space.xyz / light_space.w;
+
+	let uv = shadow_tile_uv(tile, light_space.xy);
+
+	let noise = shadow_noise(shadow.frag_coord.xyz);
+	let angle = noise * 2.0 * 3.14159265359;
+	let trig = vec2<f32>(cos(angle), sin(angle));
+
+	let bias = 0.00005;
+	let filter_radius = light.softness / atlas_size;
 
-	return directional_pcss(light, light_space.xy, index, z, bias, plane_bias, z_vs, trig);
+	return tile_pcf_filter(tile, uv, light_space.z - bias, filter_radius, trig, 16u);
 }
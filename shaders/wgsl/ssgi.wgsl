@@ -0,0 +1,57 @@
+#include <lumi/camera.wgsl>
+
+struct GlobalIllumination {
+	radius: f32,
+	thickness: f32,
+	intensity: f32,
+	history_weight: f32,
+	ray_count: u32,
+	step_count: u32,
+	frame: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> global_illumination: GlobalIllumination;
+
+@group(0) @binding(0)
+var<uniform> previous_camera: Camera;
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_multisampled_2d;
+#endif
+
+#ifndef MULTISAMPLED
+@group(0) @binding(0)
+var depth_texture: texture_depth_2d;
+#endif
+
+fn load_depth(coord: vec2<i32>) -> f32 {
+	let size = vec2<i32>(textureDimensions(depth_texture));
+	let clamped = clamp(coord, vec2<i32>(0), size - 1);
+
+	return textureLoad(depth_texture, clamped, 0);
+}
+
+// reconstructs the view space position at pixel `coord`
+fn view_position(coord: vec2<f32>) -> vec3<f32> {
+	let size = vec2<f32>(textureDimensions(depth_texture));
+	// keep the far plane at a finite distance
+	let depth = min(load_depth(vec2<i32>(coord)), 0.99999);
+
+	let uv = (floor(coord) + 0.5) / size;
+	let clip = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+	let world = clip_to_world(clip);
+
+	return (camera.inverse_view * vec4<f32>(world, 1.0)).xyz;
+}
+
+// the uv of `world` in the previous frame
+fn previous_uv(world: vec3<f32>) -> vec2<f32> {
+	let clip = previous_camera.view_proj * vec4<f32>(world, 1.0);
+	return clip.xy * (vec2<f32>(0.5, -0.5) / clip.w) + 0.5;
+}
+
+fn interleaved_gradient_noise(coord: vec2<f32>) -> f32 {
+	return fract(52.9829189 * fract(dot(coord, vec2<f32>(0.06711056, 0.00583715))));
+}
@@ -0,0 +1,64 @@
+#include <lumi/fullscreen.wgsl>
+#include <lumi/ssgi.wgsl>
+
+@group(0) @binding(0)
+var gi_noisy_texture: texture_2d<f32>;
+
+@group(0) @binding(0)
+var gi_history_texture: texture_2d<f32>;
+
+@group(0) @binding(0)
+var gi_history_sampler: sampler;
+
+let DENOISE_RADIUS: i32 = 2;
+
+// denoises the traced light and blends it with the reprojected light of the previous frames
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = vec2<i32>(fs.v_position.xy);
+	let size = vec2<i32>(textureDimensions(gi_noisy_texture));
+
+	let center = textureLoad(gi_noisy_texture, coord, 0);
+	let depth = center.a;
+
+	if depth <= 0.0 {
+		return vec4<f32>(0.0);
+	}
+
+	var gi = vec3<f32>(0.0);
+	var total_weight = 0.0;
+
+	for (var y = -DENOISE_RADIUS; y <= DENOISE_RADIUS; y += 1) {
+		for (var x = -DENOISE_RADIUS; x <= DENOISE_RADIUS; x += 1) {
+			let sample_coord = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+			let sample = textureLoad(gi_noisy_texture, sample_coord, 0);
+
+			// only blur across surfaces at a similar depth
+			let difference = abs(sample.a - depth) / depth;
+			let weight = saturate(1.0 - difference * 20.0);
+
+			gi += sample.rgb * weight;
+			total_weight += weight;
+		}
+	}
+
+	gi /= max(total_weight, 0.0001);
+
+	let position = view_position(vec2<f32>(coord));
+	let world = (camera.view * vec4<f32>(position, 1.0)).xyz;
+	let uv = previous_uv(world);
+	let previous_depth = -(previous_camera.inverse_view * vec4<f32>(world, 1.0)).z;
+
+	let history = textureSampleLevel(gi_history_texture, gi_history_sampler, uv, 0.0);
+
+	// discard the history when the surface wasn't visible in the previous frame
+	var history_weight = global_illumination.history_weight;
+	if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+		history_weight = 0.0;
+	}
+	if abs(history.a - previous_depth) > previous_depth * 0.05 {
+		history_weight = 0.0;
+	}
+
+	return vec4<f32>(mix(gi, history.rgb, history_weight), depth);
+}
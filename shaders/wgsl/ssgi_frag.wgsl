@@ -0,0 +1,131 @@
+#include <lumi/fullscreen.wgsl>
+#include <lumi/ssgi.wgsl>
+
+// the mip chain of the previous frame
+@group(0) @binding(0)
+var color_texture: texture_2d<f32>;
+
+@group(0) @binding(0)
+var color_sampler: sampler;
+
+let PI: f32 = 3.141592653589793;
+
+// reconstructs the view space normal from the neighbouring depth, picking the smallest
+// derivative on each axis to avoid smearing normals across edges
+fn view_normal(coord: vec2<f32>, position: vec3<f32>) -> vec3<f32> {
+	let left = view_position(coord - vec2<f32>(1.0, 0.0));
+	let right = view_position(coord + vec2<f32>(1.0, 0.0));
+	let top = view_position(coord - vec2<f32>(0.0, 1.0));
+	let bottom = view_position(coord + vec2<f32>(0.0, 1.0));
+
+	var dx = right - position;
+	if abs(position.z - left.z) < abs(right.z - position.z) {
+		dx = position - left;
+	}
+
+	var dy = position - bottom;
+	if abs(position.z - top.z) < abs(bottom.z - position.z) {
+		dy = top - position;
+	}
+
+	return normalize(cross(dx, dy));
+}
+
+// projects the view space `position` to pixel coordinates
+fn view_to_screen(position: vec3<f32>, size: vec2<f32>) -> vec2<f32> {
+	let clip = camera.view_proj * camera.view * vec4<f32>(position, 1.0);
+	return (clip.xy * (vec2<f32>(0.5, -0.5) / clip.w) + 0.5) * size;
+}
+
+// a direction around `n`, distributed by the cosine of the angle to `n`
+fn sample_cosine(n: vec3<f32>, u: f32, v: f32) -> vec3<f32> {
+	let phi = 2.0 * PI * u;
+	let r = sqrt(v);
+
+	let up = select(vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(1.0, 0.0, 0.0), abs(n.z) > 0.999);
+	let tangent = normalize(cross(up, n));
+	let bitangent = cross(n, tangent);
+
+	return normalize(tangent * cos(phi) * r + bitangent * sin(phi) * r + n * sqrt(1.0 - v));
+}
+
+// traces cosine distributed rays against the depth buffer and gathers the light of the previous
+// frame where they hit, the average radiance of the rays is the irradiance divided by pi
+@fragment
+fn fragment(fs: Fullscreen) -> @location(0) vec4<f32> {
+	let coord = floor(fs.v_position.xy);
+	let depth = load_depth(vec2<i32>(coord));
+
+	// the sky doesn't receive any light
+	if depth >= 1.0 {
+		return vec4<f32>(0.0);
+	}
+
+	let size = vec2<f32>(textureDimensions(depth_texture));
+	let position = view_position(coord);
+	let normal = view_normal(coord, position);
+	let start = view_to_screen(position, size);
+
+	let levels = f32(textureNumLevels(color_texture) - 1);
+	let exposure = max(previous_camera.exposure, 0.0000001);
+
+	let ray_count = max(global_illumination.ray_count, 1u);
+	let step_count = max(global_illumination.step_count, 1u);
+
+	// vary the noise every frame so the accumulation converges
+	let frame_offset = vec2<f32>(5.588238, 47.0) * f32(global_illumination.frame % 64u);
+	let noise_step = interleaved_gradient_noise(coord + frame_offset);
+
+	var radiance = vec3<f32>(0.0);
+
+	for (var ray = 0u; ray < ray_count; ray += 1u) {
+		let ray_offset = frame_offset + vec2<f32>(f32(ray) * 13.0, f32(ray) * 7.0);
+		let u = interleaved_gradient_noise(coord + ray_offset + vec2<f32>(17.0, 3.0));
+		let v = interleaved_gradient_noise(coord + ray_offset + vec2<f32>(29.0, 61.0));
+		let direction = sample_cosine(normal, u, v);
+
+		for (var i = 0u; i < step_count; i += 1u) {
+			var t = (f32(i) + noise_step) / f32(step_count);
+
+			// distribute samples closer to the origin
+			t *= t;
+
+			let sample_position = position + direction * t * global_illumination.radius;
+
+			// rays behind the camera can't be traced
+			if sample_position.z >= 0.0 {
+				break;
+			}
+
+			let screen = view_to_screen(sample_position, size);
+
+			if any(screen < vec2<f32>(0.0)) || any(screen >= size) {
+				break;
+			}
+
+			let scene = view_position(screen);
+			let difference = scene.z - sample_position.z;
+
+			// the ray is behind a surface in the depth buffer
+			if difference > 0.001 * -sample_position.z && difference < global_illumination.thickness {
+				let world = (camera.view * vec4<f32>(scene, 1.0)).xyz;
+				let uv = previous_uv(world);
+
+				if all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) {
+					// sample blurrier mips as the samples spread apart
+					let spacing = distance(screen, start) / f32(step_count);
+					let lod = clamp(log2(max(spacing, 1.0)), 0.0, levels);
+
+					let color = textureSampleLevel(color_texture, color_sampler, uv, lod).rgb;
+					radiance += color / exposure;
+				}
+
+				break;
+			}
+		}
+	}
+
+	radiance *= global_illumination.intensity / f32(ray_count);
+
+	return vec4<f32>(radiance, -position.z);
+}
@@ -1,5 +1,164 @@
+#include <lumi/camera.wgsl>
+#include <lumi/hi_z.wgsl>
+
+struct ScreenSpaceReflection {
+	enabled: u32,
+	max_steps: u32,
+	thickness: f32,
+	max_distance: f32,
+	max_roughness: f32,
+}
+
+struct ScreenSpace {
+	reflection: ScreenSpaceReflection,
+	// the camera `ssr_texture` was rendered with
+	previous_view_proj: mat4x4<f32>,
+	previous_exposure: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> screen_space: ScreenSpace;
+
 @group(0) @binding(0)
 var ssr_texture: texture_2d<f32>;
 
 @group(0) @binding(0)
 var ssr_sampler: sampler;
+
+// projects `position` to the screen, with xy in pixels and z the depth
+fn ssr_world_to_screen(position: vec3<f32>, size: vec2<f32>) -> vec3<f32> {
+	let clip = camera.view_proj * vec4<f32>(position, 1.0);
+	let ndc = clip.xyz / clip.w;
+
+	return vec3<f32>((ndc.xy * vec2<f32>(0.5, -0.5) + 0.5) * size, ndc.z);
+}
+
+fn ssr_screen_to_world(screen: vec3<f32>, size: vec2<f32>) -> vec3<f32> {
+	let uv = screen.xy / size;
+	return clip_to_world(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, screen.z, 1.0));
+}
+
+fn ssr_view_depth(screen: vec3<f32>, size: vec2<f32>) -> f32 {
+	let world = ssr_screen_to_world(screen, size);
+	return -(camera.inverse_view * vec4<f32>(world, 1.0)).z;
+}
+
+// traces a ray through the hi-z, stepping over cells in front of the ray on coarse mips and
+// refining on finer mips where the ray might hit, returns the screen position of the hit in xyz
+// and one in w if anything was hit
+fn trace_hi_z(position: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+	let size = vec2<f32>(textureDimensions(hi_z_texture));
+	let max_level = textureNumLevels(hi_z_texture) - 1;
+
+	// keep the end of the ray in front of the near plane
+	var distance = screen_space.reflection.max_distance;
+	let clip_start = camera.view_proj * vec4<f32>(position, 1.0);
+	let clip_direction = camera.view_proj * vec4<f32>(direction, 0.0);
+	if clip_direction.z < 0.0 {
+		distance = min(distance, -clip_start.z / clip_direction.z * 0.99);
+	}
+
+	let start = ssr_world_to_screen(position, size);
+	let end = ssr_world_to_screen(position + direction * distance, size);
+	let delta = end - start;
+
+	let pixel_length = max(length(delta.xy), 1.0);
+	let safe_delta = select(delta.xy, vec2<f32>(0.00001), abs(delta.xy) < vec2<f32>(0.00001));
+
+	// start a pixel away from the surface to avoid hitting it
+	var t = 1.0 / pixel_length;
+	var level = 0;
+
+	for (var i = 0u; i < screen_space.reflection.max_steps; i += 1u) {
+		if t >= 1.0 {
+			break;
+		}
+
+		let p = start + delta * t;
+
+		if any(p.xy < vec2<f32>(0.0)) || any(p.xy >= size) {
+			break;
+		}
+
+		let cell_size = exp2(f32(level));
+		let cell = floor(p.xy / cell_size);
+		let cell_depth = textureLoad(hi_z_texture, vec2<i32>(cell), level).r;
+
+		// the t at which the ray leaves the cell
+		let boundary = (cell + step(vec2<f32>(0.0), delta.xy)) * cell_size;
+		let t_cell = (boundary - start.xy) / safe_delta;
+		let t_exit = min(t_cell.x, t_cell.y) + 0.01 / pixel_length;
+
+		// the t at which the ray reaches the closest depth in the cell
+		var t_depth = 2.0;
+		if delta.z > 0.0 {
+			t_depth = (cell_depth - start.z) / delta.z;
+		}
+
+		if p.z >= cell_depth || t_depth < t_exit {
+			t = max(t, t_depth);
+
+			if level > 0 {
+				level -= 1;
+				continue;
+			}
+
+			let hit = start + delta * t;
+			let surface = vec3<f32>(hit.xy, cell_depth);
+
+			if ssr_view_depth(hit, size) - ssr_view_depth(surface, size) < screen_space.reflection.thickness {
+				return vec4<f32>(hit, 1.0);
+			}
+
+			// the ray passed behind the surface
+			t = t_exit;
+		} else {
+			t = t_exit;
+			level = min(level + 1, max_level);
+		}
+	}
+
+	return vec4<f32>(0.0);
+}
+
+// the light of the previous frame reflected at `position` in the direction `r`, widening the cone
+// of the reflection with `roughness`, the alpha is the weight of the reflection
+fn screen_space_reflections(position: vec3<f32>, r: vec3<f32>, roughness: f32) -> vec4<f32> {
+	let max_roughness = screen_space.reflection.max_roughness;
+
+	if screen_space.reflection.enabled == 0u || roughness >= max_roughness {
+		return vec4<f32>(0.0);
+	}
+
+	let hit = trace_hi_z(position, r);
+
+	if hit.w == 0.0 {
+		return vec4<f32>(0.0);
+	}
+
+	let size = vec2<f32>(textureDimensions(hi_z_texture));
+	let world = ssr_screen_to_world(hit.xyz, size);
+
+	let previous = screen_space.previous_view_proj * vec4<f32>(world, 1.0);
+	let uv = previous.xy * (vec2<f32>(0.5, -0.5) / previous.w) + 0.5;
+
+	if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+		return vec4<f32>(0.0);
+	}
+
+	let start = ssr_world_to_screen(position, size);
+	let cone_radius = distance(hit.xy, start.xy) * roughness;
+
+	let levels = f32(textureNumLevels(ssr_texture) - 1);
+	let lod = clamp(log2(max(cone_radius, 1.0)), 0.0, levels);
+
+	let exposure = max(screen_space.previous_exposure, 0.0000001);
+	let color = textureSampleLevel(ssr_texture, ssr_sampler, uv, lod).rgb / exposure;
+
+	// fade out towards the edges of the screen and the maximum roughness
+	let edge = min(uv, 1.0 - uv);
+	let edge_fade = saturate(min(edge.x, edge.y) * 10.0);
+	let roughness_fade = saturate((1.0 - roughness / max_roughness) * 4.0);
+
+	return vec4<f32>(color, edge_fade * roughness_fade);
+}
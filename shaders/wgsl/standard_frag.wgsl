@@ -1,6 +1,26 @@
 #include <lumi/mesh.wgsl>
 #include <lumi/pbr.wgsl>
 #include <lumi/standard_material.wgsl>
+#include <lumi/lightmap.wgsl>
+
+// only computes the alpha of the material, to discard fragments in the depth prepass
+@fragment
+fn prepass(mesh: Mesh) {
+	var pbr = default_pbr(mesh);
+
+	pbr.base_color = standard_material.base_color;
+	pbr.alpha_cutoff = standard_material.alpha_cutoff;
+
+#ifdef BASE_COLOR_TEXTURE
+	pbr.base_color *= textureSample(
+		base_color_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+#endif
+
+	pbr_prepass(pbr);
+}
 
 @fragment
 fn fragment(mesh: Mesh) -> @location(0) vec4<f32> {
@@ -11,13 +31,24 @@ fn fragment(mesh: Mesh) -> @location(0) vec4<f32> {
 	pbr.metallic = standard_material.metallic;
 	pbr.roughness = standard_material.roughness;
 	pbr.reflectance = standard_material.reflectance;	
+	pbr.specular_color = standard_material.specular_color;
 	pbr.emissive = standard_material.emissive * standard_material.emissive_factor;
+	pbr.emissive_exposure_compensation = standard_material.emissive_exposure_compensation;
 
 #ifdef CLEARCOAT
 	pbr.clearcoat = standard_material.clearcoat;
 	pbr.clearcoat_roughness = standard_material.clearcoat_roughness;
 #endif
 
+#ifdef SHEEN
+	pbr.sheen_color = standard_material.sheen_color;
+	pbr.sheen_roughness = standard_material.sheen_roughness;
+#endif
+
+#ifdef ANISOTROPY
+	pbr.anisotropy = standard_material.anisotropy;
+#endif
+
 #ifdef THICKNESS
 	pbr.thickness = standard_material.thickness;
 #endif
@@ -61,6 +92,110 @@ fn fragment(mesh: Mesh) -> @location(0) vec4<f32> {
 	pbr.emissive *= emissive_map.rgb;
 #endif
 
+#ifdef SPECULAR_TEXTURE
+	let specular_texture = textureSample(
+		specular_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.reflectance *= sqrt(specular_texture.a);
+#endif
+
+#ifdef SPECULAR_COLOR_TEXTURE
+	let specular_color_texture = textureSample(
+		specular_color_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.specular_color *= specular_color_texture.rgb;
+#endif
+
+#ifdef CLEARCOAT
+#ifdef CLEARCOAT_TEXTURE
+	let clearcoat_texture = textureSample(
+		clearcoat_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.clearcoat *= clearcoat_texture.r;
+#endif
+
+#ifdef CLEARCOAT_ROUGHNESS_TEXTURE
+	let clearcoat_roughness_texture = textureSample(
+		clearcoat_roughness_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.clearcoat_roughness *= clearcoat_roughness_texture.g;
+#endif
+#endif
+
+#ifdef SHEEN
+#ifdef SHEEN_COLOR_TEXTURE
+	let sheen_color_texture = textureSample(
+		sheen_color_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.sheen_color *= sheen_color_texture.rgb;
+#endif
+
+#ifdef SHEEN_ROUGHNESS_TEXTURE
+	let sheen_roughness_texture = textureSample(
+		sheen_roughness_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.sheen_roughness *= sheen_roughness_texture.a;
+#endif
+#endif
+
+#ifdef THICKNESS
+#ifdef THICKNESS_TEXTURE
+	let thickness_texture = textureSample(
+		thickness_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.thickness *= thickness_texture.g;
+#endif
+#endif
+
+#ifdef TRANSMISSION
+#ifdef TRANSMISSION_TEXTURE
+	let transmission_texture = textureSample(
+		transmission_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.transmission *= transmission_texture.r;
+#endif
+#endif
+
+#ifdef OCCLUSION_MAP
+	let occlusion_texture = textureSample(
+		occlusion_texture,
+		base_color_sampler,
+		mesh.uv_0
+	);
+	pbr.occlusion = 1.0 + standard_material.occlusion_strength * (occlusion_texture.r - 1.0);
+#endif
+
+	var tbn = mat3x3<f32>(
+		mesh.w_tangent,
+		mesh.w_bitangent,
+		mesh.w_normal
+	);
+
+#ifdef DOUBLE_SIDED
+	// back faces are shaded as if facing the camera, which flips the whole tangent frame
+	if !mesh.v_front_facing {
+		tbn = tbn * -1.0;
+	}
+#endif
+
+	let w_normal = tbn[2];
+
 #ifdef NORMAL_MAP
 	let normal_map = textureSample(
 		normal_map,
@@ -68,20 +203,14 @@ fn fragment(mesh: Mesh) -> @location(0) vec4<f32> {
 		mesh.uv_0
 	).xyz;
 
-	let tbn = mat3x3<f32>(
-		mesh.w_tangent,
-		mesh.w_bitangent,
-		mesh.w_normal
-	);	
-
 	pbr.normal = normalize(tbn * (normal_map * 2.0 - 1.0));
 #endif
 
 #ifndef NORMAL_MAP
-	pbr.normal = mesh.w_normal;
+	pbr.normal = w_normal;
 #endif
 
-#ifdef CLEACOAT
+#ifdef CLEARCOAT
 #ifdef CLEARCOAT_NORMAL_MAP
 	let clearcoat_normal_map = textureSample(
 		clearcoat_normal_map,
@@ -93,9 +222,39 @@ fn fragment(mesh: Mesh) -> @location(0) vec4<f32> {
 #endif
 
 #ifndef CLEARCOAT_NORMAL_MAP
-	pbr.clearcoat_normal = mesh.w_normal;
+	pbr.clearcoat_normal = w_normal;
 #endif
 #endif
 
+#ifdef ANISOTROPY
+	// the direction of the anisotropy in tangent space, rotated counter clockwise from the tangent
+	let anisotropy_rotation = vec2<f32>(
+		cos(standard_material.anisotropy_rotation),
+		sin(standard_material.anisotropy_rotation)
+	);
+	var anisotropy_direction = anisotropy_rotation;
+
+#ifdef ANISOTROPY_TEXTURE
+	let anisotropy_texture = textureSample(
+		anisotropy_texture,
+		base_color_sampler,
+		mesh.uv_0
+	).rgb;
+
+	let texture_direction = anisotropy_texture.rg * 2.0 - 1.0;
+	anisotropy_direction = mat2x2<f32>(
+		anisotropy_rotation,
+		vec2<f32>(-anisotropy_rotation.y, anisotropy_rotation.x)
+	) * texture_direction;
+	pbr.anisotropy *= anisotropy_texture.b;
+#endif
+
+	pbr.anisotropy_direction = normalize(tbn * vec3<f32>(anisotropy_direction, 0.0));
+#endif
+
+#ifdef LIGHTMAP
+	pbr.lightmap = lightmap_irradiance(mesh.uv_1);
+#endif
+
 	return pbr_light(pbr);
 }
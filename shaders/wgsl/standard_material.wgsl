@@ -15,37 +15,116 @@ struct StandardMaterial {
 	transmission: f32,
 	ior: f32,
 	absorption: vec3<f32>,
+	occlusion_strength: f32,
+	specular_color: vec3<f32>,
+	sheen_roughness: f32,
+	sheen_color: vec3<f32>,
+	anisotropy: f32,
+	anisotropy_rotation: f32,
 }
 
 @group(1) @binding(0)
 var<uniform> standard_material: StandardMaterial;
 
+// maps without a sampler of their own are sampled with `base_color_sampler`
 @group(1) @binding(0)
-var base_color_texture: texture_2d<f32>;
+var base_color_sampler: sampler;
 
+#ifdef BASE_COLOR_TEXTURE
 @group(1) @binding(0)
-var base_color_sampler: sampler;
+var base_color_texture: texture_2d<f32>;
+#endif
 
+#ifdef METALLIC_ROUGHNESS_TEXTURE
 @group(1) @binding(0)
 var metallic_roughness_texture: texture_2d<f32>;
 
 @group(1) @binding(0)
 var metallic_roughness_sampler: sampler;
+#endif
 
+#ifdef NORMAL_MAP
 @group(1) @binding(0)
 var normal_map: texture_2d<f32>;
 
 @group(1) @binding(0)
 var normal_map_sampler: sampler;
+#endif
 
+#ifdef CLEARCOAT
+#ifdef CLEARCOAT_NORMAL_MAP
 @group(1) @binding(0)
 var clearcoat_normal_map: texture_2d<f32>;
 
 @group(1) @binding(0)
 var clearcoat_normal_map_sampler: sampler;
+#endif
+#endif
 
+#ifdef EMISSIVE_MAP
 @group(1) @binding(0)
 var emissive_map: texture_2d<f32>;
 
 @group(1) @binding(0)
 var emissive_map_sampler: sampler;
+#endif
+
+#ifdef OCCLUSION_MAP
+@group(1) @binding(0)
+var occlusion_texture: texture_2d<f32>;
+#endif
+
+#ifdef CLEARCOAT
+#ifdef CLEARCOAT_TEXTURE
+@group(1) @binding(0)
+var clearcoat_texture: texture_2d<f32>;
+#endif
+
+#ifdef CLEARCOAT_ROUGHNESS_TEXTURE
+@group(1) @binding(0)
+var clearcoat_roughness_texture: texture_2d<f32>;
+#endif
+#endif
+
+#ifdef THICKNESS
+#ifdef THICKNESS_TEXTURE
+@group(1) @binding(0)
+var thickness_texture: texture_2d<f32>;
+#endif
+#endif
+
+#ifdef TRANSMISSION
+#ifdef TRANSMISSION_TEXTURE
+@group(1) @binding(0)
+var transmission_texture: texture_2d<f32>;
+#endif
+#endif
+
+#ifdef SPECULAR_TEXTURE
+@group(1) @binding(0)
+var specular_texture: texture_2d<f32>;
+#endif
+
+#ifdef SPECULAR_COLOR_TEXTURE
+@group(1) @binding(0)
+var specular_color_texture: texture_2d<f32>;
+#endif
+
+#ifdef SHEEN
+#ifdef SHEEN_COLOR_TEXTURE
+@group(1) @binding(0)
+var sheen_color_texture: texture_2d<f32>;
+#endif
+
+#ifdef SHEEN_ROUGHNESS_TEXTURE
+@group(1) @binding(0)
+var sheen_roughness_texture: texture_2d<f32>;
+#endif
+#endif
+
+#ifdef ANISOTROPY
+#ifdef ANISOTROPY_TEXTURE
+@group(1) @binding(0)
+var anisotropy_texture: texture_2d<f32>;
+#endif
+#endif
@@ -26,20 +26,27 @@ pub mod prelude {
     pub use lumi_gltf::OpenGltfExt;
     pub use lumi_macro::*;
     pub use lumi_material::{
-        Material, MaterialBundle, MaterialPlugin, Primitive, Primitives, StandardMaterial,
+        AlphaMode, EmissiveLight, Material, MaterialBundle, MaterialPlugin, Primitive, Primitives,
+        StandardMaterial, UnlitMaterial,
     };
     pub use lumi_mesh::{shape, Mesh, MeshId};
     pub use lumi_renderer::{
-        Camera, DirectionalLight, DirectionalLightBundle, Entity, Environment, GlobalTransform,
-        Mut, Orthographic, OrthographicCameraBundle, OwnedPtr, OwnedPtrMut, Perspective,
-        PerspectiveCameraBundle, PointLight, PointLightBundle, Query, QueryState, Renderer,
-        RendererPlugin, Transform, With, Without, World,
+        Atmosphere, AtmosphereSun, Camera, DirectionalLight, DirectionalLightBundle, DiskLight,
+        DiskLightBundle, Entity, Environment, EnvironmentSettings, FogSettings, FogVolume,
+        FogVolumeBundle, GlobalTransform, IesProfile, IrradianceVolume, IrradianceVolumeBundle,
+        Lightmap, Mut, Orthographic, OrthographicCameraBundle, OwnedPtr, OwnedPtrMut, Perspective,
+        PerspectiveCameraBundle, PointLight, PointLightBundle, ProbeVolume, Query, QueryState,
+        RectLight, RectLightBundle, ReflectionProbe, ReflectionProbeBundle, RenderLayers, Renderer,
+        RendererPlugin, ShadowBias, SpotLight, SpotLightBundle, Transform, TubeLight,
+        TubeLightBundle, With, Without, World,
     };
     pub use lumi_shader::{DefaultShader, Shader, ShaderDefs, ShaderRef};
     pub use lumi_util::math::*;
 }
 
-use material::{MaterialPlugin, Primitive, Primitives, StandardMaterial};
+use material::{
+    EmissiveLightPlugin, MaterialPlugin, Primitive, Primitives, StandardMaterial, UnlitMaterial,
+};
 use renderer::{CoreExtractPlugin, CorePlugin, ExtractMeshPlugin, Renderer, RendererPlugin};
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -51,7 +58,11 @@ impl RendererPlugin for DefaultPlugin {
             .add_plugin(CorePlugin)
             .add_plugin(CoreExtractPlugin)
             .add_plugin(MaterialPlugin::<StandardMaterial>::default())
+            .add_plugin(EmissiveLightPlugin)
+            .add_plugin(MaterialPlugin::<UnlitMaterial>::default())
             .add_plugin(ExtractMeshPlugin::<Primitive>::default())
-            .add_plugin(ExtractMeshPlugin::<Primitives>::default());
+            .add_plugin(ExtractMeshPlugin::<Primitives>::default())
+            .add_plugin(ExtractMeshPlugin::<Primitive<UnlitMaterial>>::default())
+            .add_plugin(ExtractMeshPlugin::<Primitives<UnlitMaterial>>::default());
     }
 }